@@ -0,0 +1,236 @@
+//! Local background and noise estimation
+use crate::{
+    frame::{component::Pixel, BitMap, Frame},
+    Error,
+};
+
+/// Sigma clipping factor, used to reject stars from the tile statistics
+const CLIPPING_SIGMA: f64 = 3.0;
+
+/// Number of sigma clipping iterations
+const CLIPPING_ITERATIONS: usize = 5;
+
+/// [Tile] gathers the robust background statistics of one mesh cell
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Tile {
+    /// Background level (sigma clipped mean), at native bit depth
+    pub mean: f32,
+    /// Background noise (sigma clipped std deviation), at native bit depth
+    pub rms: f32,
+}
+
+impl Tile {
+    /// Returns true if this [Tile] looks cloudy: its level or noise
+    /// exceed the reference (typically [Background::median]) by more
+    /// than k reference std deviations
+    pub fn is_cloudy(&self, reference: &Tile, k: f64) -> bool {
+        let (mean, rms) = (reference.mean as f64, reference.rms as f64);
+        self.mean as f64 > mean + k * rms || self.rms as f64 > rms + k * rms
+    }
+}
+
+/// Returns the median of n values, without allocation
+fn median(n: usize, value: impl Fn(usize) -> f32) -> f32 {
+    (0..n)
+        .map(&value)
+        .find(|candidate| {
+            let below = (0..n).filter(|i| value(*i) < *candidate).count();
+            let equal = (0..n).filter(|i| value(*i) == *candidate).count();
+            below <= n / 2 && n / 2 < below + equal
+        })
+        .unwrap_or_default()
+}
+
+/// [Background] is a meshed estimate of the sky background and noise.
+/// Each tile is measured with sigma clipping, so stars and hot pixels
+/// do not bias it, and the statistics are bilinearly interpolated
+/// between tile centers. This follows twilight gradients, moonlight
+/// and light pollution, where a single global threshold does not.
+/// Tiles are stored within a caller provided buffer: use
+/// [Background::tiles] to size it.
+#[derive(Debug)]
+pub struct Background<'a> {
+    /// (x, y) origin within the full sensor frame
+    origin: (u16, u16),
+    width: u16,
+    height: u16,
+    tile_size: u16,
+    cols: u16,
+    rows: u16,
+    tiles: &'a mut [Tile],
+}
+
+impl<'a> Background<'a> {
+    /// Returns number of [Tile]s required to mesh a (width, height)
+    /// frame with square tiles of tile_size pixels
+    pub const fn tiles(width: u16, height: u16, tile_size: u16) -> usize {
+        if tile_size == 0 {
+            return 0;
+        }
+        width.div_ceil(tile_size) as usize * height.div_ceil(tile_size) as usize
+    }
+
+    /// Estimates the [Background] of given [Frame], with square tiles of
+    /// tile_size pixels. Tile size should be several times the star size.
+    /// Pixels ignored by the [Frame] (bad pixels) are not taken into account.
+    /// Fails with [Error::VideoDimensionError] on empty [Frame], null tile size
+    /// or if tiles is too short.
+    pub fn estimate<const XY: usize, P: Pixel>(
+        frame: &Frame<'_, XY, P>,
+        tile_size: u16,
+        tiles: &'a mut [Tile],
+    ) -> Result<Self, Error> {
+        let (width, height) = (frame.width(), frame.height());
+        if width == 0
+            || height == 0
+            || tile_size == 0
+            || tiles.len() < Self::tiles(width, height, tile_size)
+        {
+            return Err(Error::VideoDimensionError);
+        }
+
+        let (cols, rows) = (width.div_ceil(tile_size), height.div_ceil(tile_size));
+
+        for row in 0..rows {
+            for col in 0..cols {
+                let (x, y) = (col * tile_size, row * tile_size);
+                let w = tile_size.min(width - x);
+                let h = tile_size.min(height - y);
+                let (mean, rms) = frame
+                    .roi(x, y, w, h)?
+                    .luma_clipped_stats(CLIPPING_SIGMA, CLIPPING_ITERATIONS);
+                tiles[row as usize * cols as usize + col as usize] = Tile {
+                    mean: mean as f32,
+                    rms: rms as f32,
+                };
+            }
+        }
+
+        Ok(Self {
+            origin: frame.origin(),
+            width,
+            height,
+            tile_size,
+            cols,
+            rows,
+            tiles,
+        })
+    }
+
+    /// Returns (x, y) origin of this [Background], within the full sensor frame
+    pub fn origin(&self) -> (u16, u16) {
+        self.origin
+    }
+
+    /// Returns (width, height) covered by this [Background]
+    pub fn dimensions(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    /// Returns (columns, rows) of the tile mesh
+    pub fn grid(&self) -> (u16, u16) {
+        (self.cols, self.rows)
+    }
+
+    /// Returns [Tile] statistics at (col, row) in the mesh
+    pub fn tile(&self, col: u16, row: u16) -> Option<Tile> {
+        if col < self.cols && row < self.rows {
+            Some(self.tiles[row as usize * self.cols as usize + col as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the median [Tile] statistics (level and noise), over the mesh
+    pub fn median(&self) -> Tile {
+        let n = self.cols as usize * self.rows as usize;
+        Tile {
+            mean: median(n, |i| self.tiles[i].mean),
+            rms: median(n, |i| self.tiles[i].rms),
+        }
+    }
+
+    /// Returns (lower tile index, upper tile index, weight) interpolating
+    /// coordinate along an axis of n tiles
+    fn axis(&self, coordinate: u16, n: u16) -> (u16, u16, f64) {
+        let position = (coordinate as f64 + 0.5) / self.tile_size as f64 - 0.5;
+        let position = position.clamp(0.0, (n - 1) as f64);
+        let lower = position as u16;
+        let upper = (lower + 1).min(n - 1);
+        (lower, upper, position - lower as f64)
+    }
+
+    /// Bilinearly interpolates the [Tile] statistics at (x, y),
+    /// in full sensor frame coordinates
+    pub(crate) fn interpolate(&self, x: u16, y: u16) -> Tile {
+        let x = x.saturating_sub(self.origin.0).min(self.width - 1);
+        let y = y.saturating_sub(self.origin.1).min(self.height - 1);
+        let (c0, c1, tx) = self.axis(x, self.cols);
+        let (r0, r1, ty) = self.axis(y, self.rows);
+
+        let tile = |col, row| self.tiles[row as usize * self.cols as usize + col as usize];
+        let (t00, t10, t01, t11) = (tile(c0, r0), tile(c1, r0), tile(c0, r1), tile(c1, r1));
+
+        let lerp = |a: f32, b: f32, t: f64| a as f64 + (b as f64 - a as f64) * t;
+        let mean = lerp(t00.mean, t10.mean, tx) * (1.0 - ty) + lerp(t01.mean, t11.mean, tx) * ty;
+        let rms = lerp(t00.rms, t10.rms, tx) * (1.0 - ty) + lerp(t01.rms, t11.rms, tx) * ty;
+
+        Tile {
+            mean: mean as f32,
+            rms: rms as f32,
+        }
+    }
+
+    /// Returns interpolated background level at (x, y), at native bit depth
+    pub fn mean(&self, x: u16, y: u16) -> f64 {
+        self.interpolate(x, y).mean as f64
+    }
+
+    /// Returns interpolated background noise at (x, y), at native bit depth
+    pub fn rms(&self, x: u16, y: u16) -> f64 {
+        self.interpolate(x, y).rms as f64
+    }
+
+    /// Returns local detection threshold at (x, y): background + k * noise
+    pub fn threshold(&self, x: u16, y: u16, k: f64) -> f64 {
+        let tile = self.interpolate(x, y);
+        tile.mean as f64 + k * tile.rms as f64
+    }
+
+    /// Writes the background subtracted [Frame] into buf.
+    /// Samples are saturated to the [Pixel] dynamic range. The subtracted
+    /// [Frame] retains the settings of the [Frame], but its [Background].
+    pub fn subtract<'b, const XY: usize, P: Pixel>(
+        &self,
+        frame: &Frame<'b, XY, P>,
+        buf: &'b mut [P; XY],
+    ) -> Result<Frame<'b, XY, P>, Error> {
+        let (width, height) = (frame.width(), frame.height());
+        let (x0, y0) = frame.origin();
+
+        if width as usize * height as usize > XY
+            || x0 < self.origin.0
+            || y0 < self.origin.1
+            || x0 as usize + width as usize > self.origin.0 as usize + self.width as usize
+            || y0 as usize + height as usize > self.origin.1 as usize + self.height as usize
+        {
+            return Err(Error::VideoDimensionError);
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                let luma = frame.get(x, y).map(|p| p.luma()).unwrap_or_default();
+                let value = luma as f64 - self.mean(x0 + x, y0 + y);
+                buf[y as usize * width as usize + x as usize] =
+                    P::from_luma(value.round().clamp(0.0, frame.max_luma() as f64) as u16);
+            }
+        }
+
+        let bitmap = BitMap::from_buffer(width, height, buf)?
+            .with_bit_depth(frame.bitmap().bit_depth());
+        let mut subtracted = frame.with_bitmap(bitmap);
+        subtracted.threshold = frame.threshold;
+        subtracted.background = None;
+        Ok(subtracted)
+    }
+}
@@ -0,0 +1,206 @@
+//! Hot and dead pixels
+use crate::{
+    frame::{calibration::MasterFrame, component::Pixel, mask::Mask, BitMap, Frame},
+    Error,
+};
+
+/// Number of sigma clipping iterations, when estimating
+/// the background statistics of a dark frame
+const CLIPPING_ITERATIONS: usize = 5;
+
+/// [BadPixelMap] flags the hot, stuck and dead pixels of a sensor, in
+/// full sensor frame coordinates. Attach it to a [Frame] with
+/// [Frame::with_bad_pixels] so flagged pixels are ignored by the detection,
+/// or replace them with [BadPixelMap::interpolate].
+#[derive(Debug)]
+pub struct BadPixelMap<'a> {
+    mask: Mask<'a>,
+}
+
+impl<'a> BadPixelMap<'a> {
+    /// Builds an empty (width, height) [BadPixelMap] within bits.
+    /// Use [Mask::words] to size the buffer.
+    pub fn new(width: u16, height: u16, bits: &'a mut [u32]) -> Result<Self, Error> {
+        Ok(Self {
+            mask: Mask::new(width, height, bits)?,
+        })
+    }
+
+    /// Builds a [BadPixelMap] from an existing [Mask]
+    pub fn from_mask(mask: Mask<'a>) -> Self {
+        Self { mask }
+    }
+
+    /// Builds a [BadPixelMap] from a dark frame (lens capped capture):
+    /// pixels brighter than the background by more than k std deviations
+    /// are flagged as hot.
+    pub fn from_dark<const XY: usize, P: Pixel>(
+        dark: &Frame<'_, XY, P>,
+        k: f64,
+        bits: &'a mut [u32],
+    ) -> Result<Self, Error> {
+        let mut map = Self::new(dark.width(), dark.height(), bits)?;
+        let (mean, stddev) = dark.luma_clipped_stats(k, CLIPPING_ITERATIONS);
+        let threshold = mean + k * stddev;
+
+        for y in 0..dark.height() {
+            for x in 0..dark.width() {
+                if let Some(p) = dark.get(x, y) {
+                    if p.luma() as f64 > threshold {
+                        map.flag(x, y);
+                    }
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    /// Builds a [BadPixelMap] from a normalized master flat:
+    /// pixels whose gain is below min_gain are flagged as dead.
+    pub fn from_flat(
+        flat: &MasterFrame<'_>,
+        min_gain: f32,
+        bits: &'a mut [u32],
+    ) -> Result<Self, Error> {
+        let (width, height) = flat.dimensions();
+        let mut map = Self::new(width, height, bits)?;
+
+        for y in 0..height {
+            for x in 0..width {
+                if flat.get(x, y).is_some_and(|gain| gain < min_gain) {
+                    map.flag(x, y);
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    /// Builds a [BadPixelMap] from N sky frames: stars move from one frame to
+    /// another, while stuck pixels remain bright (more than k std deviations
+    /// above the background) and constant (within tolerance) in all frames.
+    pub fn from_fixed_pixels<const N: usize, const XY: usize, P: Pixel>(
+        frames: &[Frame<'_, XY, P>; N],
+        tolerance: u16,
+        k: f64,
+        bits: &'a mut [u32],
+    ) -> Result<Self, Error> {
+        let (width, height) = match frames.first() {
+            Some(frame) => (frame.width(), frame.height()),
+            None => return Err(Error::VideoDimensionError),
+        };
+        if frames
+            .iter()
+            .any(|f| f.width() != width || f.height() != height)
+        {
+            return Err(Error::VideoDimensionError);
+        }
+
+        let mut thresholds = [0.0_f64; N];
+        for (threshold, frame) in thresholds.iter_mut().zip(frames.iter()) {
+            let (mean, stddev) = frame.luma_clipped_stats(k, CLIPPING_ITERATIONS);
+            *threshold = mean + k * stddev;
+        }
+
+        let mut map = Self::new(width, height, bits)?;
+
+        for y in 0..height {
+            for x in 0..width {
+                let (mut min, mut max) = (u16::MAX, 0);
+                let mut bright = true;
+                for (frame, threshold) in frames.iter().zip(thresholds.iter()) {
+                    let luma = frame.get(x, y).map(|p| p.luma()).unwrap_or_default();
+                    min = min.min(luma);
+                    max = max.max(luma);
+                    bright &= luma as f64 > *threshold;
+                }
+                if bright && max - min <= tolerance {
+                    map.flag(x, y);
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    /// Flags (x, y) pixel
+    pub fn flag(&mut self, x: u16, y: u16) {
+        self.mask.set(x, y, true);
+    }
+
+    /// Returns true if (x, y) pixel is flagged
+    pub fn is_bad(&self, x: u16, y: u16) -> bool {
+        self.mask.get(x, y)
+    }
+
+    /// Returns number of flagged pixels
+    pub fn count(&self) -> usize {
+        self.mask.count()
+    }
+
+    /// Returns reference to the underlying [Mask]
+    pub fn mask(&self) -> &Mask<'a> {
+        &self.mask
+    }
+
+    /// Writes a copy of given [Frame] into buf, where flagged pixels are replaced
+    /// by the mean of their valid neighbors (8-connectivity).
+    /// Flagged pixels without valid neighbors are left untouched.
+    /// The interpolated [Frame] retains the settings of the [Frame].
+    pub fn interpolate<'b, const XY: usize, P: Pixel>(
+        &self,
+        frame: &Frame<'b, XY, P>,
+        buf: &'b mut [P; XY],
+    ) -> Result<Frame<'b, XY, P>, Error> {
+        let (width, height) = (frame.width(), frame.height());
+
+        if width as usize * height as usize > XY {
+            return Err(Error::VideoDimensionError);
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                let mut pixel = frame.get(x, y).copied().unwrap_or_default();
+
+                let (sx, sy) = frame.sensor_coordinates(x, y);
+                if self.is_bad(sx, sy) {
+                    let (mut acc, mut n) = (0_u32, 0_u32);
+                    for (dx, dy) in [
+                        (-1, -1),
+                        (0, -1),
+                        (1, -1),
+                        (-1, 0),
+                        (1, 0),
+                        (-1, 1),
+                        (0, 1),
+                        (1, 1),
+                    ] {
+                        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                        if nx < 0 || ny < 0 {
+                            continue;
+                        }
+                        let (nx, ny) = (nx as u16, ny as u16);
+                        let (sx, sy) = frame.sensor_coordinates(nx, ny);
+                        if self.is_bad(sx, sy) {
+                            continue;
+                        }
+                        if let Some(p) = frame.get(nx, ny) {
+                            acc += p.luma() as u32;
+                            n += 1;
+                        }
+                    }
+                    if let Some(mean) = (acc + n / 2).checked_div(n) {
+                        pixel = P::from_luma(mean as u16);
+                    }
+                }
+
+                buf[y as usize * width as usize + x as usize] = pixel;
+            }
+        }
+
+        let bitmap = BitMap::from_buffer(width, height, buf)?
+            .with_bit_depth(frame.bitmap().bit_depth());
+        let mut interpolated = frame.with_bitmap(bitmap);
+        interpolated.threshold = frame.threshold;
+        interpolated.saturation = frame.saturation;
+        Ok(interpolated)
+    }
+}
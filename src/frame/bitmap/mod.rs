@@ -1,73 +1,353 @@
-use crate::frame::UnderlyingComponent;
+use crate::{
+    frame::{
+        component::{
+            bayer::BayerPattern, packed::PackedFormat, Gray16, Pixel, Rgb8, UnderlyingComponent,
+        },
+        pyramid::Binning,
+    },
+    Error,
+};
 
 #[cfg(test)]
 mod test;
 
-pub struct BitMap<'a, const XY: usize> {
-    width: u16,
-    height: u16,
-    map: &'a [UnderlyingComponent; XY],
+#[cfg(feature = "std")]
+mod owned;
+
+mod yuv;
+pub use yuv::{YuvBitMap, YuvFormat};
+
+#[cfg(feature = "std")]
+pub use owned::OwnedBitMap;
+
+/// [Layout] describes how (width, height) components are
+/// stored within a linear buffer.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Layout {
+    /// Width (in pixels)
+    pub width: u16,
+    /// Height (in pixels)
+    pub height: u16,
+    /// Index of the (0, 0) component within the buffer
+    pub offset: usize,
+    /// Number of components between two consecutive rows
+    pub stride: usize,
+    /// Number of components between two consecutive pixels of a row
+    pub step: usize,
+}
+
+impl Layout {
+    /// Builds a contiguous [Layout], where rows follow one another.
+    pub fn contiguous(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            offset: 0,
+            stride: width as usize,
+            step: 1,
+        }
+    }
+
+    /// Returns buffer index of (x, y) component, if it lies within this [Layout].
+    pub fn index(&self, x: u16, y: u16) -> Option<usize> {
+        if x < self.width && y < self.height {
+            Some(self.offset + y as usize * self.stride + x as usize * self.step)
+        } else {
+            None
+        }
+    }
+
+    /// Returns minimal buffer size (in components) to store this [Layout].
+    pub fn required_len(&self) -> usize {
+        if self.width == 0 || self.height == 0 {
+            self.offset
+        } else {
+            self.offset
+                + (self.height as usize - 1) * self.stride
+                + (self.width as usize - 1) * self.step
+                + 1
+        }
+    }
+
+    /// Returns the (x, y, width, height) sub [Layout]
+    pub fn roi(&self, x: u16, y: u16, width: u16, height: u16) -> Result<Self, Error> {
+        if x as u32 + width as u32 > self.width as u32
+            || y as u32 + height as u32 > self.height as u32
+        {
+            return Err(Error::VideoDimensionError);
+        }
+        Ok(Self {
+            width,
+            height,
+            offset: self.offset + y as usize * self.stride + x as usize * self.step,
+            stride: self.stride,
+            step: self.step,
+        })
+    }
+}
+
+/// [BitMap] is a borrowed view over (width, height) video components.
+/// It may either describe an entire buffer, or a region of interest
+/// within a larger buffer (see [BitMap::roi]).
+/// ## Generics:
+/// - XY: maximal total size (in pixels)
+/// - P: [Pixel] format. Prefer a typed format (like [Gray8](crate::frame::component::Gray8))
+///   when known at build time, [UnderlyingComponent] is the dynamic fallback.
+pub struct BitMap<'a, const XY: usize, P: Pixel = UnderlyingComponent> {
+    layout: Layout,
+    map: &'a [P],
+    /// Bit depth of the stored luminances
+    bit_depth: u8,
 }
 
-pub struct BitMapIter<'a, const XY: usize> {
+impl<const XY: usize, P: Pixel> Clone for BitMap<'_, XY, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<const XY: usize, P: Pixel> Copy for BitMap<'_, XY, P> {}
+
+/// [BitMapIter] iterates a [BitMap] row after row
+pub struct BitMapIter<'a, P: Pixel = UnderlyingComponent> {
     x_pointer: u16,
     y_pointer: u16,
-    bitmap: &'a BitMap<'a, XY>,
+    layout: Layout,
+    map: &'a [P],
 }
 
-impl<'a, const XY: usize> Iterator for BitMapIter<'a, XY> {
-    type Item = &'a UnderlyingComponent;
+impl<'a, P: Pixel> BitMapIter<'a, P> {
+    pub(crate) fn new(layout: Layout, map: &'a [P]) -> Self {
+        Self {
+            x_pointer: 0,
+            y_pointer: 0,
+            layout,
+            map,
+        }
+    }
+}
+
+impl<'a, P: Pixel> Iterator for BitMapIter<'a, P> {
+    type Item = &'a P;
     fn next(&mut self) -> Option<Self::Item> {
         let mut ret = Option::<Self::Item>::None;
-        if self.x_pointer < self.bitmap.width {
-            if self.y_pointer < self.bitmap.height {
-                ret = self.bitmap.get(self.x_pointer, self.y_pointer);
-                self.x_pointer += 1;        
-                if self.x_pointer == self.bitmap.width {
-                    self.x_pointer = 0;
-                    self.y_pointer += 1;
-                }
+        if self.x_pointer < self.layout.width && self.y_pointer < self.layout.height {
+            ret = get(&self.layout, self.map, self.x_pointer, self.y_pointer);
+            self.x_pointer += 1;
+            if self.x_pointer == self.layout.width {
+                self.x_pointer = 0;
+                self.y_pointer += 1;
             }
         }
         ret
     }
 }
 
-impl<'a, const XY: usize> BitMap<'a, XY> {
-    
-    pub fn from_slice(width: u16, height: u16, slice: &'a [UnderlyingComponent; XY]) -> Self {
-        Self {
+/// Indexes a [Layout]ed map, shared by all [BitMap] flavors.
+pub(crate) fn get<'a, P: Pixel>(
+    layout: &Layout,
+    map: &'a [P],
+    x: u16,
+    y: u16,
+) -> Option<&'a P> {
+    map.get(layout.index(x, y)?)
+}
+
+impl<'a, const XY: usize, P: Pixel> BitMap<'a, XY, P> {
+    /// Builds a contiguous (width, height) [BitMap] over given slice.
+    /// Fails with [Error::VideoDimensionError] if width * height exceeds XY.
+    pub fn from_slice(width: u16, height: u16, slice: &'a [P; XY]) -> Result<Self, Error> {
+        let layout = Layout::contiguous(width, height);
+        if layout.required_len() > XY {
+            return Err(Error::VideoDimensionError);
+        }
+        Ok(Self::from_raw(layout, slice))
+    }
+
+    /// Builds a [BitMap] from a slice where consecutive rows
+    /// are separated by stride components (stride >= width).
+    /// Typically used with padded video buffers.
+    pub fn from_strided_slice(
+        width: u16,
+        height: u16,
+        stride: usize,
+        slice: &'a [P; XY],
+    ) -> Result<Self, Error> {
+        let layout = Layout {
             width,
             height,
-            map: slice,
+            stride,
+            offset: 0,
+            step: 1,
+        };
+        if stride < width as usize || layout.required_len() > XY {
+            return Err(Error::VideoDimensionError);
         }
+        Ok(Self::from_raw(layout, slice))
     }
 
-    // #[cfg(feature = "std")]
-    // pub fn from_vec(width: usize, height: usize, vec: Vec<UnderlyingComponent>) -> Self {
-    //     Self {
-    //         width,
-    //         height,
-    //         map: vec.clone(),
-    //     }
-    // }
+    /// Builds a contiguous (width, height) [BitMap] over the first
+    /// width * height components of a processing buffer.
+    pub(crate) fn from_buffer(width: u16, height: u16, buf: &'a [P]) -> Result<Self, Error> {
+        let layout = Layout::contiguous(width, height);
+        let size = layout.required_len();
+        if size > XY || size > buf.len() {
+            return Err(Error::VideoDimensionError);
+        }
+        Ok(Self::from_raw(layout, buf))
+    }
 
-    pub fn get(&self, x: u16, y: u16) -> Option<&UnderlyingComponent> {
-        if x < self.width && y < self.height {
-            let (x, y) = (x as u32, y as u32);
-            self.map.get((x * y + x) as usize)
-        } else {
-            None
+    /// Builds a [BitMap] view over a runtime sized slice.
+    /// [Layout] is verified by the caller.
+    pub(crate) fn from_raw(layout: Layout, map: &'a [P]) -> Self {
+        Self {
+            layout,
+            map,
+            bit_depth: P::BIT_DEPTH,
         }
     }
 
+    /// Returns a copy of this [BitMap] which luminances span bit_depth bits,
+    /// instead of [Pixel::BIT_DEPTH]. Typically used with 10 or 12 bit sensor
+    /// data stored as [Gray16]. bit_depth is clamped to 8..=[Pixel::BIT_DEPTH].
+    pub fn with_bit_depth(&self, bit_depth: u8) -> Self {
+        let mut s = *self;
+        s.bit_depth = bit_depth.clamp(8, P::BIT_DEPTH);
+        s
+    }
+
+    /// Returns bit depth of the luminances of this [BitMap]
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    /// Returns maximal luminance of this [BitMap], at native bit depth
+    pub fn max_luma(&self) -> u16 {
+        ((1_u32 << self.bit_depth) - 1) as u16
+    }
+
+    /// Returns (x, y) luminance converted to Gray scale 8bit,
+    /// according to the bit depth of this [BitMap]
+    pub fn gray8(&self, x: u16, y: u16) -> Option<u8> {
+        self.get(x, y).map(|p| p.to_gray8_at(self.bit_depth))
+    }
+
+    /// Returns width of this [BitMap] (in pixels)
+    pub fn width(&self) -> u16 {
+        self.layout.width
+    }
+
+    /// Returns height of this [BitMap] (in pixels)
+    pub fn height(&self) -> u16 {
+        self.layout.height
+    }
+
+    /// Returns number of components between two consecutive rows
+    pub fn stride(&self) -> usize {
+        self.layout.stride
+    }
+
+    pub fn get(&self, x: u16, y: u16) -> Option<&'a P> {
+        get(&self.layout, self.map, x, y)
+    }
+
     /// Builds a new [BitMapIter] to iterate this [BitMap]
-    pub fn iter(&'a self) -> BitMapIter<'a, XY> {
-        BitMapIter {
-            x_pointer: 0,
-            y_pointer: 0,
-            bitmap: self,
+    pub fn iter(&self) -> BitMapIter<'a, P> {
+        BitMapIter::new(self.layout, self.map)
+    }
+
+    /// Returns a zero-copy region of interest of this [BitMap], with
+    /// (x, y) origin and (width, height) dimensions.
+    /// Coordinates of the returned [BitMap] are relative to its origin.
+    /// Fails with [Error::VideoDimensionError] if the region exceeds this [BitMap].
+    pub fn roi(&self, x: u16, y: u16, width: u16, height: u16) -> Result<Self, Error> {
+        let layout = self.layout.roi(x, y, width, height)?;
+        Ok(Self { layout, ..*self })
+    }
+
+    /// Returns a zero-copy decimated view of this [BitMap], keeping one
+    /// pixel out of factor, along both axes. Fastest reduction, without
+    /// noise reduction: faint stars may fall between the kept pixels.
+    /// Fails with [Error::VideoDimensionError] if factor is null.
+    pub fn decimate(&self, factor: u16) -> Result<Self, Error> {
+        if factor == 0 {
+            return Err(Error::VideoDimensionError);
         }
+        let layout = Layout {
+            width: self.layout.width.div_ceil(factor),
+            height: self.layout.height.div_ceil(factor),
+            offset: self.layout.offset,
+            stride: self.layout.stride * factor as usize,
+            step: self.layout.step * factor as usize,
+        };
+        Ok(Self { layout, ..*self })
+    }
+
+    /// Bins the given [BitMap] into buf, averaging each cell of [Binning] pixels.
+    /// The returned [BitMap] has the dimensions divided by the [Binning] factor
+    /// (incomplete cells at the edges are discarded), at native bit depth.
+    pub fn from_binning(
+        bitmap: &BitMap<'_, XY, P>,
+        binning: Binning,
+        buf: &'a mut [P; XY],
+    ) -> Result<Self, Error> {
+        let (width, height) = binning.bin(
+            bitmap.width(),
+            bitmap.height(),
+            |x, y| bitmap.get(x, y).map(|p| p.luma()),
+            buf,
+        )?;
+        Ok(Self::from_raw(Layout::contiguous(width, height), buf).with_bit_depth(bitmap.bit_depth))
+    }
+
+    /// Demosaics a Bayer RAW mosaic with luminance-only 2x2 binning,
+    /// into buf. The returned [BitMap] has half the mosaic dimensions,
+    /// is expressed at native bit depth and is ready for star detection.
+    /// The [BayerPattern] applies to the (0, 0) photosite of the mosaic.
+    pub fn from_bayer_binning(
+        mosaic: &BitMap<'_, XY, P>,
+        pattern: BayerPattern,
+        buf: &'a mut [P; XY],
+    ) -> Result<Self, Error> {
+        let (width, height) = pattern.luma_binning(mosaic, buf)?;
+        Ok(Self::from_raw(Layout::contiguous(width, height), buf).with_bit_depth(mosaic.bit_depth))
+    }
+}
+
+impl<'a, const XY: usize> BitMap<'a, XY, Rgb8> {
+    /// Demosaics a Bayer RAW mosaic with bilinear full color
+    /// reconstruction, into buf. The returned [BitMap] has the mosaic dimensions.
+    /// The [BayerPattern] applies to the (0, 0) photosite of the mosaic.
+    pub fn from_bayer_bilinear<P: Pixel>(
+        mosaic: &BitMap<'_, XY, P>,
+        pattern: BayerPattern,
+        buf: &'a mut [Rgb8; XY],
+    ) -> Result<Self, Error> {
+        pattern.bilinear(mosaic, buf)?;
+        Ok(Self::from_raw(
+            Layout::contiguous(mosaic.width(), mosaic.height()),
+            buf,
+        ))
     }
+}
 
-}
\ No newline at end of file
+impl<'a, const XY: usize> BitMap<'a, XY, Gray16> {
+    /// Unpacks (width, height) packed sensor data into buf,
+    /// and returns the resulting [BitMap], at native bit depth
+    /// (see [PackedFormat::bit_depth]).
+    pub fn from_packed(
+        width: u16,
+        height: u16,
+        format: PackedFormat,
+        src: &[u8],
+        buf: &'a mut [Gray16; XY],
+    ) -> Result<Self, Error> {
+        let layout = Layout::contiguous(width, height);
+        let size = layout.required_len();
+        if size > XY {
+            return Err(Error::VideoDimensionError);
+        }
+        format.unpack(src, &mut buf[..size])?;
+        Ok(Self::from_raw(layout, buf).with_bit_depth(format.bit_depth()))
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    frame::{
+        bitmap::{get, BitMap, BitMapIter, Layout},
+        component::{packed::PackedFormat, Gray16, Pixel, UnderlyingComponent},
+        Frame,
+    },
+    Error,
+};
+
+/// [OwnedBitMap] is a heap backed [BitMap], which dimensions
+/// are only known at runtime. Typically used when loading frames from disk
+/// or replay logs. Use [OwnedBitMap::as_bitmap] or [OwnedBitMap::as_frame]
+/// to obtain a borrowed view, compatible with the rest of the library.
+#[derive(Debug, Clone)]
+pub struct OwnedBitMap<P: Pixel = UnderlyingComponent> {
+    layout: Layout,
+    map: Vec<P>,
+    /// Bit depth of the stored luminances
+    bit_depth: u8,
+}
+
+impl<P: Pixel> OwnedBitMap<P> {
+    /// Allocates a new (width, height) [OwnedBitMap], filled with default components.
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            layout: Layout::contiguous(width, height),
+            map: vec![P::default(); width as usize * height as usize],
+            bit_depth: P::BIT_DEPTH,
+        }
+    }
+
+    /// Builds an [OwnedBitMap] from a [Vec] of components, stored row after row.
+    /// The [Vec] must contain exactly width * height components.
+    pub fn from_vec(width: u16, height: u16, vec: Vec<P>) -> Result<Self, Error> {
+        if vec.len() != width as usize * height as usize {
+            return Err(Error::VideoDimensionError);
+        }
+        Ok(Self {
+            layout: Layout::contiguous(width, height),
+            map: vec,
+            bit_depth: P::BIT_DEPTH,
+        })
+    }
+
+    /// Returns width of this [OwnedBitMap] (in pixels)
+    pub fn width(&self) -> u16 {
+        self.layout.width
+    }
+
+    /// Returns height of this [OwnedBitMap] (in pixels)
+    pub fn height(&self) -> u16 {
+        self.layout.height
+    }
+
+    /// Returns bit depth of the luminances of this [OwnedBitMap],
+    /// see [BitMap::with_bit_depth]
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    /// Returns total number of components
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if this [OwnedBitMap] is empty
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Obtain reference to a component
+    pub fn get(&self, x: u16, y: u16) -> Option<&P> {
+        get(&self.layout, &self.map, x, y)
+    }
+
+    /// Builds a new [BitMapIter] to iterate this [OwnedBitMap]
+    pub fn iter(&self) -> BitMapIter<'_, P> {
+        BitMapIter::new(self.layout, &self.map)
+    }
+
+    /// Returns mutable access to the underlying components, stored row after row.
+    pub fn as_mut_slice(&mut self) -> &mut [P] {
+        &mut self.map
+    }
+
+    /// Returns a borrowed [BitMap] view. Fails if this [OwnedBitMap]
+    /// does not fit within XY pixels.
+    pub fn as_bitmap<const XY: usize>(&self) -> Result<BitMap<'_, XY, P>, Error> {
+        if self.map.len() > XY {
+            return Err(Error::VideoDimensionError);
+        }
+        Ok(BitMap::from_raw(self.layout, &self.map).with_bit_depth(self.bit_depth))
+    }
+
+    /// Returns a borrowed [Frame] view. Fails if this [OwnedBitMap]
+    /// does not fit within XY pixels.
+    pub fn as_frame<const XY: usize>(&self) -> Result<Frame<'_, XY, P>, Error> {
+        let bitmap = self.as_bitmap::<XY>()?;
+        Frame::new(
+            self.layout.width as usize,
+            self.layout.height as usize,
+            bitmap,
+        )
+    }
+}
+
+impl OwnedBitMap<Gray16> {
+    /// Unpacks (width, height) packed sensor data into a new
+    /// [OwnedBitMap], at native bit depth (see [PackedFormat::bit_depth]).
+    pub fn from_packed(
+        width: u16,
+        height: u16,
+        format: PackedFormat,
+        src: &[u8],
+    ) -> Result<Self, Error> {
+        let mut owned = Self::new(width, height);
+        format.unpack(src, &mut owned.map)?;
+        owned.bit_depth = format.bit_depth();
+        Ok(owned)
+    }
+}
@@ -1,26 +1,198 @@
+use crate::{
+    frame::{
+        component::{Gray8, Pixel, Rgb8},
+        pyramid::Binning,
+        BitMap, Frame, UnderlyingComponent,
+    },
+    Error,
+};
 
-use crate::tracker::Star;
+#[cfg(feature = "std")]
+use crate::frame::OwnedBitMap;
 
-use crate::frame::{BitMap, UnderlyingComponent};
+#[test]
+fn borrowed_bitmap() {
+    let map = [UnderlyingComponent::gray8(0); 12];
+    let bitmap = BitMap::from_slice(4, 3, &map).unwrap();
+    assert_eq!(bitmap.width(), 4);
+    assert_eq!(bitmap.height(), 3);
+    assert_eq!(bitmap.iter().count(), 12);
+    assert!(bitmap.get(4, 0).is_none());
+    assert!(bitmap.get(0, 3).is_none());
+}
 
-use image::open as image_open;
-use image::DynamicImage;
-use image::EncodableLayout;
+#[test]
+fn frame_dimensions() {
+    let map = [UnderlyingComponent::gray8(0); 12];
+    let bitmap = BitMap::from_slice(4, 3, &map).unwrap();
+    assert_eq!(Frame::new(4, 3, bitmap).unwrap().area(), 12);
+    assert_eq!(
+        Frame::new(3, 4, bitmap).err(),
+        Some(Error::VideoDimensionError)
+    );
+}
 
+#[test]
 #[cfg(feature = "std")]
-use std::path::Path;
+fn owned_bitmap() {
+    let owned: OwnedBitMap = OwnedBitMap::new(6, 5);
+    assert_eq!(owned.len(), 30);
+    assert_eq!(owned.iter().count(), 30);
+    assert!(owned.get(6, 0).is_none());
 
-#[cfg(feature = "std")]
-pub struct OwnedBitMap {
-    map: Vec<UnderlyingComponent>,
+    let bitmap = owned.as_bitmap::<32>().unwrap();
+    assert_eq!(bitmap.width(), 6);
+    assert_eq!(bitmap.height(), 5);
+    assert_eq!(bitmap.iter().count(), 30);
+
+    let frame = owned.as_frame::<30>().unwrap();
+    assert_eq!(frame.area(), 30);
+
+    assert!(owned.as_bitmap::<29>().is_err());
 }
 
+#[test]
 #[cfg(feature = "std")]
-impl<'a, const XY: usize> BitMap<'a, XY> {
-    
-    // /// Helpers to build a test bitmap based on [Star] content
-    // pub fn from_stars_coords_radius(stars: Vec<Star>) -> Self {
-    //     let mut s = Self::from_vec(Vec::with_capacity(XY));
-    //     s
-    // }
-}
\ No newline at end of file
+fn owned_bitmap_from_vec() {
+    let vec = vec![UnderlyingComponent::gray8(10); 8];
+    assert!(OwnedBitMap::from_vec(3, 3, vec.clone()).is_err());
+
+    let owned = OwnedBitMap::from_vec(4, 2, vec).unwrap();
+    assert!(owned
+        .iter()
+        .all(|p| matches!(p, UnderlyingComponent::Gray8(10))));
+}
+
+/// Builds a (width, height) gray map where each pixel equals y * 10 + x
+fn indexed_map<const XY: usize>(width: usize) -> [UnderlyingComponent; XY] {
+    let mut map = [UnderlyingComponent::gray8(0); XY];
+    for (i, p) in map.iter_mut().enumerate() {
+        *p = UnderlyingComponent::gray8(((i / width) * 10 + i % width) as u8);
+    }
+    map
+}
+
+#[test]
+fn bitmap_indexing() {
+    let map = indexed_map::<12>(4);
+    let bitmap = BitMap::from_slice(4, 3, &map).unwrap();
+    for y in 0..3 {
+        for x in 0..4 {
+            let p = bitmap.get(x, y).unwrap();
+            assert_eq!(p.to_gray8(), (y * 10 + x) as u8);
+        }
+    }
+    assert!(bitmap
+        .iter()
+        .map(|p| p.to_gray8())
+        .eq([0, 1, 2, 3, 10, 11, 12, 13, 20, 21, 22, 23]));
+}
+
+#[test]
+fn strided_bitmap() {
+    // 3x3 image padded to 5 components per row
+    let map = indexed_map::<15>(5);
+    assert!(BitMap::from_strided_slice(3, 3, 2, &map).is_err());
+    assert!(BitMap::from_strided_slice(3, 4, 5, &map).is_err());
+
+    let bitmap = BitMap::from_strided_slice(3, 3, 5, &map).unwrap();
+    assert_eq!(bitmap.stride(), 5);
+    assert!(bitmap
+        .iter()
+        .map(|p| p.to_gray8())
+        .eq([0, 1, 2, 10, 11, 12, 20, 21, 22]));
+}
+
+#[test]
+fn oversized_bitmap() {
+    let map = indexed_map::<12>(4);
+    assert!(BitMap::from_slice(4, 4, &map).is_err());
+}
+
+#[test]
+fn bitmap_roi() {
+    let map = indexed_map::<30>(6);
+    let bitmap = BitMap::from_slice(6, 5, &map).unwrap();
+
+    let roi = bitmap.roi(2, 1, 3, 2).unwrap();
+    assert_eq!(roi.width(), 3);
+    assert_eq!(roi.height(), 2);
+    assert_eq!(roi.get(0, 0).unwrap().to_gray8(), 12);
+    assert_eq!(roi.get(2, 1).unwrap().to_gray8(), 24);
+    assert!(roi.get(3, 0).is_none());
+    assert!(roi.get(0, 2).is_none());
+
+    assert!(roi
+        .iter()
+        .map(|p| p.to_gray8())
+        .eq([12, 13, 14, 22, 23, 24]));
+
+    // nested regions
+    let nested = roi.roi(1, 1, 2, 1).unwrap();
+    assert!(nested.iter().map(|p| p.to_gray8()).eq([23, 24]));
+
+    assert_eq!(
+        bitmap.roi(4, 0, 3, 1).err(),
+        Some(Error::VideoDimensionError)
+    );
+    assert_eq!(
+        bitmap.roi(0, 4, 1, 2).err(),
+        Some(Error::VideoDimensionError)
+    );
+    assert!(roi.roi(0, 0, 4, 1).is_err());
+}
+
+#[test]
+fn typed_bitmaps() {
+    let gray: [Gray8; 6] = [0, 50, 100, 150, 200, 250];
+    let bitmap = BitMap::from_slice(3, 2, &gray).unwrap();
+    assert_eq!(bitmap.get(1, 1), Some(&200));
+    assert_eq!(core::mem::size_of_val(&gray), 6);
+
+    let rgb = [Rgb8 {
+        r: 255,
+        g: 255,
+        b: 255,
+    }; 4];
+    let bitmap = BitMap::from_slice(2, 2, &rgb).unwrap();
+    assert!(bitmap.iter().all(|p| p.to_gray8() == 255));
+}
+
+#[test]
+fn decimated_bitmap() {
+    let map = indexed_map::<30>(6);
+    let bitmap = BitMap::from_slice(6, 5, &map).unwrap();
+    assert!(bitmap.decimate(0).is_err());
+
+    let decimated = bitmap.decimate(2).unwrap();
+    assert_eq!((decimated.width(), decimated.height()), (3, 3));
+    assert!(decimated
+        .iter()
+        .map(|p| p.to_gray8())
+        .eq([0, 2, 4, 20, 22, 24, 40, 42, 44]));
+
+    // decimated region of interest
+    let roi = bitmap.roi(1, 1, 4, 3).unwrap().decimate(3).unwrap();
+    assert!(roi.iter().map(|p| p.to_gray8()).eq([11, 14]));
+}
+
+#[test]
+fn binned_bitmap() {
+    let gray: [Gray8; 20] = [
+        0, 2, 4, 6, 9, //
+        2, 4, 6, 8, 9, //
+        10, 10, 20, 20, 9, //
+        10, 11, 20, 21, 9,
+    ];
+    let bitmap = BitMap::from_slice(5, 4, &gray).unwrap();
+
+    let mut buf = [0; 20];
+    let binned = BitMap::from_binning(&bitmap, Binning::X2, &mut buf).unwrap();
+    assert_eq!((binned.width(), binned.height()), (2, 2));
+    assert!(binned.iter().copied().eq([2, 6, 10, 20]));
+
+    let mut buf = [0; 20];
+    let binned = BitMap::from_binning(&bitmap, Binning::X4, &mut buf).unwrap();
+    assert_eq!((binned.width(), binned.height()), (1, 1));
+    assert_eq!(binned.get(0, 0), Some(&10));
+}
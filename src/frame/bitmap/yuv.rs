@@ -0,0 +1,146 @@
+//! Planar and packed YUV buffers
+use crate::{
+    frame::{
+        bitmap::{BitMap, Layout},
+        component::{Gray8, YCbCr8},
+    },
+    Error,
+};
+
+/// [YuvFormat] describes the YUV buffer layouts
+/// typically published by embedded cameras.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum YuvFormat {
+    /// 4:2:0 semi-planar: Y plane followed by one interleaved (Cb, Cr) plane
+    Nv12,
+    /// 4:2:0 planar: Y plane, followed by Cb plane then Cr plane
+    I420,
+    /// 4:2:2 packed: Y0 Cb Y1 Cr, for each pair of pixels
+    Yuyv,
+}
+
+impl YuvFormat {
+    /// Returns number of bytes of a (width, height) buffer in this [YuvFormat]
+    pub fn buffer_len(&self, width: u16, height: u16) -> usize {
+        let (width, height) = (width as usize, height as usize);
+        match self {
+            Self::Nv12 | Self::I420 => width * height + 2 * width.div_ceil(2) * height.div_ceil(2),
+            Self::Yuyv => 2 * width * height,
+        }
+    }
+
+    /// Returns [Layout] of the luma samples
+    fn luma_layout(&self, width: u16, height: u16) -> Layout {
+        match self {
+            Self::Nv12 | Self::I420 => Layout::contiguous(width, height),
+            Self::Yuyv => Layout {
+                width,
+                height,
+                offset: 0,
+                stride: 2 * width as usize,
+                step: 2,
+            },
+        }
+    }
+}
+
+/// [YuvBitMap] wraps a YUV buffer without converting it.
+/// Star detection only requires the luminance, so prefer
+/// [YuvBitMap::luma] to obtain a zero-copy [Gray8] [BitMap].
+/// XY is the maximal total size (in pixels).
+#[derive(Clone, Copy)]
+pub struct YuvBitMap<'a, const XY: usize> {
+    format: YuvFormat,
+    width: u16,
+    height: u16,
+    buf: &'a [u8],
+}
+
+impl<'a, const XY: usize> YuvBitMap<'a, XY> {
+    /// Wraps a (width, height) buffer in given [YuvFormat].
+    /// Fails with [Error::VideoDimensionError] if the buffer is too short
+    /// or the frame exceeds XY pixels, and with [Error::VideoFormatError]
+    /// for odd [YuvFormat::Yuyv] widths.
+    pub fn new(format: YuvFormat, width: u16, height: u16, buf: &'a [u8]) -> Result<Self, Error> {
+        if format == YuvFormat::Yuyv && width % 2 != 0 {
+            return Err(Error::VideoFormatError);
+        }
+        if width as usize * height as usize > XY || buf.len() < format.buffer_len(width, height) {
+            return Err(Error::VideoDimensionError);
+        }
+        Ok(Self {
+            format,
+            width,
+            height,
+            buf,
+        })
+    }
+
+    /// Wraps a (width, height) [YuvFormat::Nv12] buffer
+    pub fn from_nv12(width: u16, height: u16, buf: &'a [u8]) -> Result<Self, Error> {
+        Self::new(YuvFormat::Nv12, width, height, buf)
+    }
+
+    /// Wraps a (width, height) [YuvFormat::I420] buffer
+    pub fn from_i420(width: u16, height: u16, buf: &'a [u8]) -> Result<Self, Error> {
+        Self::new(YuvFormat::I420, width, height, buf)
+    }
+
+    /// Wraps a (width, height) [YuvFormat::Yuyv] buffer
+    pub fn from_yuyv(width: u16, height: u16, buf: &'a [u8]) -> Result<Self, Error> {
+        Self::new(YuvFormat::Yuyv, width, height, buf)
+    }
+
+    /// Returns [YuvFormat] of this buffer
+    pub fn format(&self) -> YuvFormat {
+        self.format
+    }
+
+    /// Returns width of this [YuvBitMap] (in pixels)
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Returns height of this [YuvBitMap] (in pixels)
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Returns a zero-copy view over the luminance samples
+    pub fn luma(&self) -> BitMap<'a, XY, Gray8> {
+        BitMap::from_raw(self.format.luma_layout(self.width, self.height), self.buf)
+    }
+
+    /// Returns (x, y) pixel, expressed as [YCbCr8]
+    pub fn get(&self, x: u16, y: u16) -> Option<YCbCr8> {
+        let y_index = self
+            .format
+            .luma_layout(self.width, self.height)
+            .index(x, y)?;
+
+        let (width, height) = (self.width as usize, self.height as usize);
+        let (x, y) = (x as usize, y as usize);
+
+        let (cb_index, cr_index) = match self.format {
+            YuvFormat::Nv12 => {
+                let index = width * height + (y / 2) * 2 * width.div_ceil(2) + (x / 2) * 2;
+                (index, index + 1)
+            }
+            YuvFormat::I420 => {
+                let plane = width.div_ceil(2) * height.div_ceil(2);
+                let index = width * height + (y / 2) * width.div_ceil(2) + x / 2;
+                (index, index + plane)
+            }
+            YuvFormat::Yuyv => {
+                let index = y * 2 * width + (x / 2) * 4;
+                (index + 1, index + 3)
+            }
+        };
+
+        Some(YCbCr8 {
+            y: *self.buf.get(y_index)?,
+            cb: *self.buf.get(cb_index)?,
+            cr: *self.buf.get(cr_index)?,
+        })
+    }
+}
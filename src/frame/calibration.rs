@@ -0,0 +1,261 @@
+//! Dark frame subtraction and flat field correction
+use crate::{
+    frame::{component::Pixel, BitMap, Frame},
+    Error,
+};
+
+/// Flat field samples below this gain are considered dead
+/// and left uncorrected
+const MIN_FLAT_GAIN: f32 = 1.0E-3;
+
+/// [MasterFrame] is a calibration frame (bias, dark or flat),
+/// with one sample per pixel, expressed at native bit depth
+/// (normalized gain for flats).
+#[derive(Debug, Clone, Copy)]
+pub struct MasterFrame<'a> {
+    width: u16,
+    height: u16,
+    data: &'a [f32],
+}
+
+/// Returns median of a small set of samples
+fn median(samples: &mut [f32]) -> f32 {
+    samples.sort_unstable_by(|a, b| a.total_cmp(b));
+    let n = samples.len();
+    if n % 2 == 0 {
+        (samples[n / 2 - 1] + samples[n / 2]) / 2.0
+    } else {
+        samples[n / 2]
+    }
+}
+
+impl<'a> MasterFrame<'a> {
+    /// Wraps (width, height) samples, stored row after row.
+    pub fn new(width: u16, height: u16, data: &'a [f32]) -> Result<Self, Error> {
+        if data.len() < width as usize * height as usize {
+            return Err(Error::VideoDimensionError);
+        }
+        Ok(Self {
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// Builds a [MasterFrame] by median-combining N captures
+    /// (typically bias or dark frames), into dst.
+    /// The median rejects cosmic rays and other transients.
+    pub fn median_combine<const N: usize, const XY: usize, P: Pixel>(
+        frames: &[Frame<'_, XY, P>; N],
+        dst: &'a mut [f32],
+    ) -> Result<Self, Error> {
+        let (width, height) = match frames.first() {
+            Some(frame) => (frame.width(), frame.height()),
+            None => return Err(Error::VideoDimensionError),
+        };
+        if dst.len() < width as usize * height as usize
+            || frames
+                .iter()
+                .any(|f| f.width() != width || f.height() != height)
+        {
+            return Err(Error::VideoDimensionError);
+        }
+
+        let mut samples = [0.0_f32; N];
+        for y in 0..height {
+            for x in 0..width {
+                for (sample, frame) in samples.iter_mut().zip(frames.iter()) {
+                    *sample = frame.get(x, y).map(|p| p.luma()).unwrap_or_default() as f32;
+                }
+                dst[y as usize * width as usize + x as usize] = median(&mut samples);
+            }
+        }
+
+        Self::new(width, height, dst)
+    }
+
+    /// Builds a master flat by median-combining N flat captures, into dst.
+    /// The master bias (if any) is subtracted, then the result
+    /// is normalized to a unit mean gain.
+    pub fn median_combine_flat<const N: usize, const XY: usize, P: Pixel>(
+        frames: &[Frame<'_, XY, P>; N],
+        bias: Option<&MasterFrame<'_>>,
+        dst: &'a mut [f32],
+    ) -> Result<Self, Error> {
+        let (width, height) = MasterFrame::median_combine(frames, &mut dst[..])?.dimensions();
+        let size = width as usize * height as usize;
+
+        if let Some(bias) = bias {
+            if bias.dimensions() != (width, height) {
+                return Err(Error::VideoDimensionError);
+            }
+            for (sample, bias) in dst[..size].iter_mut().zip(bias.data.iter()) {
+                *sample -= bias;
+            }
+        }
+
+        let mean = dst[..size].iter().sum::<f32>() / size as f32;
+        if mean <= 0.0 {
+            return Err(Error::VideoFormatError);
+        }
+        for sample in dst[..size].iter_mut() {
+            *sample /= mean;
+        }
+
+        Self::new(width, height, dst)
+    }
+
+    /// Returns (width, height) of this [MasterFrame]
+    pub fn dimensions(&self) -> (u16, u16) {
+        (self.width, self.height)
+    }
+
+    /// Returns (x, y) sample
+    pub fn get(&self, x: u16, y: u16) -> Option<f32> {
+        if x < self.width && y < self.height {
+            self.data
+                .get(y as usize * self.width as usize + x as usize)
+                .copied()
+        } else {
+            None
+        }
+    }
+}
+
+/// [Calibration] gathers the [MasterFrame]s used to correct
+/// a [Frame] prior star detection, removing thermal noise,
+/// readout offset and vignetting:
+///
+/// corrected = (raw - bias - scaling * (dark - bias)) / flat
+#[derive(Debug, Clone, Copy)]
+pub struct Calibration<'a> {
+    /// Master bias
+    bias: Option<MasterFrame<'a>>,
+    /// Master dark (bias included)
+    dark: Option<MasterFrame<'a>>,
+    /// Normalized master flat
+    flat: Option<MasterFrame<'a>>,
+    /// Dark current scaling
+    dark_scaling: f32,
+}
+
+impl Default for Calibration<'_> {
+    fn default() -> Self {
+        Self {
+            bias: None,
+            dark: None,
+            flat: None,
+            dark_scaling: 1.0,
+        }
+    }
+}
+
+impl<'a> Calibration<'a> {
+    /// Returns a copy of this [Calibration] with given master bias
+    pub fn with_bias(&self, bias: MasterFrame<'a>) -> Self {
+        let mut s = *self;
+        s.bias = Some(bias);
+        s
+    }
+
+    /// Returns a copy of this [Calibration] with given master dark.
+    /// The master dark includes the bias, and should be taken with the same
+    /// exposure time as the frames, unless you define a [Self::with_dark_scaling].
+    pub fn with_dark(&self, dark: MasterFrame<'a>) -> Self {
+        let mut s = *self;
+        s.dark = Some(dark);
+        s
+    }
+
+    /// Returns a copy of this [Calibration] where the dark current is scaled
+    /// by the frame / master dark exposure times ratio.
+    /// Requires a master bias, to isolate the dark current.
+    /// Fails with [Error::ExposureError] if an exposure time is not
+    /// strictly positive and finite.
+    pub fn with_dark_scaling(
+        &self,
+        frame_exposure_s: f32,
+        dark_exposure_s: f32,
+    ) -> Result<Self, Error> {
+        let valid = |exposure: f32| exposure.is_finite() && exposure > 0.0;
+        if !valid(frame_exposure_s) || !valid(dark_exposure_s) {
+            return Err(Error::ExposureError);
+        }
+        let mut s = *self;
+        s.dark_scaling = frame_exposure_s / dark_exposure_s;
+        Ok(s)
+    }
+
+    /// Returns a copy of this [Calibration] with given normalized master flat
+    pub fn with_flat(&self, flat: MasterFrame<'a>) -> Self {
+        let mut s = *self;
+        s.flat = Some(flat);
+        s
+    }
+
+    /// Corrects the (x, y) luminance sample
+    fn correct(&self, x: u16, y: u16, value: f32) -> f32 {
+        let bias = self.bias.and_then(|b| b.get(x, y));
+        let mut value = value - bias.unwrap_or_default();
+
+        if let Some(dark) = self.dark.and_then(|d| d.get(x, y)) {
+            let dark_current = dark - bias.unwrap_or_default();
+            value -= self.dark_scaling * dark_current;
+        }
+
+        if let Some(gain) = self.flat.and_then(|f| f.get(x, y)) {
+            if gain > MIN_FLAT_GAIN {
+                value /= gain;
+            }
+        }
+
+        value
+    }
+
+    /// Applies this [Calibration] to given [Frame], writing
+    /// the corrected [Frame] into buf. The luminance is corrected, the
+    /// chrominance (if any) is kept, and corrected samples are saturated
+    /// to the [Pixel] dynamic range. [MasterFrame]s span the full sensor
+    /// frame, so region of interest [Frame]s are corrected at their origin,
+    /// and the corrected [Frame] retains the settings of the [Frame].
+    /// Fails with [Error::VideoDimensionError] if [MasterFrame]s do not
+    /// cover the [Frame], and with [Error::VideoFormatError]
+    /// if dark scaling is requested without a master bias.
+    pub fn apply<'b, const XY: usize, P: Pixel>(
+        &self,
+        frame: &Frame<'b, XY, P>,
+        buf: &'b mut [P; XY],
+    ) -> Result<Frame<'b, XY, P>, Error> {
+        let (width, height) = (frame.width(), frame.height());
+        let (x1, y1) = frame.sensor_coordinates(width, height);
+
+        for master in [self.bias, self.dark, self.flat].iter().flatten() {
+            let (master_width, master_height) = master.dimensions();
+            if x1 > master_width || y1 > master_height {
+                return Err(Error::VideoDimensionError);
+            }
+        }
+        if self.dark_scaling != 1.0 && self.bias.is_none() {
+            return Err(Error::VideoFormatError);
+        }
+        if width as usize * height as usize > XY {
+            return Err(Error::VideoDimensionError);
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                let raw = frame.get(x, y).copied().unwrap_or_default();
+                let (sensor_x, sensor_y) = frame.sensor_coordinates(x, y);
+                let value = self.correct(sensor_x, sensor_y, raw.luma() as f32);
+                buf[y as usize * width as usize + x as usize] =
+                    raw.with_luma(value.round().clamp(0.0, frame.max_luma() as f32) as u16);
+            }
+        }
+
+        let bitmap = BitMap::from_buffer(width, height, buf)?
+            .with_bit_depth(frame.bitmap().bit_depth());
+        let mut calibrated = frame.with_bitmap(bitmap);
+        calibrated.threshold = frame.threshold;
+        Ok(calibrated)
+    }
+}
@@ -0,0 +1,201 @@
+//! Star candidate measurements
+use geo::Coord;
+
+use crate::{
+    frame::{
+        blob::Blob,
+        centroid::{local_background, Centroid, Window, WINDOW_MARGIN},
+        component::Pixel,
+        selection::Ranking,
+        Frame,
+    },
+    Error,
+};
+
+/// Ratio between the FWHM and the standard deviation of a Gaussian PSF
+const FWHM_SIGMA_RATIO: f64 = 2.354_820_045;
+
+/// [StarCandidate] gathers the measurements of one detection,
+/// so downstream identification can decide which detections to trust,
+/// and how to weight them. Luminance related values are expressed
+/// at native bit depth, positions in [Frame] coordinates.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StarCandidate {
+    /// Sub-pixel [Centroid] and its uncertainty
+    pub centroid: Centroid,
+    /// Background subtracted integrated flux
+    pub flux: f64,
+    /// Brightest pixel value
+    pub peak: u16,
+    /// Number of detected pixels
+    pub area: u32,
+    /// Local background level
+    pub background: f64,
+    /// Full width at half maximum (in pixels)
+    pub fwhm: f64,
+    /// Ellipticity: 1 - minor axis / major axis (0 for round stars)
+    pub ellipticity: f64,
+    /// Major axis orientation, counter clockwise from the x axis (in radians)
+    pub orientation: f64,
+    /// Signal to noise ratio
+    pub snr: f64,
+    /// True when at least one pixel reached the saturation level:
+    /// flux and centroid are then less reliable.
+    pub saturated: bool,
+    /// Expected (dx, dy) motion blur over the exposure (in pixels),
+    /// see [crate::frame::motion::Motion]
+    pub blur: Coord<f64>,
+    /// True when the motion blur exceeds [crate::frame::motion::Motion::max_blur]:
+    /// the centroid should not be trusted.
+    pub smeared: bool,
+}
+
+impl StarCandidate {
+    /// Measures the [StarCandidate] described by given [Blob], extracted from
+    /// this [Frame], using the [Frame] centroiding method and saturation level.
+    /// Fails with [Error::CentroidingError] if the centroid cannot be estimated.
+    pub fn measure<const XY: usize, P: Pixel>(
+        frame: &Frame<'_, XY, P>,
+        blob: &Blob,
+    ) -> Result<Self, Error> {
+        let centroid = frame.centroid(blob)?;
+        let (background, rms) = local_background(frame, blob);
+        let (_, _, peak) = blob.peak();
+
+        let area = blob.area();
+        let flux = (blob.flux() - area as f64 * background).max(0.0);
+
+        // CCD equation, unit gain
+        let noise = (flux + area as f64 * rms * rms).sqrt();
+        let snr = if noise > 0.0 { flux / noise } else { 0.0 };
+
+        // background subtracted second moments, including the star wings
+        let window = Window::new(blob, WINDOW_MARGIN, frame.width(), frame.height());
+        let (mut sum, mut sum_xx, mut sum_yy, mut sum_xy) = (0.0_f64, 0.0_f64, 0.0_f64, 0.0_f64);
+        for (x, y, luma) in window.pixels(frame) {
+            let value = luma - background;
+            if value > 0.0 {
+                let (dx, dy) = (x as f64 - centroid.coord.x, y as f64 - centroid.coord.y);
+                sum += value;
+                sum_xx += value * dx * dx;
+                sum_yy += value * dy * dy;
+                sum_xy += value * dx * dy;
+            }
+        }
+
+        let (mut fwhm, mut ellipticity, mut orientation) = (0.0, 0.0, 0.0);
+        if sum > 0.0 {
+            let (xx, yy, xy) = (sum_xx / sum, sum_yy / sum, sum_xy / sum);
+            let delta = ((xx - yy) / 2.0).hypot(xy);
+            let major = ((xx + yy) / 2.0 + delta).max(0.0);
+            let minor = ((xx + yy) / 2.0 - delta).max(0.0);
+            fwhm = FWHM_SIGMA_RATIO * ((major + minor) / 2.0).sqrt();
+            if major > 0.0 {
+                ellipticity = 1.0 - (minor / major).sqrt();
+            }
+            orientation = 0.5 * (2.0 * xy).atan2(xx - yy);
+        }
+
+        let blur = frame.blur(blob);
+        let smeared = match frame.motion {
+            Some(motion) => blur.x.hypot(blur.y) > motion.max_blur,
+            None => false,
+        };
+
+        Ok(Self {
+            centroid,
+            flux,
+            peak,
+            area,
+            background,
+            fwhm,
+            ellipticity,
+            orientation,
+            snr,
+            saturated: peak >= frame.saturation_level(),
+            blur,
+            smeared,
+        })
+    }
+}
+
+/// [StarCandidates] is the fixed capacity list of (at most N) [StarCandidate]s
+/// of a [Frame], sorted by decreasing flux unless ranked otherwise.
+#[derive(Debug, Clone, Copy)]
+pub struct StarCandidates<const N: usize> {
+    candidates: [StarCandidate; N],
+    len: usize,
+    overflow: usize,
+    dropped: usize,
+}
+
+impl<const N: usize> Default for StarCandidates<N> {
+    fn default() -> Self {
+        Self {
+            candidates: [StarCandidate::default(); N],
+            len: 0,
+            overflow: 0,
+            dropped: 0,
+        }
+    }
+}
+
+impl<const N: usize> StarCandidates<N> {
+    /// Appends a [StarCandidate], counted as overflow when full
+    pub(crate) fn push(&mut self, candidate: StarCandidate) {
+        if self.len < N {
+            self.candidates[self.len] = candidate;
+            self.len += 1;
+        } else {
+            self.overflow += 1;
+        }
+    }
+
+    /// Accounts for detections lost before the measurements
+    pub(crate) fn add_overflow(&mut self, overflow: usize) {
+        self.overflow += overflow;
+    }
+
+    /// Accounts for a candidate rejected by the [crate::frame::selection::Selection]
+    pub(crate) fn drop_candidate(&mut self) {
+        self.dropped += 1;
+    }
+
+    /// Returns number of [StarCandidate]s
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no [StarCandidate] was found
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns number of detections that could not be stored,
+    /// see [crate::frame::blob::Blobs::overflow]
+    pub fn overflow(&self) -> usize {
+        self.overflow
+    }
+
+    /// Returns number of candidates rejected by the
+    /// [crate::frame::selection::Selection]: too close to the [Frame]
+    /// edges or to a better ranked star
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Returns [StarCandidate]s as a slice
+    pub fn as_slice(&self) -> &[StarCandidate] {
+        &self.candidates[..self.len]
+    }
+
+    /// Iterates the [StarCandidate]s
+    pub fn iter(&self) -> core::slice::Iter<'_, StarCandidate> {
+        self.as_slice().iter()
+    }
+
+    /// Sorts the [StarCandidate]s, best first, see [Ranking::compare]
+    pub fn rank(&mut self, ranking: Ranking) {
+        self.candidates[..self.len].sort_unstable_by(|a, b| ranking.compare(a, b));
+    }
+}
@@ -0,0 +1,371 @@
+//! Sub-pixel star centroiding
+use geo::Coord;
+use nalgebra::{Matrix5, Vector5};
+
+use crate::{
+    frame::{blob::Blob, component::Pixel, Frame},
+    Error,
+};
+
+/// Pixels added around the [Blob] bounding box, so the star wings
+/// are taken into account
+pub(crate) const WINDOW_MARGIN: u16 = 2;
+
+/// Width (in pixels) of the annulus used to estimate the local
+/// background, when the [Frame] has no [crate::frame::background::Background]
+const ANNULUS_WIDTH: u16 = 3;
+
+/// Quantization noise variance (in squared LSB)
+const QUANTIZATION_VARIANCE: f64 = 1.0 / 12.0;
+
+/// Iterative centroiding stops once the centroid moves less than this (in pixels)
+const CONVERGENCE: f64 = 1.0E-3;
+
+/// Maximal number of Gaussian fitting iterations
+const FIT_ITERATIONS: usize = 30;
+
+/// [Centroiding] methods, from the fastest to the most accurate
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Centroiding {
+    /// Luminance weighted center of mass, without background subtraction.
+    /// Fast, but biased toward the window center on bright backgrounds.
+    CenterOfMass,
+    /// Background subtracted center of gravity
+    #[default]
+    WeightedCenterOfGravity,
+    /// Iterative center of gravity, weighted by a circular Gaussian
+    /// window of given sigma (in pixels) recentered on each iteration.
+    /// Reduces the noise of the star wings.
+    Iterative {
+        /// Gaussian window sigma (in pixels)
+        sigma: f64,
+        /// Maximal number of iterations
+        iterations: usize,
+    },
+    /// Circular 2D Gaussian PSF least squares fitting (Levenberg-Marquardt).
+    /// Most accurate on well sampled stars, and the slowest.
+    Gaussian,
+}
+
+/// [Centroid] is a sub-pixel star position with its 1 sigma uncertainty,
+/// expressed in [Frame] coordinates (pixels)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Centroid {
+    /// Estimated (x, y) position
+    pub coord: Coord<f64>,
+    /// (x, y) 1 sigma uncertainty
+    pub uncertainty: Coord<f64>,
+}
+
+/// Inclusive pixel window
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Window {
+    x_min: u16,
+    y_min: u16,
+    x_max: u16,
+    y_max: u16,
+}
+
+impl Window {
+    /// Returns [Blob] bounding box extended by margin, within (width, height)
+    pub(crate) fn new(blob: &Blob, margin: u16, width: u16, height: u16) -> Self {
+        let (x_min, y_min, x_max, y_max) = blob.bbox();
+        Self {
+            x_min: x_min.saturating_sub(margin),
+            y_min: y_min.saturating_sub(margin),
+            x_max: x_max.saturating_add(margin).min(width.saturating_sub(1)),
+            y_max: y_max.saturating_add(margin).min(height.saturating_sub(1)),
+        }
+    }
+
+    /// Returns true if (x, y) lies within this [Window]
+    fn contains(&self, x: u16, y: u16) -> bool {
+        x >= self.x_min && x <= self.x_max && y >= self.y_min && y <= self.y_max
+    }
+
+    /// Iterates (x, y) luminance of the valid pixels within this [Window]
+    pub(crate) fn pixels<'f, const XY: usize, P: Pixel>(
+        self,
+        frame: &'f Frame<'_, XY, P>,
+    ) -> impl Iterator<Item = (u16, u16, f64)> + 'f {
+        (self.y_min..=self.y_max)
+            .flat_map(move |y| (self.x_min..=self.x_max).map(move |x| (x, y)))
+            .filter(|(x, y)| !frame.is_ignored(*x, *y))
+            .filter_map(|(x, y)| frame.get(x, y).map(|p| (x, y, p.luma() as f64)))
+    }
+}
+
+/// Returns the local (background, noise) around given [Blob], at native bit depth.
+/// Uses the [crate::frame::background::Background] of the [Frame] when defined,
+/// a sigma clipped annulus around the [Blob] otherwise.
+pub(crate) fn local_background<const XY: usize, P: Pixel>(
+    frame: &Frame<'_, XY, P>,
+    blob: &Blob,
+) -> (f64, f64) {
+    if let Some(background) = frame.background {
+        let (x, y, _) = blob.peak();
+        let (x, y) = (frame.origin.0 + x, frame.origin.1 + y);
+        return (background.mean(x, y), background.rms(x, y));
+    }
+
+    let inner = Window::new(blob, WINDOW_MARGIN, frame.width(), frame.height());
+    let outer = Window::new(
+        blob,
+        WINDOW_MARGIN + ANNULUS_WIDTH,
+        frame.width(),
+        frame.height(),
+    );
+
+    let (mut mean, mut stddev) = (0.0, f64::INFINITY);
+    for _ in 0..3 {
+        let (mut acc, mut acc2, mut n) = (0.0_f64, 0.0_f64, 0);
+        for (_, _, luma) in outer
+            .pixels(frame)
+            .filter(|(x, y, _)| !inner.contains(*x, *y))
+        {
+            if (luma - mean).abs() <= 3.0 * stddev {
+                acc += luma;
+                acc2 += luma * luma;
+                n += 1;
+            }
+        }
+        if n == 0 {
+            break;
+        }
+        mean = acc / n as f64;
+        stddev = (acc2 / n as f64 - mean * mean).max(0.0).sqrt();
+    }
+
+    if stddev.is_finite() {
+        (mean, stddev)
+    } else {
+        (0.0, 0.0)
+    }
+}
+
+/// Returns variance of a pixel, for given background noise and signal
+fn pixel_variance(rms: f64, signal: f64) -> f64 {
+    rms * rms + signal.max(0.0) + QUANTIZATION_VARIANCE
+}
+
+/// Weighted center of gravity over window, and its uncertainty.
+/// Weight returns the weight of each (x, y) pixel.
+fn center_of_gravity<const XY: usize, P: Pixel>(
+    frame: &Frame<'_, XY, P>,
+    window: Window,
+    (background, rms): (f64, f64),
+    subtract: bool,
+    weight: impl Fn(f64, f64) -> f64,
+) -> Result<Centroid, Error> {
+    let (mut sum, mut sum_x, mut sum_y) = (0.0_f64, 0.0_f64, 0.0_f64);
+    for (x, y, luma) in window.pixels(frame) {
+        let (x, y) = (x as f64, y as f64);
+        let value = if subtract {
+            (luma - background).max(0.0)
+        } else {
+            luma
+        };
+        let w = weight(x, y) * value;
+        sum += w;
+        sum_x += w * x;
+        sum_y += w * y;
+    }
+
+    if sum <= 0.0 {
+        return Err(Error::CentroidingError);
+    }
+
+    let coord = Coord {
+        x: sum_x / sum,
+        y: sum_y / sum,
+    };
+
+    // first order error propagation
+    let (mut var_x, mut var_y) = (0.0_f64, 0.0_f64);
+    for (x, y, luma) in window.pixels(frame) {
+        let (x, y) = (x as f64, y as f64);
+        let w = weight(x, y);
+        let variance = w * w * pixel_variance(rms, luma - background);
+        var_x += variance * (x - coord.x).powi(2);
+        var_y += variance * (y - coord.y).powi(2);
+    }
+
+    Ok(Centroid {
+        coord,
+        uncertainty: Coord {
+            x: var_x.sqrt() / sum,
+            y: var_y.sqrt() / sum,
+        },
+    })
+}
+
+/// Gaussian window weight at (dx, dy) from its center, elongated
+/// along the blur: the PSF convolved with a uniform motion
+fn smeared_weight(sigma: f64, blur: Coord<f64>, dx: f64, dy: f64) -> f64 {
+    let (xx, xy, yy) = (
+        sigma * sigma + blur.x * blur.x / 12.0,
+        blur.x * blur.y / 12.0,
+        sigma * sigma + blur.y * blur.y / 12.0,
+    );
+    let det = xx * yy - xy * xy;
+    let r2 = (yy * dx * dx - 2.0 * xy * dx * dy + xx * dy * dy) / det;
+    (-r2 / 2.0).exp()
+}
+
+/// Iterative center of gravity, weighted by a (smeared) Gaussian window
+/// recentered on each iteration
+fn iterative<const XY: usize, P: Pixel>(
+    frame: &Frame<'_, XY, P>,
+    window: Window,
+    background: (f64, f64),
+    (sigma, iterations): (f64, usize),
+    blur: Coord<f64>,
+) -> Result<Centroid, Error> {
+    let mut centroid = center_of_gravity(frame, window, background, true, |_, _| 1.0)?;
+    for _ in 0..iterations {
+        let center = centroid.coord;
+        let next = center_of_gravity(frame, window, background, true, |x, y| {
+            smeared_weight(sigma, blur, x - center.x, y - center.y)
+        })?;
+        let shift = (next.coord.x - center.x).hypot(next.coord.y - center.y);
+        centroid = next;
+        if shift < CONVERGENCE {
+            break;
+        }
+    }
+    Ok(centroid)
+}
+
+/// Circular 2D Gaussian PSF fitting, with parameters
+/// (amplitude, x0, y0, sigma, background)
+fn gaussian_fit<const XY: usize, P: Pixel>(
+    frame: &Frame<'_, XY, P>,
+    window: Window,
+    initial: Vector5<f64>,
+    rms: f64,
+) -> Result<Centroid, Error> {
+    // returns (normal matrix, gradient, chi²) at p
+    let normal_equations = |p: &Vector5<f64>| {
+        let (mut h, mut g, mut chi2) = (Matrix5::<f64>::zeros(), Vector5::<f64>::zeros(), 0.0);
+        let (a, x0, y0, s, b) = (p[0], p[1], p[2], p[3], p[4]);
+        for (x, y, luma) in window.pixels(frame) {
+            let (dx, dy) = (x as f64 - x0, y as f64 - y0);
+            let r2 = dx * dx + dy * dy;
+            let e = (-r2 / (2.0 * s * s)).exp();
+            let residual = luma - (b + a * e);
+            let weight = 1.0 / pixel_variance(rms, luma - b);
+            let j = Vector5::new(
+                e,
+                a * e * dx / (s * s),
+                a * e * dy / (s * s),
+                a * e * r2 / (s * s * s),
+                1.0,
+            );
+            h += weight * j * j.transpose();
+            g += weight * residual * j;
+            chi2 += weight * residual * residual;
+        }
+        (h, g, chi2)
+    };
+
+    let mut p = initial;
+    let mut lambda = 1.0E-3;
+    let (mut h, mut g, mut chi2) = normal_equations(&p);
+
+    for _ in 0..FIT_ITERATIONS {
+        let mut damped = h;
+        for i in 0..5 {
+            damped[(i, i)] *= 1.0 + lambda;
+        }
+        let delta = match damped.try_inverse() {
+            Some(inverse) => inverse * g,
+            None => return Err(Error::CentroidingError),
+        };
+
+        let candidate = p + delta;
+        let (c_h, c_g, c_chi2) = normal_equations(&candidate);
+        if c_chi2 < chi2 && candidate[3] > 0.0 {
+            (p, h, g, chi2) = (candidate, c_h, c_g, c_chi2);
+            lambda /= 10.0;
+            if delta[1].abs() < CONVERGENCE && delta[2].abs() < CONVERGENCE {
+                break;
+            }
+        } else {
+            lambda *= 10.0;
+        }
+    }
+
+    let covariance = h.try_inverse().ok_or(Error::CentroidingError)?;
+    let (x_min, x_max) = (window.x_min as f64, window.x_max as f64);
+    let (y_min, y_max) = (window.y_min as f64, window.y_max as f64);
+
+    if p[0] <= 0.0 || p[1] < x_min || p[1] > x_max || p[2] < y_min || p[2] > y_max {
+        return Err(Error::CentroidingError);
+    }
+
+    Ok(Centroid {
+        coord: Coord { x: p[1], y: p[2] },
+        uncertainty: Coord {
+            x: covariance[(1, 1)].max(0.0).sqrt(),
+            y: covariance[(2, 2)].max(0.0).sqrt(),
+        },
+    })
+}
+
+impl Centroiding {
+    /// Estimates the sub-pixel [Centroid] of given [Blob], extracted from this [Frame].
+    /// Pixels ignored by the [Frame] (bad pixels) are not taken into account.
+    /// When the [Frame] has a [crate::frame::motion::Motion], the window follows
+    /// the expected streak, and the centroid is the mid-exposure position.
+    /// Fails with [Error::CentroidingError] when the [Blob] has no signal
+    /// above the background, when the Gaussian fit diverges,
+    /// or when the [Centroiding::Iterative] sigma is not strictly positive.
+    pub fn estimate<const XY: usize, P: Pixel>(
+        &self,
+        frame: &Frame<'_, XY, P>,
+        blob: &Blob,
+    ) -> Result<Centroid, Error> {
+        let (width, height) = (frame.width(), frame.height());
+        let blur = frame.blur(blob);
+        let reach = (blur.x.abs().max(blur.y.abs()) / 2.0).ceil() as u16;
+        let window = Window::new(blob, WINDOW_MARGIN.saturating_add(reach), width, height);
+        let background = local_background(frame, blob);
+
+        match self {
+            Self::CenterOfMass => center_of_gravity(frame, window, background, false, |_, _| 1.0),
+            Self::WeightedCenterOfGravity => {
+                center_of_gravity(frame, window, background, true, |_, _| 1.0)
+            }
+            Self::Iterative { sigma, iterations } => {
+                if !sigma.is_finite() || *sigma <= 0.0 {
+                    return Err(Error::CentroidingError);
+                }
+                let margin = (3.0 * sigma).ceil().max(WINDOW_MARGIN as f64) as u16;
+                let window = Window::new(blob, margin.saturating_add(reach), width, height);
+                iterative(frame, window, background, (*sigma, *iterations), blur)
+            }
+            Self::Gaussian if blur.x.hypot(blur.y) >= 1.0 => {
+                // a circular PSF does not model the streak:
+                // smeared Gaussian window, sized by the PSF without the blur
+                let (xx, yy, _) = blob.second_moments();
+                let length2 = blur.x * blur.x + blur.y * blur.y;
+                let sigma = ((xx + yy - length2 / 12.0) / 2.0).max(0.25).sqrt();
+                iterative(frame, window, background, (sigma, FIT_ITERATIONS), blur)
+            }
+            Self::Gaussian => {
+                let initial = center_of_gravity(frame, window, background, true, |_, _| 1.0)?;
+                let (xx, yy, _) = blob.second_moments();
+                let sigma = ((xx + yy) / 2.0).sqrt().max(0.5);
+                let (_, _, peak) = blob.peak();
+                let p = Vector5::new(
+                    peak as f64 - background.0,
+                    initial.coord.x,
+                    initial.coord.y,
+                    sigma,
+                    background.0,
+                );
+                gaussian_fit(frame, window, p, background.1)
+            }
+        }
+    }
+}
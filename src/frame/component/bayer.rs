@@ -0,0 +1,162 @@
+//! Bayer RAW mosaics and demosaicing
+use crate::{
+    frame::{
+        component::{Pixel, Rgb8},
+        BitMap,
+    },
+    Error,
+};
+
+/// Color filter of one photosite
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Channel {
+    Red,
+    Green,
+    Blue,
+}
+
+/// [BayerPattern] describes the 2x2 color filter array
+/// of a RAW sensor, starting from the (0, 0) photosite.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BayerPattern {
+    /// R G / G B
+    Rggb,
+    /// B G / G R
+    Bggr,
+    /// G R / B G
+    Grbg,
+    /// G B / R G
+    Gbrg,
+}
+
+impl BayerPattern {
+    /// Returns color filter of (x, y) photosite
+    fn channel(&self, x: u16, y: u16) -> Channel {
+        let (even_x, even_y) = (x % 2 == 0, y % 2 == 0);
+        let red_site = match self {
+            Self::Rggb => even_x && even_y,
+            Self::Bggr => !even_x && !even_y,
+            Self::Grbg => !even_x && even_y,
+            Self::Gbrg => even_x && !even_y,
+        };
+        if red_site {
+            Channel::Red
+        } else if (even_x == even_y) == matches!(self, Self::Grbg | Self::Gbrg) {
+            Channel::Green
+        } else {
+            Channel::Blue
+        }
+    }
+
+    /// Luminance-only 2x2 binning: each 2x2 cell is reduced to one
+    /// luminance sample (BT.601 weights), at native bit depth.
+    /// This is the fastest path for star detection.
+    /// dst is filled with (width/2, height/2) pixels, stored row after row.
+    pub(crate) fn luma_binning<const XY: usize, P: Pixel>(
+        &self,
+        mosaic: &BitMap<'_, XY, P>,
+        dst: &mut [P],
+    ) -> Result<(u16, u16), Error> {
+        let (width, height) = (mosaic.width() / 2, mosaic.height() / 2);
+        if (width as usize * height as usize) > dst.len() {
+            return Err(Error::VideoDimensionError);
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                let (mut r, mut g, mut b) = (0.0_f64, 0.0_f64, 0.0_f64);
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let (px, py) = (2 * x + dx, 2 * y + dy);
+                    let value = sample(mosaic, px as i32, py as i32);
+                    match self.channel(px, py) {
+                        Channel::Red => r = value,
+                        Channel::Green => g += value / 2.0,
+                        Channel::Blue => b = value,
+                    }
+                }
+                let luma = 0.299 * r + 0.587 * g + 0.114 * b;
+                dst[y as usize * width as usize + x as usize] = P::from_luma(luma.round() as u16);
+            }
+        }
+        Ok((width, height))
+    }
+
+    /// Bilinear full color reconstruction: missing channels
+    /// are interpolated from the nearest photosites of that color.
+    /// Borders are mirrored. Samples are rescaled to 8 bit.
+    /// dst is filled with (width, height) pixels, stored row after row.
+    pub(crate) fn bilinear<const XY: usize, P: Pixel>(
+        &self,
+        mosaic: &BitMap<'_, XY, P>,
+        dst: &mut [Rgb8],
+    ) -> Result<(), Error> {
+        let (width, height) = (mosaic.width(), mosaic.height());
+        if width < 2 || height < 2 || (width as usize * height as usize) > dst.len() {
+            return Err(Error::VideoDimensionError);
+        }
+
+        let scaling = u8::MAX as f64 / mosaic.max_luma() as f64;
+
+        for y in 0..height {
+            for x in 0..width {
+                let (xi, yi) = (x as i32, y as i32);
+                let own = sample(mosaic, xi, yi);
+                let cross = (sample(mosaic, xi - 1, yi)
+                    + sample(mosaic, xi + 1, yi)
+                    + sample(mosaic, xi, yi - 1)
+                    + sample(mosaic, xi, yi + 1))
+                    / 4.0;
+                let diagonal = (sample(mosaic, xi - 1, yi - 1)
+                    + sample(mosaic, xi + 1, yi - 1)
+                    + sample(mosaic, xi - 1, yi + 1)
+                    + sample(mosaic, xi + 1, yi + 1))
+                    / 4.0;
+                let horizontal = (sample(mosaic, xi - 1, yi) + sample(mosaic, xi + 1, yi)) / 2.0;
+                let vertical = (sample(mosaic, xi, yi - 1) + sample(mosaic, xi, yi + 1)) / 2.0;
+
+                let (r, g, b) = match self.channel(x, y) {
+                    Channel::Red => (own, cross, diagonal),
+                    Channel::Blue => (diagonal, cross, own),
+                    Channel::Green => {
+                        // neighbor on the same row
+                        let x_neighbor = if x + 1 < width { x + 1 } else { x - 1 };
+                        if self.channel(x_neighbor, y) == Channel::Red {
+                            (horizontal, own, vertical)
+                        } else {
+                            (vertical, own, horizontal)
+                        }
+                    }
+                };
+
+                let rescale = |v: f64| (v * scaling).round().clamp(0.0, 255.0) as u8;
+
+                dst[y as usize * width as usize + x as usize] = Rgb8 {
+                    r: rescale(r),
+                    g: rescale(g),
+                    b: rescale(b),
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads (x, y) photosite, mirroring coordinates out of bounds,
+/// which preserves the color filter parity.
+fn sample<const XY: usize, P: Pixel>(mosaic: &BitMap<'_, XY, P>, x: i32, y: i32) -> f64 {
+    let mirror = |v: i32, size: u16| {
+        let size = size as i32;
+        if v < 0 {
+            -v
+        } else if v >= size {
+            2 * (size - 1) - v
+        } else {
+            v
+        }
+    };
+    let (x, y) = (mirror(x, mosaic.width()), mirror(y, mosaic.height()));
+    mosaic
+        .get(x as u16, y as u16)
+        .map(|p| p.luma() as f64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,144 @@
+//! Color space conversions
+use crate::frame::component::{Gray8, Rgb8, YCbCr8};
+
+/// [Matrix] defines the luma coefficients (and therefore the
+/// YCbCr conversion matrix) of each standard.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Matrix {
+    /// ITU-R BT.601 (SD video, JPEG)
+    #[default]
+    Bt601,
+    /// ITU-R BT.709 (HD video)
+    Bt709,
+    /// ITU-R BT.2020 (UHD video)
+    Bt2020,
+}
+
+impl Matrix {
+    /// Returns (Kr, Kg, Kb) luma coefficients
+    pub fn luma_coefficients(&self) -> (f32, f32, f32) {
+        let (kr, kb) = match self {
+            Self::Bt601 => (0.299, 0.114),
+            Self::Bt709 => (0.2126, 0.0722),
+            Self::Bt2020 => (0.2627, 0.0593),
+        };
+        (kr, 1.0 - kr - kb, kb)
+    }
+}
+
+/// [Range] defines the quantization range of YCbCr samples
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Range {
+    /// Full range: Y, Cb and Cr use 0..=255 (JPEG)
+    #[default]
+    Full,
+    /// Limited (studio) range: Y uses 16..=235 and Cb, Cr use 16..=240
+    Limited,
+}
+
+impl Range {
+    /// Returns (Y offset, Y scale, C scale)
+    fn quantization(&self) -> (f32, f32, f32) {
+        match self {
+            Self::Full => (0.0, 255.0, 255.0),
+            Self::Limited => (16.0, 219.0, 224.0),
+        }
+    }
+}
+
+/// [Conversion] between [Rgb8], [YCbCr8] and [Gray8], for a given
+/// [Matrix] and [Range]. Defaults to BT.601 full range,
+/// which is what JPEG decoders produce.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Conversion {
+    /// [Matrix] (luma coefficients)
+    pub matrix: Matrix,
+    /// YCbCr quantization [Range]
+    pub range: Range,
+}
+
+/// Rounds and saturates to 8 bit
+fn quantize(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+impl Conversion {
+    /// Builds a new [Conversion]
+    pub fn new(matrix: Matrix, range: Range) -> Self {
+        Self { matrix, range }
+    }
+
+    /// Converts [Rgb8] to [YCbCr8]
+    pub fn rgb8_to_ycbcr8(&self, rgb8: Rgb8) -> YCbCr8 {
+        let (kr, kg, kb) = self.matrix.luma_coefficients();
+        let (y_offset, y_scale, c_scale) = self.range.quantization();
+
+        let (r, g, b) = (
+            rgb8.r as f32 / 255.0,
+            rgb8.g as f32 / 255.0,
+            rgb8.b as f32 / 255.0,
+        );
+
+        let y = kr * r + kg * g + kb * b;
+        let cb = (b - y) / (2.0 * (1.0 - kb));
+        let cr = (r - y) / (2.0 * (1.0 - kr));
+
+        YCbCr8 {
+            y: quantize(y_offset + y_scale * y),
+            cb: quantize(128.0 + c_scale * cb),
+            cr: quantize(128.0 + c_scale * cr),
+        }
+    }
+
+    /// Converts [YCbCr8] to [Rgb8]. Correct over the whole input range:
+    /// out of gamut combinations are saturated.
+    pub fn ycbcr8_to_rgb8(&self, ycbcr8: YCbCr8) -> Rgb8 {
+        let (kr, kg, kb) = self.matrix.luma_coefficients();
+        let (y_offset, y_scale, c_scale) = self.range.quantization();
+
+        let y = (ycbcr8.y as f32 - y_offset) / y_scale;
+        let cb = (ycbcr8.cb as f32 - 128.0) / c_scale;
+        let cr = (ycbcr8.cr as f32 - 128.0) / c_scale;
+
+        let r = y + 2.0 * (1.0 - kr) * cr;
+        let b = y + 2.0 * (1.0 - kb) * cb;
+        let g = (y - kr * r - kb * b) / kg;
+
+        Rgb8 {
+            r: quantize(255.0 * r),
+            g: quantize(255.0 * g),
+            b: quantize(255.0 * b),
+        }
+    }
+
+    /// Converts [Rgb8] to [Gray8], using the luma coefficients of the [Matrix]
+    pub fn rgb8_to_gray8(&self, rgb8: Rgb8) -> Gray8 {
+        let (kr, kg, kb) = self.matrix.luma_coefficients();
+        quantize(kr * rgb8.r as f32 + kg * rgb8.g as f32 + kb * rgb8.b as f32)
+    }
+
+    /// Converts [YCbCr8] to full range [Gray8]. Only the luma sample is used.
+    pub fn ycbcr8_to_gray8(&self, ycbcr8: YCbCr8) -> Gray8 {
+        let (y_offset, y_scale, _) = self.range.quantization();
+        quantize((ycbcr8.y as f32 - y_offset) * 255.0 / y_scale)
+    }
+
+    /// Converts full range [Gray8] to [Rgb8]
+    pub fn gray8_to_rgb8(&self, gray8: Gray8) -> Rgb8 {
+        Rgb8 {
+            r: gray8,
+            g: gray8,
+            b: gray8,
+        }
+    }
+
+    /// Converts full range [Gray8] to [YCbCr8] (neutral chroma)
+    pub fn gray8_to_ycbcr8(&self, gray8: Gray8) -> YCbCr8 {
+        let (y_offset, y_scale, _) = self.range.quantization();
+        YCbCr8 {
+            y: quantize(y_offset + gray8 as f32 * y_scale / 255.0),
+            cb: 128,
+            cr: 128,
+        }
+    }
+}
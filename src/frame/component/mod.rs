@@ -1,10 +1,93 @@
 
 /// [UnderlyingPixel] holds all supported inner pixel format that we support
+pub mod bayer;
+pub mod color;
+pub mod packed;
 pub mod rgb8;
 pub mod ycbcr8;
 
-use rgb8::Rgb8;
-use ycbcr8::YCbCr8;
+use color::Conversion;
+
+pub use rgb8::Rgb8;
+pub use ycbcr8::YCbCr8;
+
+/// [Pixel] is implemented by all pixel formats a [BitMap](crate::frame::BitMap)
+/// may store. Processing is monomorphised for each format.
+pub trait Pixel: Copy + Default {
+    /// Maximal luminance value this format may represent
+    const MAX_LUMA: u16;
+
+    /// Maximal bit depth this format may represent. Data of lower bit depth
+    /// (like 10 or 12 bit sensors) is described by its
+    /// [BitMap](crate::frame::BitMap), see [BitMap::bit_depth](crate::frame::BitMap::bit_depth).
+    const BIT_DEPTH: u8;
+
+    /// Returns luminance of this [Pixel] at native bit depth
+    fn luma(self) -> u16;
+
+    /// Builds a gray [Pixel] from native bit depth luminance,
+    /// saturated to [Pixel::MAX_LUMA]
+    fn from_luma(luma: u16) -> Self;
+
+    /// Returns this [Pixel] with given native bit depth luminance,
+    /// saturated to [Pixel::MAX_LUMA], keeping its chrominance (if any)
+    fn with_luma(self, luma: u16) -> Self {
+        Self::from_luma(luma)
+    }
+
+    /// Converts this [Pixel] to Gray scale 8bit
+    fn to_gray8(self) -> u8;
+
+    /// Converts this [Pixel], which luminance spans bit_depth bits,
+    /// to Gray scale 8bit
+    fn to_gray8_at(self, bit_depth: u8) -> u8 {
+        (self.luma() >> bit_depth.saturating_sub(8)).min(u8::MAX as u16) as u8
+    }
+}
+
+/// Gray Scale 8 bit pixel
+pub type Gray8 = u8;
+
+impl Pixel for Gray8 {
+    const MAX_LUMA: u16 = u8::MAX as u16;
+    const BIT_DEPTH: u8 = 8;
+
+    fn luma(self) -> u16 {
+        self as u16
+    }
+
+    fn from_luma(luma: u16) -> Self {
+        luma.min(Self::MAX_LUMA) as u8
+    }
+
+    fn to_gray8(self) -> u8 {
+        self
+    }
+}
+
+/// Gray Scale 16 bit pixel. Also used to store
+/// 10 and 12 bit sensor data, at native depth (see [packed]):
+/// the [BitMap](crate::frame::BitMap) then describes the actual bit depth.
+pub type Gray16 = u16;
+
+impl Pixel for Gray16 {
+    const MAX_LUMA: u16 = u16::MAX;
+    const BIT_DEPTH: u8 = 16;
+
+    fn luma(self) -> u16 {
+        self
+    }
+
+    fn from_luma(luma: u16) -> Self {
+        luma
+    }
+
+    /// Converts 16 bit data to Gray scale 8bit,
+    /// see [Pixel::to_gray8_at] for lower bit depths
+    fn to_gray8(self) -> u8 {
+        self.to_gray8_at(Self::BIT_DEPTH)
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 pub enum UnderlyingComponent {
@@ -34,12 +117,43 @@ impl UnderlyingComponent {
         Self::Gray8(gray)
     }
 
-    /// Converts [UnderlyingComponent] to Gray8 (whatever the input format)
-    pub(crate) fn to_gray8(&self) -> u8 {
+    /// Converts [UnderlyingComponent] to Gray8 (whatever the input format),
+    /// with the luma weights and YCbCr range of given [Conversion].
+    /// [Pixel::to_gray8] uses BT.601 full range.
+    pub fn to_gray8_with(self, conversion: &Conversion) -> u8 {
+        match self {
+            Self::Gray8(gray8) => gray8,
+            Self::Rgb8(rgb8) => conversion.rgb8_to_gray8(rgb8),
+            Self::YCbCr8(ycbcr8) => conversion.ycbcr8_to_gray8(ycbcr8),
+        }
+    }
+
+}
+
+/// [UnderlyingComponent] is the dynamic [Pixel] format,
+/// for frames which format is only known at runtime.
+impl Pixel for UnderlyingComponent {
+    const MAX_LUMA: u16 = u8::MAX as u16;
+    const BIT_DEPTH: u8 = 8;
+
+    fn luma(self) -> u16 {
+        self.to_gray8() as u16
+    }
+
+    fn from_luma(luma: u16) -> Self {
+        Self::Gray8(Gray8::from_luma(luma))
+    }
+
+    fn with_luma(self, luma: u16) -> Self {
         match self {
-            Self::Gray8(gray8) => *gray8,
-            Self::Rgb8(rgb8) => rgb8.to_gray8(),
-            Self::YCbCr8(ycbcr8) => ycbcr8.to_rgb8().to_gray8(),
+            Self::Gray8(_) => Self::from_luma(luma),
+            Self::Rgb8(rgb8) => Self::Rgb8(rgb8.with_luma(luma)),
+            Self::YCbCr8(ycbcr8) => Self::YCbCr8(ycbcr8.with_luma(luma)),
         }
     }
+
+    /// Converts [UnderlyingComponent] to Gray8 (whatever the input format)
+    fn to_gray8(self) -> u8 {
+        self.to_gray8_with(&Conversion::default())
+    }
 }
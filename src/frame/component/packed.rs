@@ -0,0 +1,105 @@
+//! Packed high bit-depth sensor formats
+use crate::{frame::component::Gray16, Error};
+
+/// [PackedFormat] describes how 10 or 12 bit mono sensors pack
+/// their pixels into bytes. Unpacked pixels are stored as [Gray16]
+/// at native depth (no rescaling).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PackedFormat {
+    /// GenICam PFNC Mono10p: 4 pixels in 5 bytes, LSB first bit stream
+    Mono10p,
+    /// GigE Vision Mono10Packed: 2 pixels in 3 bytes.
+    /// Byte 0 and 2 hold the 8 MSBs, byte 1 holds both 2 LSBs (bits 0-1 and 4-5)
+    Mono10Packed,
+    /// GenICam PFNC Mono12p: 2 pixels in 3 bytes, LSB first bit stream
+    Mono12p,
+    /// GigE Vision Mono12Packed: 2 pixels in 3 bytes.
+    /// Byte 0 and 2 hold the 8 MSBs, byte 1 holds both 4 LSBs
+    Mono12Packed,
+    /// MIPI CSI-2 RAW10: 4 pixels in 5 bytes.
+    /// Byte 0-3 hold the 8 MSBs, byte 4 holds the 2 LSBs of each pixel
+    Raw10,
+    /// MIPI CSI-2 RAW12: 2 pixels in 3 bytes.
+    /// Byte 0-1 hold the 8 MSBs, byte 2 holds the 4 LSBs of each pixel
+    Raw12,
+}
+
+impl PackedFormat {
+    /// Returns the native bit depth of this [PackedFormat]
+    pub fn bit_depth(&self) -> u8 {
+        match self {
+            Self::Mono10p | Self::Mono10Packed | Self::Raw10 => 10,
+            Self::Mono12p | Self::Mono12Packed | Self::Raw12 => 12,
+        }
+    }
+
+    /// Returns maximal luminance value of this [PackedFormat]
+    pub fn max_luma(&self) -> u16 {
+        (1 << self.bit_depth()) - 1
+    }
+
+    /// Returns (pixels, bytes) group size
+    fn group(&self) -> (usize, usize) {
+        match self {
+            Self::Mono10p | Self::Raw10 => (4, 5),
+            Self::Mono10Packed | Self::Mono12p | Self::Mono12Packed | Self::Raw12 => (2, 3),
+        }
+    }
+
+    /// Returns number of bytes required to store this many pixels
+    pub fn packed_len(&self, pixels: usize) -> usize {
+        let (group_pixels, group_bytes) = self.group();
+        pixels.div_ceil(group_pixels) * group_bytes
+    }
+
+    /// Unpacks dst.len() pixels from the packed bytes.
+    /// Fails with [Error::VideoDimensionError] if src is too short.
+    pub fn unpack(&self, src: &[u8], dst: &mut [Gray16]) -> Result<(), Error> {
+        if src.len() < self.packed_len(dst.len()) {
+            return Err(Error::VideoDimensionError);
+        }
+
+        let (group_pixels, group_bytes) = self.group();
+        let mut pixels = [0_u16; 4];
+
+        for (bytes, dst) in src.chunks(group_bytes).zip(dst.chunks_mut(group_pixels)) {
+            self.unpack_group(bytes, &mut pixels);
+            dst.copy_from_slice(&pixels[..dst.len()]);
+        }
+        Ok(())
+    }
+
+    /// Unpacks one group of pixels
+    fn unpack_group(&self, b: &[u8], p: &mut [u16; 4]) {
+        let b = |i: usize| b[i] as u16;
+        match self {
+            Self::Mono10p => {
+                p[0] = b(0) | (b(1) & 0x03) << 8;
+                p[1] = b(1) >> 2 | (b(2) & 0x0f) << 6;
+                p[2] = b(2) >> 4 | (b(3) & 0x3f) << 4;
+                p[3] = b(3) >> 6 | b(4) << 2;
+            }
+            Self::Mono10Packed => {
+                p[0] = b(0) << 2 | (b(1) & 0x03);
+                p[1] = b(2) << 2 | (b(1) >> 4 & 0x03);
+            }
+            Self::Mono12p => {
+                p[0] = b(0) | (b(1) & 0x0f) << 8;
+                p[1] = b(1) >> 4 | b(2) << 4;
+            }
+            Self::Mono12Packed => {
+                p[0] = b(0) << 4 | (b(1) & 0x0f);
+                p[1] = b(2) << 4 | b(1) >> 4;
+            }
+            Self::Raw10 => {
+                for (i, p) in p.iter_mut().enumerate() {
+                    *p = b(i) << 2 | (b(4) >> (2 * i) & 0x03);
+                }
+            }
+            Self::Raw12 => {
+                p[0] = b(0) << 4 | (b(2) & 0x0f);
+                p[1] = b(1) << 4 | b(2) >> 4;
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::frame::component::{color::Conversion, Pixel};
+
 /// [Rgb8] representation of a pixel
 #[derive(Debug, Clone, Default, Copy)]
 pub struct Rgb8 {
@@ -15,11 +17,40 @@ impl Rgb8 {
     //         b: u8::MAX,
     //     }
     // }
+}
+
+impl Pixel for Rgb8 {
+    const MAX_LUMA: u16 = u8::MAX as u16;
+    const BIT_DEPTH: u8 = 8;
+
+    fn luma(self) -> u16 {
+        self.to_gray8() as u16
+    }
+
+    fn from_luma(luma: u16) -> Self {
+        let gray = luma.min(Self::MAX_LUMA) as u8;
+        Self {
+            r: gray,
+            g: gray,
+            b: gray,
+        }
+    }
 
-    /// Converts [Rgb8] to Gray scale 8bit
-    pub(crate) fn to_gray8(&self) -> u8 {
-        let gray8 = 0.299 * self.r as f32 + 0.587 * self.g as f32 + 0.114 * self.b as f32;
-        gray8.round() as u8
+    /// Shifts all channels by the luminance difference: the luma weights
+    /// summing to one, the color differences are kept (unless saturated)
+    fn with_luma(self, luma: u16) -> Self {
+        let shift = luma.min(Self::MAX_LUMA) as i16 - self.luma() as i16;
+        let channel = |c: u8| (c as i16 + shift).clamp(0, u8::MAX as i16) as u8;
+        Self {
+            r: channel(self.r),
+            g: channel(self.g),
+            b: channel(self.b),
+        }
     }
 
+    /// Converts [Rgb8] to Gray scale 8bit, with BT.601 luma weights.
+    /// Use [Conversion] for other weights.
+    fn to_gray8(self) -> u8 {
+        Conversion::default().rgb8_to_gray8(self)
+    }
 }
\ No newline at end of file
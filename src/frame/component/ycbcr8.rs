@@ -1,4 +1,4 @@
-use crate::frame::component::Rgb8;
+use crate::frame::component::{color::Conversion, Pixel, Rgb8};
 
 #[derive(Debug, Clone, Copy)]
 /// [YCbCr8] representation of a pixel
@@ -22,20 +22,47 @@ impl YCbCr8 {
     //     }
     // }
 
-    /// Converts [YCbCr8] to [Rgb8]
-    pub(crate) fn to_rgb8(&self) -> Rgb8 {
+    /// Converts [YCbCr8] to [Rgb8], assuming BT.601 full range.
+    /// Use [Conversion] for other standards.
+    pub fn to_rgb8(self) -> Rgb8 {
+        Conversion::default().ycbcr8_to_rgb8(self)
+    }
+}
+
+impl Default for YCbCr8 {
+    /// Black [YCbCr8] pixel
+    fn default() -> Self {
+        Self {
+            y: 0,
+            cb: 128,
+            cr: 128,
+        }
+    }
+}
 
-        let r = self.y as f32 + 1.402 * (self.cr - 128) as f32;
-        
-        let mut g = self.y as f32 - 0.344136 * (self.cb - 128) as f32;
-        g -= 0.714136 * (self.cr - 128) as f32;
+impl Pixel for YCbCr8 {
+    const MAX_LUMA: u16 = u8::MAX as u16;
+    const BIT_DEPTH: u8 = 8;
 
-        let b = self.y as f32 + 1.772 * (self.cb - 128) as f32;
+    fn luma(self) -> u16 {
+        self.to_gray8() as u16
+    }
+
+    fn from_luma(luma: u16) -> Self {
+        Self {
+            y: luma.min(Self::MAX_LUMA) as u8,
+            ..Default::default()
+        }
+    }
 
-        Rgb8 { 
-            r: r.round().clamp(0.0, 255.0) as u8,
-            g: g.round().clamp(0.0, 255.0) as u8,
-            b: b.round().clamp(0.0, 255.0) as u8,
+    fn with_luma(self, luma: u16) -> Self {
+        Self {
+            y: luma.min(Self::MAX_LUMA) as u8,
+            ..self
         }
     }
+
+    fn to_gray8(self) -> u8 {
+        Conversion::default().ycbcr8_to_gray8(self)
+    }
 }
\ No newline at end of file
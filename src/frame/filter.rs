@@ -0,0 +1,211 @@
+//! Convolution and denoising filters
+use geo::Coord;
+
+use crate::{
+    frame::{component::Pixel, BitMap, Frame},
+    Error,
+};
+
+/// [Border] handling, for pixels whose K x K window exceeds the [Frame]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Border {
+    /// Edge pixels are repeated outside the [Frame]
+    #[default]
+    Replicate,
+    /// [Frame] is mirrored at its edges (without repeating the edge pixels)
+    Reflect,
+    /// Border pixels are copied without filtering
+    Unfiltered,
+}
+
+impl Border {
+    /// Maps coordinate i, possibly outside 0..n, within the [Frame].
+    /// Returns None for [Border::Unfiltered].
+    fn map(&self, i: i32, n: u16) -> Option<u16> {
+        let last = n as i32 - 1;
+        if (0..=last).contains(&i) {
+            return Some(i as u16);
+        }
+        match self {
+            Self::Replicate => Some(i.clamp(0, last) as u16),
+            Self::Reflect => {
+                let i = if i < 0 { -i } else { 2 * last - i };
+                Some(i.clamp(0, last) as u16)
+            }
+            Self::Unfiltered => None,
+        }
+    }
+}
+
+/// [Filter]s applied before the star detection, over a K x K window
+/// (K odd). Sky noise is reduced, so fainter stars can be detected
+/// at the same false alarm rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter<'a> {
+    /// Moving average
+    Box,
+    /// Gaussian smoothing of given sigma (in pixels)
+    Gaussian {
+        /// Standard deviation (in pixels)
+        sigma: f64,
+    },
+    /// Median: removes hot pixels and cosmic rays,
+    /// while preserving the star profiles
+    Median,
+    /// Matched filter: correlation with the point spread function of the
+    /// optics, the optimal linear detector in white noise. The PSF is
+    /// a K x K row major kernel, normalized to unit sum by the [Filter].
+    Matched(&'a [f32]),
+    /// Motion filter: average along the expected (dx, dy) star streak
+    /// (in pixels), see [crate::frame::motion::Motion::blur].
+    /// Matched to the stars smeared by the camera rotation.
+    Motion {
+        /// Displacement over the exposure (in pixels)
+        blur: Coord<f64>,
+    },
+}
+
+impl Filter<'_> {
+    /// Returns the normalized K x K convolution kernel,
+    /// None for non linear filters
+    fn kernel<const K: usize>(&self) -> Result<Option<[[f64; K]; K]>, Error> {
+        let mut kernel = [[1.0; K]; K];
+        let center = (K / 2) as f64;
+
+        match self {
+            Self::Box => {}
+            Self::Gaussian { sigma } => {
+                if *sigma <= 0.0 {
+                    return Err(Error::KernelError);
+                }
+                for (y, row) in kernel.iter_mut().enumerate() {
+                    for (x, weight) in row.iter_mut().enumerate() {
+                        let r2 = (x as f64 - center).powi(2) + (y as f64 - center).powi(2);
+                        *weight = (-r2 / (2.0 * sigma * sigma)).exp();
+                    }
+                }
+            }
+            Self::Median => return Ok(None),
+            Self::Motion { blur } => {
+                // anti-aliased segment, centered on the kernel
+                let (dx, dy) = (blur.x, blur.y);
+                let length2 = dx * dx + dy * dy;
+                for (y, row) in kernel.iter_mut().enumerate() {
+                    for (x, weight) in row.iter_mut().enumerate() {
+                        let (px, py) = (x as f64 - center, y as f64 - center);
+                        let t = if length2 > 0.0 {
+                            ((px * dx + py * dy) / length2).clamp(-0.5, 0.5)
+                        } else {
+                            0.0
+                        };
+                        let distance = (px - t * dx).hypot(py - t * dy);
+                        *weight = (1.0 - distance).max(0.0);
+                    }
+                }
+            }
+            Self::Matched(psf) => {
+                if psf.len() != K * K {
+                    return Err(Error::KernelError);
+                }
+                for (row, psf) in kernel.iter_mut().zip(psf.chunks_exact(K)) {
+                    for (weight, value) in row.iter_mut().zip(psf) {
+                        *weight = *value as f64;
+                    }
+                }
+            }
+        }
+
+        let sum = kernel.iter().flatten().sum::<f64>();
+        if sum.abs() < f64::EPSILON {
+            return Err(Error::KernelError);
+        }
+        for weight in kernel.iter_mut().flatten() {
+            *weight /= sum;
+        }
+        Ok(Some(kernel))
+    }
+
+    /// Writes the filtered [Frame] into buf, using a K x K window. The filtered
+    /// [Frame] retains the settings (bad pixels, background, threshold..) of the
+    /// input [Frame]. Bad pixels do not contribute to their neighbors.
+    /// Fails with [Error::KernelError] if K is even or the kernel is invalid,
+    /// with [Error::VideoDimensionError] if buf is too small.
+    pub fn apply<'b, const K: usize, const XY: usize, P: Pixel>(
+        &self,
+        frame: &Frame<'b, XY, P>,
+        border: Border,
+        buf: &'b mut [P; XY],
+    ) -> Result<Frame<'b, XY, P>, Error> {
+        if K % 2 == 0 {
+            return Err(Error::KernelError);
+        }
+
+        let (width, height) = (frame.width(), frame.height());
+        let max_luma = frame.max_luma() as f64;
+        if width as usize * height as usize > XY {
+            return Err(Error::VideoDimensionError);
+        }
+
+        let kernel = self.kernel::<K>()?;
+        let half = (K / 2) as i32;
+
+        // median scratch buffer
+        let mut window = [[0_u16; K]; K];
+
+        for y in 0..height {
+            for x in 0..width {
+                let luma = frame.get(x, y).map(|p| p.luma()).unwrap_or_default();
+                let mut value = luma as f64;
+
+                let (mut acc, mut weights, mut n) = (0.0_f64, 0.0_f64, 0);
+                let mut complete = true;
+
+                for dy in 0..K {
+                    let sy = border.map(y as i32 + dy as i32 - half, height);
+                    for dx in 0..K {
+                        let sx = border.map(x as i32 + dx as i32 - half, width);
+                        let (Some(sx), Some(sy)) = (sx, sy) else {
+                            complete = false;
+                            continue;
+                        };
+                        if frame.is_ignored(sx, sy) {
+                            continue;
+                        }
+                        let sample = frame.get(sx, sy).map(|p| p.luma()).unwrap_or_default();
+                        match kernel {
+                            Some(kernel) => {
+                                acc += kernel[dy][dx] * sample as f64;
+                                weights += kernel[dy][dx];
+                            }
+                            None => {
+                                window.as_flattened_mut()[n] = sample;
+                                n += 1;
+                            }
+                        }
+                    }
+                }
+
+                if complete {
+                    if kernel.is_some() {
+                        // renormalized over the valid pixels
+                        if weights > 0.0 {
+                            value = acc / weights;
+                        }
+                    } else if n > 0 {
+                        let samples = &mut window.as_flattened_mut()[..n];
+                        samples.sort_unstable();
+                        value = samples[n / 2] as f64;
+                    }
+                }
+
+                buf[y as usize * width as usize + x as usize] =
+                    P::from_luma(value.round().clamp(0.0, max_luma) as u16);
+            }
+        }
+
+        let mut filtered = *frame;
+        filtered.bitmap =
+            BitMap::from_buffer(width, height, buf)?.with_bit_depth(frame.bitmap.bit_depth());
+        Ok(filtered)
+    }
+}
@@ -0,0 +1,213 @@
+//! High dynamic range merge of bracketed exposures
+use crate::{
+    frame::{
+        candidate::{StarCandidate, StarCandidates},
+        centroid::WINDOW_MARGIN,
+        component::Pixel,
+        BitMap, Frame,
+    },
+    Error,
+};
+
+/// Default fraction of the long exposure saturation level,
+/// above which the short exposure takes over
+pub const DEFAULT_KNEE: f64 = 0.8;
+
+/// [Exposure] metadata of a [Frame], see [Frame::with_exposure]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exposure {
+    /// Exposure duration (in seconds)
+    pub duration: f64,
+    /// Sensor gain, relative to unity
+    pub gain: f64,
+    /// Black level: luminance of an unexposed pixel (native bit depth)
+    pub black_level: u16,
+}
+
+impl Exposure {
+    /// Builds new [Exposure] metadata, without black level
+    pub fn new(duration: f64, gain: f64) -> Self {
+        Self {
+            duration,
+            gain,
+            black_level: 0,
+        }
+    }
+
+    /// Returns a copy of this [Exposure] with given black level (native bit depth)
+    pub fn with_black_level(&self, black_level: u16) -> Self {
+        let mut s = *self;
+        s.black_level = black_level;
+        s
+    }
+
+    /// Returns the sensitivity: luminance per unit of scene radiance
+    pub fn sensitivity(&self) -> f64 {
+        self.duration * self.gain
+    }
+}
+
+/// [Bracket] of a short and a long exposure of the same scene. At twilight,
+/// the bright planets saturate the long exposure, while the dim stars are barely
+/// visible in the short one. Both [Frame]s must share their dimensions and origin,
+/// and are expected to be captured close enough not to require a registration.
+/// Luminances of the merged [Frame] and [StarCandidate]s are expressed in
+/// long exposure units.
+#[derive(Clone, Copy)]
+pub struct Bracket<'a, const XY: usize, P: Pixel> {
+    /// Short exposure [Frame]
+    short: Frame<'a, XY, P>,
+    /// Long exposure [Frame]
+    long: Frame<'a, XY, P>,
+    /// Sensitivity ratio between the long and short exposures
+    ratio: f64,
+    /// Fraction of the long exposure saturation level, above which
+    /// the short exposure is progressively used
+    pub knee: f64,
+}
+
+impl<'a, const XY: usize, P: Pixel> Bracket<'a, XY, P> {
+    /// Builds a new [Bracket] from two exposures, in any order.
+    /// Fails with [Error::ExposureError] if a [Frame] misses its [Exposure]
+    /// metadata or has no sensitivity, with [Error::VideoDimensionError]
+    /// if the [Frame]s do not overlap exactly.
+    pub fn new(first: Frame<'a, XY, P>, second: Frame<'a, XY, P>) -> Result<Self, Error> {
+        let (Some(a), Some(b)) = (first.exposure(), second.exposure()) else {
+            return Err(Error::ExposureError);
+        };
+        if a.sensitivity() <= 0.0 || b.sensitivity() <= 0.0 {
+            return Err(Error::ExposureError);
+        }
+        if (first.width(), first.height(), first.origin())
+            != (second.width(), second.height(), second.origin())
+        {
+            return Err(Error::VideoDimensionError);
+        }
+
+        let (short, long) = if a.sensitivity() <= b.sensitivity() {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        Ok(Self {
+            short,
+            long,
+            ratio: a.sensitivity().max(b.sensitivity()) / a.sensitivity().min(b.sensitivity()),
+            knee: DEFAULT_KNEE,
+        })
+    }
+
+    /// Returns a copy of this [Bracket] with given knee, see [Bracket::knee]
+    pub fn with_knee(&self, knee: f64) -> Self {
+        let mut s = *self;
+        s.knee = knee;
+        s
+    }
+
+    /// Returns the short exposure [Frame]
+    pub fn short(&self) -> &Frame<'a, XY, P> {
+        &self.short
+    }
+
+    /// Returns the long exposure [Frame]
+    pub fn long(&self) -> &Frame<'a, XY, P> {
+        &self.long
+    }
+
+    /// Returns the sensitivity ratio between the long and short exposures
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// Converts a short exposure luminance to long exposure units
+    fn long_units(&self, luma: f64) -> f64 {
+        let short = self
+            .short
+            .exposure
+            .map(|e| e.black_level)
+            .unwrap_or_default() as f64;
+        let long = self
+            .long
+            .exposure
+            .map(|e| e.black_level)
+            .unwrap_or_default() as f64;
+        (luma - short) * self.ratio + long
+    }
+
+    /// Writes the high dynamic range [Frame] into buf, in long exposure units.
+    /// Pixels below the knee come from the long exposure, saturated pixels from
+    /// the short one, both being blended in between. Choose Q deep enough
+    /// for the extended range (e.g. Gray16 for Gray8 exposures): values are
+    /// clamped to [Pixel::MAX_LUMA]. The merged [Frame] retains the long exposure
+    /// settings that do not depend on the pixel format (bad pixels, background,
+    /// masks, centroiding..), and saturates where the short exposure does.
+    /// The [crate::frame::threshold::Threshold] strategy is specific to P:
+    /// attach one to the merged [Frame] if needed.
+    /// Fails with [Error::VideoDimensionError] if buf is too small.
+    pub fn merge<'b, Q: Pixel>(&self, buf: &'b mut [Q; XY]) -> Result<Frame<'b, XY, Q>, Error>
+    where
+        'a: 'b,
+    {
+        let (width, height) = (self.long.width(), self.long.height());
+        if width as usize * height as usize > XY {
+            return Err(Error::VideoDimensionError);
+        }
+
+        let saturation = self.long.saturation_level() as f64;
+        let knee = self.knee * saturation;
+
+        for y in 0..height {
+            for x in 0..width {
+                let long = self.long.get(x, y).map(|p| p.luma()).unwrap_or_default() as f64;
+                let short = self.short.get(x, y).map(|p| p.luma()).unwrap_or_default() as f64;
+
+                let weight = if saturation > knee {
+                    ((saturation - long) / (saturation - knee)).clamp(0.0, 1.0)
+                } else {
+                    (long < saturation) as u8 as f64
+                };
+                let value = weight * long + (1.0 - weight) * self.long_units(short);
+
+                buf[y as usize * width as usize + x as usize] =
+                    Q::from_luma(value.round().clamp(0.0, Q::MAX_LUMA as f64) as u16);
+            }
+        }
+
+        let mut merged = self
+            .long
+            .with_bitmap(BitMap::from_buffer(width, height, buf)?);
+        merged.saturation = Some(
+            self.long_units(self.short.saturation_level() as f64)
+                .round()
+                .clamp(0.0, Q::MAX_LUMA as f64) as u16,
+        );
+        Ok(merged)
+    }
+
+    /// Detects the N brightest [StarCandidates] in the long exposure, by decreasing
+    /// flux, like [Frame::star_candidates]. Stars saturated in the long exposure are
+    /// measured in the short one instead (over their centroiding window), then converted to long exposure units:
+    /// faint stars are centroided from the long exposure, bright ones from the short.
+    /// bits is the detection [crate::frame::mask::Mask] storage.
+    pub fn star_candidates<const N: usize>(
+        &self,
+        bits: &mut [u32],
+    ) -> Result<StarCandidates<N>, Error> {
+        self.long.measure_candidates(bits, |blob| {
+            let candidate = StarCandidate::measure(&self.long, blob)?;
+            if !candidate.saturated {
+                return Ok(candidate);
+            }
+            let mut bright =
+                StarCandidate::measure(&self.short, &blob.resample(&self.short, WINDOW_MARGIN))?;
+            bright.flux *= self.ratio;
+            bright.background = self.long_units(bright.background);
+            bright.peak = self
+                .long_units(bright.peak as f64)
+                .round()
+                .clamp(0.0, u16::MAX as f64) as u16;
+            Ok(bright)
+        })
+    }
+}
@@ -1,31 +1,167 @@
+//! Luminance histogram and automatic thresholds
+use crate::frame::{component::Pixel, Frame};
 
-// pub(crate) struct Histogram64<T> {
-//     pub bins: [T; 64],
-//     pub populations: [usize; 64],
-// }
-
-// impl<T> Histogram64<T> {
-
-//     /// Returns maximal population found in this [Histogram]
-//     pub fn max_population(&self) -> usize {
-//         let mut max = 0;
-//         for pop in &self.populations {
-//             if *pop > max {
-//                 max = *pop;
-//             }
-//         }
-//         max
-//     }
-
-//     /// Returns bin quantization for this population
-//     pub fn get_by_min_population(&self, population: &usize) -> Option<&T> {
-//         for (index, pop) in self.populations.iter().enumerate() {
-//             if index > 0 {
-//                 if pop >= population {
-//                     return self.bins.get(index);
-//                 }
-//             }
-//         }
-//         None
-//     }
-// }
\ No newline at end of file
+/// [Histogram] of the luminance, made of B bins evenly spread over the
+/// native dynamic range (0..=max luma). Use 256 bins for exact 8 bit
+/// histograms, fewer bins for high bit-depth sensors. The bins are stored
+/// inline, so the [Histogram] does not allocate.
+#[derive(Debug, Clone, Copy)]
+pub struct Histogram<const B: usize> {
+    counts: [u32; B],
+    max_luma: u16,
+    bin_width: u32,
+    total: u32,
+}
+
+impl<const B: usize> Histogram<B> {
+    /// Builds an empty [Histogram] covering 0..=max_luma
+    pub fn new(max_luma: u16) -> Self {
+        Self {
+            counts: [0; B],
+            max_luma,
+            bin_width: (max_luma as u32 + 1).div_ceil(B.max(1) as u32),
+            total: 0,
+        }
+    }
+
+    /// Builds the [Histogram] of a [Frame], at native bit depth.
+    /// Pixels ignored by the [Frame] (bad pixels) are not counted.
+    pub fn from_frame<const XY: usize, P: Pixel>(frame: &Frame<'_, XY, P>) -> Self {
+        let mut histogram = Self::new(frame.max_luma());
+        for luma in frame.valid_lumas() {
+            histogram.add(luma);
+        }
+        histogram
+    }
+
+    /// Adds one luminance sample
+    pub fn add(&mut self, luma: u16) {
+        let bin = self.bin(luma);
+        if let Some(count) = self.counts.get_mut(bin) {
+            *count += 1;
+            self.total += 1;
+        }
+    }
+
+    /// Returns number of bins
+    pub fn bins(&self) -> usize {
+        B
+    }
+
+    /// Returns luminance range covered by each bin
+    pub fn bin_width(&self) -> u32 {
+        self.bin_width
+    }
+
+    /// Returns total number of samples
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// Returns population of given bin
+    pub fn count(&self, bin: usize) -> u32 {
+        self.counts.get(bin).copied().unwrap_or_default()
+    }
+
+    /// Returns bin index of given luminance
+    pub fn bin(&self, luma: u16) -> usize {
+        (luma.min(self.max_luma) as u32 / self.bin_width) as usize
+    }
+
+    /// Returns lowest luminance of given bin
+    pub fn bin_luma(&self, bin: usize) -> u16 {
+        (bin as u32 * self.bin_width).min(self.max_luma as u32) as u16
+    }
+
+    /// Returns the most populated bin
+    pub fn mode(&self) -> usize {
+        let mut mode = 0;
+        for (bin, count) in self.counts.iter().enumerate() {
+            if *count > self.counts[mode] {
+                mode = bin;
+            }
+        }
+        mode
+    }
+
+    /// Returns luminance below which lies given fraction (0.0..=1.0)
+    /// of the samples (bin resolution)
+    pub fn percentile(&self, fraction: f64) -> u16 {
+        let target = (fraction.clamp(0.0, 1.0) * self.total as f64).ceil() as u64;
+        let mut cumulated = 0_u64;
+        for (bin, count) in self.counts.iter().enumerate() {
+            cumulated += *count as u64;
+            if cumulated >= target.max(1) {
+                return self.bin_luma(bin);
+            }
+        }
+        self.max_luma
+    }
+
+    /// Returns median luminance (bin resolution)
+    pub fn median(&self) -> u16 {
+        self.percentile(0.5)
+    }
+
+    /// Otsu's method: returns the luminance threshold that maximizes
+    /// the between-class variance. Pixels at or above the threshold
+    /// are foreground. Best suited to bimodal histograms
+    /// (large bright objects over the sky background).
+    pub fn otsu(&self) -> u16 {
+        let total = self.total as f64;
+        let sum: f64 = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(bin, count)| bin as f64 * *count as f64)
+            .sum();
+
+        let (mut background, mut background_sum) = (0.0_f64, 0.0_f64);
+        let (mut best, mut best_variance) = (0, 0.0_f64);
+
+        for (bin, count) in self.counts.iter().enumerate() {
+            if background > 0.0 && background < total {
+                let foreground = total - background;
+                let delta = background_sum / background - (sum - background_sum) / foreground;
+                let variance = background * foreground * delta * delta;
+                if variance > best_variance {
+                    best = bin;
+                    best_variance = variance;
+                }
+            }
+            background += *count as f64;
+            background_sum += bin as f64 * *count as f64;
+        }
+
+        self.bin_luma(best)
+    }
+
+    /// Triangle method: draws a line from the histogram peak to the
+    /// brightest populated bin, and returns the luminance of the bin
+    /// that lies the furthest below that line. Pixels at or above the
+    /// threshold are foreground. Best suited to star fields, where
+    /// stars are a faint tail of a dominant background peak.
+    pub fn triangle(&self) -> u16 {
+        let peak = self.mode();
+        let end = match self.counts.iter().rposition(|count| *count > 0) {
+            Some(end) if end > peak => end,
+            _ => return self.bin_luma(peak + 1),
+        };
+
+        let (x0, y0) = (peak as f64, self.counts[peak] as f64);
+        let (x1, y1) = (end as f64, self.counts[end] as f64);
+
+        let (mut best, mut best_distance) = (peak + 1, 0.0_f64);
+        for bin in peak + 1..=end {
+            // distance to the line, up to a constant factor
+            let distance =
+                (y1 - y0) * bin as f64 - (x1 - x0) * self.counts[bin] as f64 + x1 * y0 - y1 * x0;
+            if distance > best_distance {
+                best = bin;
+                best_distance = distance;
+            }
+        }
+
+        self.bin_luma(best)
+    }
+}
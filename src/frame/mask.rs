@@ -0,0 +1,239 @@
+//! Binary pixel masks
+use crate::{frame::blob::Connectivity, Error};
+
+/// [Morphology] operation applied to the detection [Mask],
+/// see [crate::frame::Frame::with_morphology]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Morphology {
+    /// See [Mask::erode]
+    Erosion,
+    /// See [Mask::dilate]
+    Dilation,
+    /// See [Mask::open]
+    Opening,
+    /// See [Mask::close]
+    Closing,
+}
+
+impl Morphology {
+    /// Applies this [Morphology] operation to given [Mask].
+    /// scratch is a working [Mask] of the same dimensions.
+    pub fn apply(
+        &self,
+        mask: &mut Mask<'_>,
+        connectivity: Connectivity,
+        scratch: &mut Mask<'_>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Erosion => mask.erode(connectivity, scratch),
+            Self::Dilation => mask.dilate(connectivity, scratch),
+            Self::Opening => mask.open(connectivity, scratch),
+            Self::Closing => mask.close(connectivity, scratch),
+        }
+    }
+}
+
+/// [Mask] is a (width, height) binary image, packed 32 pixels per word,
+/// stored within a caller provided buffer (no heap allocation).
+/// Use [Mask::words] to size the buffer.
+#[derive(Debug)]
+pub struct Mask<'a> {
+    width: u16,
+    height: u16,
+    bits: &'a mut [u32],
+}
+
+impl<'a> Mask<'a> {
+    /// Returns number of words required to store a (width, height) [Mask]
+    pub const fn words(width: u16, height: u16) -> usize {
+        (width as usize * height as usize).div_ceil(32)
+    }
+
+    /// Builds a new cleared (width, height) [Mask] within bits.
+    /// Fails with [Error::VideoDimensionError] if bits is too short.
+    pub fn new(width: u16, height: u16, bits: &'a mut [u32]) -> Result<Self, Error> {
+        if bits.len() < Self::words(width, height) {
+            return Err(Error::VideoDimensionError);
+        }
+        let mut mask = Self {
+            width,
+            height,
+            bits,
+        };
+        mask.clear();
+        Ok(mask)
+    }
+
+    /// Returns width of this [Mask] (in pixels)
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Returns height of this [Mask] (in pixels)
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Returns (word, bit) position of (x, y)
+    fn position(&self, x: u16, y: u16) -> Option<(usize, u32)> {
+        if x < self.width && y < self.height {
+            let index = y as usize * self.width as usize + x as usize;
+            Some((index / 32, 1 << (index % 32)))
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if (x, y) is set. Pixels out of bounds are never set.
+    pub fn get(&self, x: u16, y: u16) -> bool {
+        match self.position(x, y) {
+            Some((word, bit)) => self.bits[word] & bit != 0,
+            None => false,
+        }
+    }
+
+    /// Sets or clears (x, y). Pixels out of bounds are ignored.
+    pub fn set(&mut self, x: u16, y: u16, value: bool) {
+        if let Some((word, bit)) = self.position(x, y) {
+            if value {
+                self.bits[word] |= bit;
+            } else {
+                self.bits[word] &= !bit;
+            }
+        }
+    }
+
+    /// Clears all pixels
+    pub fn clear(&mut self) {
+        let words = Self::words(self.width, self.height);
+        self.bits[..words].fill(0);
+    }
+
+    /// Returns number of pixels set
+    pub fn count(&self) -> usize {
+        let words = Self::words(self.width, self.height);
+        self.bits[..words]
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Sets every pixel that is set in rhs (logical or).
+    /// Both [Mask]s should share the same dimensions.
+    pub fn union(&mut self, rhs: &Mask<'_>) -> Result<(), Error> {
+        if (self.width, self.height) != (rhs.width, rhs.height) {
+            return Err(Error::VideoDimensionError);
+        }
+        let words = Self::words(self.width, self.height);
+        for (lhs, rhs) in self.bits[..words].iter_mut().zip(rhs.bits.iter()) {
+            *lhs |= rhs;
+        }
+        Ok(())
+    }
+
+    /// Clears every pixel that is set in rhs (logical and not).
+    /// Both [Mask]s should share the same dimensions.
+    pub fn subtract(&mut self, rhs: &Mask<'_>) -> Result<(), Error> {
+        if (self.width, self.height) != (rhs.width, rhs.height) {
+            return Err(Error::VideoDimensionError);
+        }
+        let words = Self::words(self.width, self.height);
+        for (lhs, rhs) in self.bits[..words].iter_mut().zip(rhs.bits.iter()) {
+            *lhs &= !rhs;
+        }
+        Ok(())
+    }
+
+    /// Copies rhs into this [Mask].
+    /// Both [Mask]s should share the same dimensions.
+    pub fn copy_from(&mut self, rhs: &Mask<'_>) -> Result<(), Error> {
+        if (self.width, self.height) != (rhs.width, rhs.height) {
+            return Err(Error::VideoDimensionError);
+        }
+        let words = Self::words(self.width, self.height);
+        self.bits[..words].copy_from_slice(&rhs.bits[..words]);
+        Ok(())
+    }
+
+    /// Iterates the neighbors of (x, y) within this [Mask]
+    fn neighbors(
+        &self,
+        x: u16,
+        y: u16,
+        connectivity: Connectivity,
+    ) -> impl Iterator<Item = bool> + '_ {
+        connectivity.offsets().iter().filter_map(move |(dx, dy)| {
+            let (x, y) = (x as i32 + dx, y as i32 + dy);
+            if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+                Some(self.get(x as u16, y as u16))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Binary erosion: clears every pixel that has a cleared neighbor.
+    /// Removes isolated noise pixels and thin bridges between stars.
+    /// Neighbors out of the [Mask] do not take part, so stars touching
+    /// the edges are not eroded more than the others.
+    /// scratch is a working [Mask] of the same dimensions.
+    pub fn erode(
+        &mut self,
+        connectivity: Connectivity,
+        scratch: &mut Mask<'_>,
+    ) -> Result<(), Error> {
+        scratch.copy_from(self)?;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if scratch.get(x, y) && !scratch.neighbors(x, y, connectivity).all(|n| n) {
+                    self.set(x, y, false);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Binary dilation: sets every pixel that has a set neighbor.
+    /// Fills holes and grows the star footprints.
+    /// scratch is a working [Mask] of the same dimensions.
+    pub fn dilate(
+        &mut self,
+        connectivity: Connectivity,
+        scratch: &mut Mask<'_>,
+    ) -> Result<(), Error> {
+        scratch.copy_from(self)?;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !scratch.get(x, y) && scratch.neighbors(x, y, connectivity).any(|n| n) {
+                    self.set(x, y, true);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Binary opening (erosion then dilation): removes the detections
+    /// smaller than the structuring element, single pixel noise typically,
+    /// while preserving the shape of the larger ones.
+    /// scratch is a working [Mask] of the same dimensions.
+    pub fn open(
+        &mut self,
+        connectivity: Connectivity,
+        scratch: &mut Mask<'_>,
+    ) -> Result<(), Error> {
+        self.erode(connectivity, scratch)?;
+        self.dilate(connectivity, scratch)
+    }
+
+    /// Binary closing (dilation then erosion): fills the small holes
+    /// and gaps of the detections, while preserving their shape.
+    /// scratch is a working [Mask] of the same dimensions.
+    pub fn close(
+        &mut self,
+        connectivity: Connectivity,
+        scratch: &mut Mask<'_>,
+    ) -> Result<(), Error> {
+        self.dilate(connectivity, scratch)?;
+        self.erode(connectivity, scratch)
+    }
+}
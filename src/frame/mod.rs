@@ -1,5 +1,5 @@
 //! Basic video frame definitions
-// use crate::Error;
+use crate::Error;
 
 // use itertools::{IntoChunks, Itertools};
 
@@ -7,35 +7,291 @@
 
 use geo::Coord;
 
+pub mod background;
+pub mod bad_pixels;
+pub mod blob;
+pub mod calibration;
+pub mod candidate;
+pub mod centroid;
 pub mod component;
-use component::UnderlyingComponent;
+pub mod filter;
+pub mod hdr;
+pub mod histogram;
+pub mod mask;
+pub mod motion;
+pub mod pyramid;
+pub mod quality;
+pub mod selection;
+pub mod sky;
+pub mod stack;
+pub mod streak;
+pub mod threshold;
+
+use background::Background;
+use bad_pixels::BadPixelMap;
+use blob::{Blob, Blobs, Connectivity};
+use candidate::{StarCandidate, StarCandidates};
+use centroid::{Centroid, Centroiding};
+use component::{Pixel, UnderlyingComponent};
+use filter::{Border, Filter};
+use hdr::Exposure;
+use mask::{Mask, Morphology};
+use motion::Motion;
+use quality::{Quality, QualityCriteria};
+use selection::{Selection, MAX_CANDIDATES};
+use streak::{StreakDetector, Streaks, MAX_STREAKS};
+use threshold::{SigmaK, Threshold};
 
 
 // private modules
 mod bitmap;
 
-pub use bitmap::{BitMap, BitMapIter};
+pub use bitmap::{BitMap, BitMapIter, YuvBitMap, YuvFormat};
+
+#[cfg(feature = "std")]
+pub use bitmap::OwnedBitMap;
 
-/// [Frame] describes a Video Frame of maximal (X, Y) dimension
-pub struct Frame<'a, const XY: usize> {
-    x: usize,
-    y: usize,
-    bitmap: BitMap<'a, XY>,
+/// [Frame] describes a Video Frame of maximal (X, Y) dimension,
+/// made of P [Pixel]s.
+pub struct Frame<'a, const XY: usize, P: Pixel = UnderlyingComponent> {
+    bitmap: BitMap<'a, XY, P>,
+    /// (x, y) origin within the full sensor frame,
+    /// for region of interest [Frame]s
+    origin: (u16, u16),
+    /// [BadPixelMap] (if any), expressed in full sensor frame coordinates
+    bad_pixels: Option<&'a BadPixelMap<'a>>,
+    /// Local [Background] (if any), expressed in full sensor frame coordinates
+    background: Option<&'a Background<'a>>,
+    /// [Threshold] strategy (if any), [SigmaK] by default
+    threshold: Option<&'a dyn Threshold<XY, P>>,
+    /// [Centroiding] method
+    centroiding: Centroiding,
+    /// Luminance at or above which a pixel is saturated (if any),
+    /// maximal luminance of the [BitMap] by default
+    saturation: Option<u16>,
+    /// Star [Selection] policy
+    selection: Selection,
+    /// Deblending contrast (if any)
+    deblending: Option<f64>,
+    /// [Morphology] operation applied to the detection [Mask] (if any)
+    morphology: Option<(Morphology, Connectivity)>,
+    /// Excluded pixels (if any), expressed in full sensor frame coordinates
+    exclusion: Option<&'a Mask<'a>>,
+    /// [StreakDetector] (if any), rejecting the star candidates along streaks
+    streaks: Option<StreakDetector>,
+    /// Camera [Motion] during the exposure (if any)
+    motion: Option<Motion>,
+    /// [Exposure] metadata (if any)
+    exposure: Option<Exposure>,
 }
 
+impl<const XY: usize, P: Pixel> Clone for Frame<'_, XY, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
 
-impl<'a, const XY: usize> Frame<'a, XY> {
+impl<const XY: usize, P: Pixel> Copy for Frame<'_, XY, P> {}
+
+impl<'a, const XY: usize, P: Pixel> Frame<'a, XY, P> {
     /// Create a new video [Frame]
     /// ## Input
     /// - x: frame width (in pixels)
     /// - y: frame height (in pixels)
     /// - bitmap: array of x*y dimension capture by video source.
-    pub fn new(x: usize, y: usize, bitmap: BitMap<'a, XY>) -> Self {
-        Self { x, y, bitmap }
+    ///
+    /// Fails with [Error::VideoDimensionError] if x and y
+    /// do not match the [BitMap] dimensions.
+    pub fn new(x: usize, y: usize, bitmap: BitMap<'a, XY, P>) -> Result<Self, Error> {
+        if (x, y) != (bitmap.width() as usize, bitmap.height() as usize) {
+            return Err(Error::VideoDimensionError);
+        }
+        Ok(Self {
+            bitmap,
+            origin: (0, 0),
+            bad_pixels: None,
+            background: None,
+            threshold: None,
+            centroiding: Centroiding::default(),
+            saturation: None,
+            selection: Selection::default(),
+            deblending: None,
+            morphology: None,
+            exclusion: None,
+            streaks: None,
+            motion: None,
+            exposure: None,
+        })
+    }
+
+    /// Returns a copy of this [Frame] where pixels flagged by the
+    /// [BadPixelMap] are ignored by the statistics and the star detection.
+    /// See [BadPixelMap::interpolate] to replace them instead.
+    pub fn with_bad_pixels(&self, bad_pixels: &'a BadPixelMap<'a>) -> Self {
+        let mut s = *self;
+        s.bad_pixels = Some(bad_pixels);
+        s
+    }
+
+    /// Returns a copy of this [Frame] where pixels set in the exclusion [Mask]
+    /// (streaks, non sky regions..) are ignored like bad pixels. See
+    /// [streak::Streaks::mask] and [sky::SkySegmentation::segment] to produce it.
+    /// The [Mask] is expressed in full sensor frame coordinates.
+    pub fn with_exclusion_mask(&self, exclusion: &'a Mask<'a>) -> Self {
+        let mut s = *self;
+        s.exclusion = Some(exclusion);
+        s
+    }
+
+    /// Returns a copy of this [Frame] where the [StreakDetector] identifies
+    /// satellite or aircraft trails, and the star finder rejects the
+    /// candidates lying along them.
+    pub fn with_streak_rejection(&self, detector: StreakDetector) -> Self {
+        let mut s = *self;
+        s.streaks = Some(detector);
+        s
+    }
+
+    /// Returns a copy of this [Frame] where each pixel is thresholded
+    /// against its local [Background], instead of the global statistics.
+    pub fn with_background(&self, background: &'a Background<'a>) -> Self {
+        let mut s = *self;
+        s.background = Some(background);
+        s
+    }
+
+    /// Returns a copy of this [Frame] using given [Threshold] strategy
+    /// for the star detection, instead of the default [SigmaK].
+    pub fn with_threshold(&self, threshold: &'a dyn Threshold<XY, P>) -> Self {
+        let mut s = *self;
+        s.threshold = Some(threshold);
+        s
+    }
+
+    /// Returns a copy of this [Frame] captured while the camera was rotating:
+    /// the centroiding follows the expected star streaks, and returns
+    /// the mid-exposure positions.
+    pub fn with_motion(&self, motion: Motion) -> Self {
+        let mut s = *self;
+        s.motion = Some(motion);
+        s
+    }
+
+    /// Returns a copy of this [Frame] described by given [Exposure] metadata,
+    /// see [hdr::Bracket] to merge bracketed exposures.
+    pub fn with_exposure(&self, exposure: Exposure) -> Self {
+        let mut s = *self;
+        s.exposure = Some(exposure);
+        s
+    }
+
+    /// Returns [Exposure] metadata of this [Frame] (if any)
+    pub fn exposure(&self) -> Option<Exposure> {
+        self.exposure
+    }
+
+    /// Returns the expected (dx, dy) blur (in pixels) of given [Blob]
+    /// over the exposure, zero without [Motion]
+    pub(crate) fn blur(&self, blob: &Blob) -> Coord<f64> {
+        match self.motion {
+            Some(motion) => {
+                let centroid = blob.centroid();
+                motion.blur(Coord {
+                    x: self.origin.0 as f64 + centroid.x,
+                    y: self.origin.1 as f64 + centroid.y,
+                })
+            }
+            None => Coord::zero(),
+        }
     }
 
-    /// Obtain reference to underlying video component, expressed as [UnderlyingPixel].
-    pub(crate) fn get(&self, x: u16, y: u16) -> Option<&UnderlyingComponent> {
+    /// Returns a copy of this [Frame] using given [Centroiding] method,
+    /// instead of the default [Centroiding::WeightedCenterOfGravity].
+    pub fn with_centroiding(&self, centroiding: Centroiding) -> Self {
+        let mut s = *self;
+        s.centroiding = centroiding;
+        s
+    }
+
+    /// Returns a copy of this [Frame] where pixels at or above given luminance
+    /// (native bit depth) are considered saturated, instead of [Self::max_luma].
+    /// Useful for sensors that clip below their full scale.
+    pub fn with_saturation_level(&self, saturation: u16) -> Self {
+        let mut s = *self;
+        s.saturation = Some(saturation);
+        s
+    }
+
+    /// Returns a copy of this [Frame] using given star [Selection] policy,
+    /// instead of the brightest stars regardless of their position.
+    pub fn with_selection(&self, selection: Selection) -> Self {
+        let mut s = *self;
+        s.selection = selection;
+        s
+    }
+
+    /// Returns a copy of this [Frame] where touching stars are split
+    /// into separate [Blob]s, with given contrast. See [Blob::deblend].
+    pub fn with_deblending(&self, contrast: f64) -> Self {
+        let mut s = *self;
+        s.deblending = Some(contrast);
+        s
+    }
+
+    /// Returns a copy of this [Frame] where the given [Morphology] operation
+    /// cleans the detection [Mask] before the [Blob]s are extracted (e.g.
+    /// [Morphology::Opening] removes the single pixel noise detections).
+    /// The detection storage (bits) must then hold two [Mask]s:
+    /// see [Mask::words] and size it twice.
+    pub fn with_morphology(&self, morphology: Morphology, connectivity: Connectivity) -> Self {
+        let mut s = *self;
+        s.morphology = Some((morphology, connectivity));
+        s
+    }
+
+    /// Returns luminance at or above which a pixel is saturated
+    pub fn saturation_level(&self) -> u16 {
+        self.saturation.unwrap_or(self.max_luma())
+    }
+
+    /// Returns (x, y) origin of this [Frame], within the full sensor frame
+    pub fn origin(&self) -> (u16, u16) {
+        self.origin
+    }
+
+    /// Converts (x, y) [Frame] coordinates to full sensor frame coordinates,
+    /// saturated to the sensor range
+    pub(crate) fn sensor_coordinates(&self, x: u16, y: u16) -> (u16, u16) {
+        (
+            self.origin.0.saturating_add(x),
+            self.origin.1.saturating_add(y),
+        )
+    }
+
+    /// Returns true if (x, y) pixel should be ignored
+    pub(crate) fn is_ignored(&self, x: u16, y: u16) -> bool {
+        let (x, y) = self.sensor_coordinates(x, y);
+        let bad = match self.bad_pixels {
+            Some(bad_pixels) => bad_pixels.is_bad(x, y),
+            None => false,
+        };
+        let excluded = match self.exclusion {
+            Some(exclusion) => exclusion.get(x, y),
+            None => false,
+        };
+        bad || excluded
+    }
+
+    /// Iterates the luminance of all pixels that are not ignored, at native bit depth
+    pub(crate) fn valid_lumas(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..self.height())
+            .flat_map(move |y| (0..self.width()).map(move |x| (x, y)))
+            .filter(|(x, y)| !self.is_ignored(*x, *y))
+            .filter_map(|(x, y)| self.get(x, y).map(|p| p.luma()))
+    }
+
+    /// Obtain reference to underlying video component, expressed as [Pixel].
+    pub fn get(&self, x: u16, y: u16) -> Option<&P> {
         self.bitmap.get(x, y)
     }
 
@@ -50,7 +306,93 @@ impl<'a, const XY: usize> Frame<'a, XY> {
 
     /// Returns total area of this [Frame]
     pub fn area(&self) -> usize {
-        self.x * self.y
+        self.bitmap.width() as usize * self.bitmap.height() as usize
+    }
+
+    /// Returns width of this [Frame] (in pixels)
+    pub fn width(&self) -> u16 {
+        self.bitmap.width()
+    }
+
+    /// Returns height of this [Frame] (in pixels)
+    pub fn height(&self) -> u16 {
+        self.bitmap.height()
+    }
+
+    /// Returns a [Frame] over given [BitMap], possibly of another [Pixel] format,
+    /// retaining the settings of this [Frame] that do not depend on it: all but
+    /// the [Threshold] strategy and the saturation level, which fall back
+    /// to their defaults.
+    pub(crate) fn with_bitmap<'b, Q: Pixel>(&self, bitmap: BitMap<'b, XY, Q>) -> Frame<'b, XY, Q>
+    where
+        'a: 'b,
+    {
+        Frame {
+            bitmap,
+            origin: self.origin,
+            bad_pixels: self.bad_pixels,
+            background: self.background,
+            threshold: None,
+            centroiding: self.centroiding,
+            saturation: None,
+            selection: self.selection,
+            deblending: self.deblending,
+            morphology: self.morphology,
+            exclusion: self.exclusion,
+            streaks: self.streaks,
+            motion: self.motion,
+            exposure: self.exposure,
+        }
+    }
+
+    /// Returns reference to the underlying [BitMap]
+    pub fn bitmap(&self) -> &BitMap<'a, XY, P> {
+        &self.bitmap
+    }
+
+    /// Returns a copy of this [Frame] which luminances span bit_depth bits,
+    /// like 10 or 12 bit sensor data stored as [Gray16](component::Gray16).
+    /// See [BitMap::with_bit_depth].
+    pub fn with_bit_depth(&self, bit_depth: u8) -> Self {
+        let mut s = *self;
+        s.bitmap = self.bitmap.with_bit_depth(bit_depth);
+        s
+    }
+
+    /// Returns maximal luminance of this [Frame], at native bit depth
+    pub fn max_luma(&self) -> u16 {
+        self.bitmap.max_luma()
+    }
+
+    /// Writes this [Frame], filtered over a K x K window, into buf.
+    /// Typically applied before the star detection. See [Filter::apply].
+    pub fn filter<'b, const K: usize>(
+        &self,
+        filter: &Filter<'_>,
+        border: Border,
+        buf: &'b mut [P; XY],
+    ) -> Result<Frame<'b, XY, P>, Error>
+    where
+        'a: 'b,
+    {
+        filter.apply::<K, XY, P>(self, border, buf)
+    }
+
+    /// Returns a zero-copy region of interest of this [Frame], with
+    /// (x, y) origin and (width, height) dimensions. Typically used to
+    /// run the star detection around predicted star positions.
+    /// Fails with [Error::VideoDimensionError] if the region exceeds this [Frame],
+    /// or if its origin exceeds the sensor coordinates range.
+    pub fn roi(&self, x: u16, y: u16, width: u16, height: u16) -> Result<Self, Error> {
+        let bitmap = self.bitmap.roi(x, y, width, height)?;
+        let (Some(x0), Some(y0)) = (self.origin.0.checked_add(x), self.origin.1.checked_add(y))
+        else {
+            return Err(Error::VideoDimensionError);
+        };
+        let mut roi = *self;
+        roi.bitmap = bitmap;
+        roi.origin = (x0, y0);
+        Ok(roi)
     }
 
     // /// Converts [Frame] to gray8
@@ -135,47 +477,271 @@ impl<'a, const XY: usize> Frame<'a, XY> {
     //     max
     // }
 
-    /// Computes the maximal value in gray scale
-    fn gray_scale_mean(&self) -> f64 {
-        let mut acc = 0.0_f64;
-        for p in self.bitmap.iter() {
-            acc += p.to_gray8() as f64;
+    /// Computes the mean luminance, at native bit depth
+    pub fn luma_mean(&self) -> f64 {
+        let (mut acc, mut n) = (0.0_f64, 0);
+        for luma in self.valid_lumas() {
+            acc += luma as f64;
+            n += 1;
+        }
+        acc / n.max(1) as f64
+    }
+
+    /// Compute std deviation of the luminance, at native bit depth
+    pub fn luma_std_dev(&self, mean: f64) -> f64 {
+        let (mut acc, mut n) = (0.0_f64, 0);
+        for luma in self.valid_lumas() {
+            acc += (luma as f64 - mean).powi(2);
+            n += 1;
+        }
+        (acc / n.max(1) as f64).sqrt()
+    }
+
+    /// Computes robust (mean, std deviation) of the luminance, at native bit depth,
+    /// by iteratively rejecting samples further than k std deviations from the mean.
+    /// Stars and hot pixels are rejected, leaving the background statistics.
+    pub fn luma_clipped_stats(&self, k: f64, iterations: usize) -> (f64, f64) {
+        let mut mean = self.luma_mean();
+        let mut stddev = self.luma_std_dev(mean);
+        for _ in 0..iterations {
+            let (low, high) = (mean - k * stddev, mean + k * stddev);
+            let (mut acc, mut acc2, mut n) = (0.0_f64, 0.0_f64, 0);
+            for luma in self.valid_lumas() {
+                let luma = luma as f64;
+                if luma >= low && luma <= high {
+                    acc += luma;
+                    acc2 += luma * luma;
+                    n += 1;
+                }
+            }
+            if n == 0 {
+                break;
+            }
+            let clipped_mean = acc / n as f64;
+            let clipped_stddev = (acc2 / n as f64 - clipped_mean.powi(2)).max(0.0).sqrt();
+            let converged = clipped_stddev == stddev;
+            (mean, stddev) = (clipped_mean, clipped_stddev);
+            if converged {
+                break;
+            }
+        }
+        (mean, stddev)
+    }
+
+    /// Flags, within mask, the pixels that may belong to a star, using the
+    /// [Threshold] strategy of this [Frame]. Returns the number of flagged pixels.
+    /// Fails with [Error::VideoDimensionError] if mask dimensions
+    /// do not match this [Frame].
+    pub fn detect(&self, mask: &mut Mask<'_>) -> Result<usize, Error> {
+        match self.threshold {
+            Some(threshold) => threshold.detect(self, mask),
+            None => SigmaK::default().detect(self, mask),
+        }
+    }
+
+    /// Flags, within mask, the pixels brighter than the background by more
+    /// than k std deviations. Each pixel is compared to its local [Background]
+    /// when defined, to the global statistics otherwise.
+    /// Ignored pixels are never flagged. Returns the number of flagged pixels.
+    /// Fails with [Error::VideoDimensionError] if mask dimensions
+    /// do not match this [Frame].
+    pub fn detection_mask(&self, k: f64, mask: &mut Mask<'_>) -> Result<usize, Error> {
+        let (width, height) = (self.width(), self.height());
+        if (mask.width(), mask.height()) != (width, height) {
+            return Err(Error::VideoDimensionError);
+        }
+
+        let global = match self.background {
+            Some(_) => 0.0,
+            None => {
+                let mean = self.luma_mean();
+                mean + k * self.luma_std_dev(mean)
+            }
+        };
+
+        let mut count = 0;
+        for y in 0..height {
+            for x in 0..width {
+                let threshold = match self.background {
+                    Some(background) => {
+                        background.threshold(self.origin.0 + x, self.origin.1 + y, k)
+                    }
+                    None => global,
+                };
+                let luma = self.get(x, y).map(|p| p.luma()).unwrap_or_default();
+                let detected = !self.is_ignored(x, y) && luma as f64 > threshold;
+                mask.set(x, y, detected);
+                count += detected as usize;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Builds the detection [Mask] within bits, using the [Threshold]
+    /// strategy then the [Morphology] operation (if any) of this [Frame]
+    fn detection<'b>(&self, bits: &'b mut [u32]) -> Result<Mask<'b>, Error> {
+        let (width, height) = (self.width(), self.height());
+        let words = Mask::words(width, height);
+        match self.morphology {
+            Some((morphology, connectivity)) => {
+                if bits.len() < 2 * words {
+                    return Err(Error::VideoDimensionError);
+                }
+                let (bits, scratch) = bits.split_at_mut(words);
+                let mut mask = Mask::new(width, height, bits)?;
+                self.detect(&mut mask)?;
+                morphology.apply(
+                    &mut mask,
+                    connectivity,
+                    &mut Mask::new(width, height, scratch)?,
+                )?;
+                Ok(mask)
+            }
+            None => {
+                let mut mask = Mask::new(width, height, bits)?;
+                self.detect(&mut mask)?;
+                Ok(mask)
+            }
+        }
+    }
+
+    /// Detects the star candidates of this [Frame], using its [Threshold]
+    /// strategy, then extracts up to N connected [Blobs], deblended
+    /// if [Self::with_deblending] was used.
+    /// The detection [Mask] is cleaned first if [Self::with_morphology] was used.
+    /// bits is the detection [Mask] storage, see [Mask::words] to size it.
+    /// Fails with [Error::VideoDimensionError] if bits is too short.
+    pub fn find_blobs<const N: usize>(
+        &self,
+        bits: &mut [u32],
+        connectivity: Connectivity,
+    ) -> Result<Blobs<N>, Error> {
+        let mask = self.detection(bits)?;
+        self.extract_blobs(&mask, connectivity)
+    }
+
+    /// Extracts up to N connected [Blobs] from given detection [Mask],
+    /// deblended if [Self::with_deblending] was used
+    fn extract_blobs<const N: usize>(
+        &self,
+        mask: &Mask<'_>,
+        connectivity: Connectivity,
+    ) -> Result<Blobs<N>, Error> {
+        let blobs = Blobs::extract(self, mask, connectivity)?;
+        match self.deblending {
+            Some(contrast) => Ok(blobs.deblend(self, mask, connectivity, contrast)),
+            None => Ok(blobs),
         }
-        acc / XY as f64
     }
 
-    /// Compute std deviation of the gray scale
-    fn gray_scale_std_dev(&self, mean: f64) -> f64 {
-        let mut acc = 0.0;
-        for p in self.bitmap.iter() {
-            acc += (p.to_gray8() as f64 - mean).powi(2);
+    /// Estimates the sub-pixel [Centroid] of given [Blob],
+    /// using the [Centroiding] method of this [Frame].
+    pub fn centroid(&self, blob: &Blob) -> Result<Centroid, Error> {
+        self.centroiding.estimate(self, blob)
+    }
+
+    /// Detects the (up to N) [Streaks] of this [Frame], by decreasing flux.
+    /// bits is the detection [Mask] storage, see [Mask::words] to size it.
+    /// Use [Streaks::mask] and [Self::with_exclusion_mask] so the
+    /// following [Frame]s ignore them.
+    pub fn find_streaks<const N: usize>(
+        &self,
+        bits: &mut [u32],
+        detector: &StreakDetector,
+    ) -> Result<Streaks<N>, Error> {
+        let mask = self.detection(bits)?;
+        let blobs = Blobs::<MAX_CANDIDATES>::extract(self, &mask, Connectivity::default())?;
+        Ok(detector.detect(&blobs))
+    }
+
+    /// Measures the N brightest [StarCandidates], by decreasing flux.
+    /// Candidates that could not be centroided, or lying along a streak
+    /// (see [Self::with_streak_rejection]), are dropped.
+    /// bits is the detection [Mask] storage, see [Mask::words] to size it.
+    pub fn star_candidates<const N: usize>(
+        &self,
+        bits: &mut [u32],
+    ) -> Result<StarCandidates<N>, Error> {
+        self.measure_candidates(bits, |blob| StarCandidate::measure(self, blob))
+    }
+
+    /// Detects the N brightest [Blob]s like [Self::star_candidates],
+    /// and measures them with the given method
+    pub(crate) fn measure_candidates<const N: usize>(
+        &self,
+        bits: &mut [u32],
+        measure: impl Fn(&Blob) -> Result<StarCandidate, Error>,
+    ) -> Result<StarCandidates<N>, Error> {
+        let mut mask = self.detection(bits)?;
+
+        // streaks are detected on their own pass, then cleared from
+        // the detection mask, so they do not take up candidate slots
+        let streaks = match self.streaks {
+            Some(detector) => {
+                let blobs = Blobs::<MAX_CANDIDATES>::extract(self, &mask, Connectivity::default())?;
+                let streaks = detector.detect::<MAX_CANDIDATES, MAX_STREAKS>(&blobs);
+                for streak in streaks.iter() {
+                    streak.for_each_pixel(self.width(), self.height(), detector.margin, |x, y| {
+                        mask.set(x, y, false)
+                    });
+                }
+                streaks
+            }
+            None => Streaks::default(),
+        };
+        let margin = self.streaks.map(|detector| detector.margin).unwrap_or_default();
+
+        let mut blobs = self.extract_blobs::<N>(&mask, Connectivity::default())?;
+        blobs.sort_by_flux();
+
+        let mut candidates = StarCandidates::default();
+        for blob in blobs.iter() {
+            if streaks.contains(blob.centroid(), margin) {
+                continue;
+            }
+            if let Ok(candidate) = measure(blob) {
+                candidates.push(candidate);
+            }
         }
-        (acc / XY as f64).sqrt()
+        candidates.add_overflow(blobs.overflow());
+        Ok(candidates)
     }
 
-    /// Compute star luminosity threshold for this [Frame]
-    pub(crate) fn gray8_star_luminosity_threshold(&self) -> u8 {
-        let mean = self.gray_scale_mean();
-        let stddev = self.gray_scale_std_dev(mean);
-        (mean + 5.0 * stddev).round().clamp(0.0, 255.0) as u8
+    /// Selects the N best [StarCandidates] among the [MAX_CANDIDATES]
+    /// brightest ones, using the [Selection] policy of this [Frame].
+    /// N must not exceed [MAX_CANDIDATES] (checked at build time).
+    /// bits is the detection [Mask] storage, see [Mask::words] to size it.
+    pub fn select_stars<const N: usize>(
+        &self,
+        bits: &mut [u32],
+    ) -> Result<StarCandidates<N>, Error> {
+        let candidates = self.star_candidates::<MAX_CANDIDATES>(bits)?;
+        Ok(self.selection.select(self, &candidates))
     }
 
-    /// Estimates central coordinates simple histogram on gray scaled [Frame]
-    pub fn star_coordinates_finder<const N: usize>(&self) -> [Coord<usize>; N] {
-        let mut coords = [Coord::zero(); N];
+    /// Assesses the [Quality] of this [Frame] against given [QualityCriteria],
+    /// from its [MAX_CANDIDATES] brightest [StarCandidates].
+    /// bits is the detection [Mask] storage, see [Mask::words] to size it.
+    pub fn quality(&self, criteria: &QualityCriteria, bits: &mut [u32]) -> Result<Quality, Error> {
+        let candidates = self.star_candidates::<MAX_CANDIDATES>(bits)?;
+        Ok(Quality::assess(self, &candidates, criteria))
+    }
+
+    /// Estimates sub-pixel [Centroid]s of the N stars retained by
+    /// [Self::select_stars], best first. Unused slots are None.
+    /// bits is the detection [Mask] storage, see [Mask::words] to size it.
+    pub fn star_coordinates_finder<const N: usize>(
+        &self,
+        bits: &mut [u32],
+    ) -> Result<[Option<Centroid>; N], Error> {
+        let mut centroids = [None; N];
 
-        let threshold = self.gray8_star_luminosity_threshold();
+        let stars = self.select_stars::<N>(bits)?;
 
-        // // overwrite image by '1' where g8_(i,j) >= threshold:
-        // for p in self.pixels.iter_mut() {
-        //     let gray = p.to_gray8();
-        //     if gray >= threshold {
-        //         *p = UnderlyingPixel::Gray8(u8::MAX);
-        //     } else {
-        //         *p = UnderlyingPixel::Gray8(0);
-        //     }
-        // }
+        for (centroid, star) in centroids.iter_mut().zip(stars.iter()) {
+            *centroid = Some(star.centroid);
+        }
 
-        coords
+        Ok(centroids)
     }
 }
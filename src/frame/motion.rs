@@ -0,0 +1,68 @@
+//! Camera motion during the exposure
+use geo::Coord;
+use nalgebra::Vector3;
+
+/// Default maximal blur (in pixels) for a centroid to be trusted
+pub const DEFAULT_MAX_BLUR: f64 = 8.0;
+
+/// [Motion] describes the camera rotation during the exposure,
+/// typically measured by the IMU of a pitching boat or a bumpy rover.
+/// Stars then smear into short streaks, whose luminance centroid is the
+/// star position at mid-exposure (constant angular rate).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Motion {
+    /// Angular rate (in rad/s), expressed in the camera frame:
+    /// x toward the right of the image, y toward the bottom,
+    /// z along the optical axis.
+    pub rate: Vector3<f64>,
+    /// Exposure duration (in seconds)
+    pub exposure: f64,
+    /// Focal length (in pixels)
+    pub focal_length: f64,
+    /// Optical center, in full sensor frame coordinates (pixels)
+    pub center: Coord<f64>,
+    /// Blur length (in pixels) beyond which the centroids are not trusted
+    pub max_blur: f64,
+}
+
+impl Motion {
+    /// Builds a new [Motion], see [Motion::rate] for the camera frame definition
+    pub fn new(rate: Vector3<f64>, exposure: f64, focal_length: f64, center: Coord<f64>) -> Self {
+        Self {
+            rate,
+            exposure,
+            focal_length,
+            center,
+            max_blur: DEFAULT_MAX_BLUR,
+        }
+    }
+
+    /// Returns a copy of this [Motion] with given maximal blur length (in pixels)
+    pub fn with_max_blur(&self, max_blur: f64) -> Self {
+        let mut s = *self;
+        s.max_blur = max_blur;
+        s
+    }
+
+    /// Returns the image velocity (in pixels/s) of a star located
+    /// at given point, in full sensor frame coordinates
+    pub fn velocity(&self, point: Coord<f64>) -> Coord<f64> {
+        let f = self.focal_length;
+        let (x, y) = (point.x - self.center.x, point.y - self.center.y);
+        let (wx, wy, wz) = (self.rate[0], self.rate[1], self.rate[2]);
+        Coord {
+            x: wx * x * y / f - wy * (f + x * x / f) + wz * y,
+            y: wx * (f + y * y / f) - wy * x * y / f - wz * x,
+        }
+    }
+
+    /// Returns the (dx, dy) displacement (in pixels) of a star located at given
+    /// point (full sensor frame coordinates), from the start to the end of the exposure
+    pub fn blur(&self, point: Coord<f64>) -> Coord<f64> {
+        let velocity = self.velocity(point);
+        Coord {
+            x: velocity.x * self.exposure,
+            y: velocity.y * self.exposure,
+        }
+    }
+}
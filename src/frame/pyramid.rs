@@ -0,0 +1,302 @@
+//! Binning and multi-resolution pyramid, for fast coarse detection
+use geo::Coord;
+
+use crate::{
+    frame::{
+        blob::{Blob, Blobs, Connectivity},
+        candidate::{StarCandidate, StarCandidates},
+        centroid::local_background,
+        component::{Pixel, UnderlyingComponent},
+        mask::Mask,
+        selection::MAX_CANDIDATES,
+        BitMap, Frame,
+    },
+    Error,
+};
+
+/// Maximal number of levels of a [Pyramid], full resolution included
+pub const MAX_LEVELS: usize = 4;
+
+/// Sigma multiplier of the full resolution refinement: pixels of the refinement
+/// window brighter than the local background by [REFINEMENT_SIGMA_K] std deviations
+/// belong to the star. Lower than the usual detection thresholds, binning having
+/// increased the signal to noise ratio of the coarse detection.
+pub const REFINEMENT_SIGMA_K: f64 = 3.0;
+
+/// Maximal number of [Blob]s within a refinement window
+const REFINEMENT_BLOBS: usize = 8;
+
+/// [Binning] reduces each square cell of pixels to their mean luminance.
+/// Noise decreases by the cell width, so binned stars remain detectable
+/// while the number of pixels to process drops by the cell area.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Binning {
+    /// 2x2 cells
+    #[default]
+    X2,
+    /// 4x4 cells
+    X4,
+}
+
+impl Binning {
+    /// Returns the cell width (in pixels)
+    pub fn factor(&self) -> u16 {
+        match self {
+            Self::X2 => 2,
+            Self::X4 => 4,
+        }
+    }
+
+    /// Bins (width, height) samples, into dst, row after row. Missing samples
+    /// (None) do not contribute to their cell, and empty cells are set to zero.
+    /// Returns the binned (width, height).
+    pub(crate) fn bin<P: Pixel>(
+        &self,
+        width: u16,
+        height: u16,
+        sample: impl Fn(u16, u16) -> Option<u16>,
+        dst: &mut [P],
+    ) -> Result<(u16, u16), Error> {
+        let factor = self.factor();
+        let (binned_width, binned_height) = (width / factor, height / factor);
+        if binned_width as usize * binned_height as usize > dst.len() {
+            return Err(Error::VideoDimensionError);
+        }
+
+        for y in 0..binned_height {
+            for x in 0..binned_width {
+                let (mut sum, mut n) = (0_u32, 0_u32);
+                for dy in 0..factor {
+                    for dx in 0..factor {
+                        if let Some(luma) = sample(x * factor + dx, y * factor + dy) {
+                            sum += luma as u32;
+                            n += 1;
+                        }
+                    }
+                }
+                let mean = (sum + n / 2).checked_div(n).unwrap_or_default();
+                dst[y as usize * binned_width as usize + x as usize] = P::from_luma(mean as u16);
+            }
+        }
+        Ok((binned_width, binned_height))
+    }
+}
+
+/// [Pyramid] of binned [Frame]s: level 0 is the full resolution [Frame], and
+/// each level is binned from the previous one. Stars are detected coarsely on a
+/// binned level, then refined by centroiding only their windows at full resolution.
+/// All binned levels share one buffer: a third of the full resolution
+/// area is enough with [Binning::X2].
+pub struct Pyramid<'a, const XY: usize, P: Pixel = UnderlyingComponent> {
+    /// Levels, full resolution first
+    levels: [Option<Frame<'a, XY, P>>; MAX_LEVELS],
+    /// [Binning] between two consecutive levels
+    binning: Binning,
+}
+
+impl<'a, const XY: usize, P: Pixel> Pyramid<'a, XY, P> {
+    /// Builds a [Pyramid] of up to levels (at most [MAX_LEVELS]) from given [Frame].
+    /// Stops early when a level would be empty. Binned levels skip the ignored pixels,
+    /// and retain the [Frame] threshold, centroiding and saturation settings; masks and
+    /// [crate::frame::background::Background] expressed in sensor coordinates are not retained.
+    /// Fails with [Error::VideoDimensionError] if buf is too small,
+    /// or if levels is not within 1..=[MAX_LEVELS].
+    pub fn new(
+        frame: Frame<'a, XY, P>,
+        binning: Binning,
+        levels: usize,
+        buf: &'a mut [P; XY],
+    ) -> Result<Self, Error> {
+        if levels == 0 || levels > MAX_LEVELS {
+            return Err(Error::VideoDimensionError);
+        }
+        let mut pyramid = Self {
+            levels: [None; MAX_LEVELS],
+            binning,
+        };
+        pyramid.levels[0] = Some(frame);
+
+        let factor = binning.factor();
+        let mut previous = frame;
+        let mut rest: &'a mut [P] = buf;
+
+        for level in pyramid.levels.iter_mut().take(levels).skip(1) {
+            let (width, height) = (previous.width() / factor, previous.height() / factor);
+            if width == 0 || height == 0 {
+                break;
+            }
+            let size = width as usize * height as usize;
+            if size > rest.len() {
+                return Err(Error::VideoDimensionError);
+            }
+            let (dst, tail) = rest.split_at_mut(size);
+            rest = tail;
+
+            binning.bin(
+                previous.width(),
+                previous.height(),
+                |x, y| {
+                    if previous.is_ignored(x, y) {
+                        None
+                    } else {
+                        previous.get(x, y).map(|p| p.luma())
+                    }
+                },
+                dst,
+            )?;
+
+            let mut binned = Frame::new(
+                width as usize,
+                height as usize,
+                BitMap::from_buffer(width, height, dst)?
+                    .with_bit_depth(previous.bitmap.bit_depth()),
+            )?;
+            binned.origin = (previous.origin.0 / factor, previous.origin.1 / factor);
+            binned.threshold = previous.threshold;
+            binned.centroiding = previous.centroiding;
+            binned.saturation = previous.saturation;
+            binned.deblending = previous.deblending;
+
+            *level = Some(binned);
+            previous = binned;
+        }
+
+        Ok(pyramid)
+    }
+
+    /// Returns the number of levels, full resolution included
+    pub fn len(&self) -> usize {
+        self.levels.iter().flatten().count()
+    }
+
+    /// Returns true if this [Pyramid] has no level
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the [Binning] between two consecutive levels
+    pub fn binning(&self) -> Binning {
+        self.binning
+    }
+
+    /// Returns the [Frame] of given level (0 being the full resolution)
+    pub fn level(&self, level: usize) -> Option<&Frame<'a, XY, P>> {
+        self.levels.get(level)?.as_ref()
+    }
+
+    /// Returns the scale of given level: full resolution pixels per binned pixel
+    pub fn scale(&self, level: usize) -> u16 {
+        self.binning.factor().pow(level as u32)
+    }
+
+    /// Detects the N brightest [StarCandidates] on the given (coarse) level, then refines
+    /// each of them at full resolution, within a window spanning twice the coarse FWHM
+    /// centered on the coarse centroid: the star is extracted again from the pixels of
+    /// the window brighter than [REFINEMENT_SIGMA_K] local std deviations, trimmed to
+    /// its half maximum core, then measured.
+    /// Refined [StarCandidate]s are expressed in full resolution [Frame] coordinates.
+    /// bits is the detection [crate::frame::mask::Mask] storage of the coarse level,
+    /// reused for the refinement windows: candidates whose window exceeds it, or
+    /// without signal at full resolution, are dropped.
+    /// Fails with [Error::VideoDimensionError] if the level does not exist.
+    pub fn star_candidates<const N: usize>(
+        &self,
+        level: usize,
+        bits: &mut [u32],
+    ) -> Result<StarCandidates<N>, Error> {
+        let (Some(full), Some(coarse)) = (self.level(0), self.level(level)) else {
+            return Err(Error::VideoDimensionError);
+        };
+
+        let scale = self.scale(level) as f64;
+        let (width, height) = (full.width(), full.height());
+
+        let coarse = coarse.star_candidates::<N>(bits)?;
+
+        let mut candidates = StarCandidates::default();
+        for candidate in coarse.iter() {
+            // binned pixel centers
+            let center = Coord {
+                x: (candidate.centroid.coord.x + 0.5) * scale - 0.5,
+                y: (candidate.centroid.coord.y + 0.5) * scale - 0.5,
+            };
+            let half = (candidate.fwhm.max(1.0) * scale).ceil();
+
+            let x_min = (center.x - half).round().clamp(0.0, width as f64 - 1.0) as u16;
+            let y_min = (center.y - half).round().clamp(0.0, height as f64 - 1.0) as u16;
+            let x_max = (center.x + half).round().clamp(0.0, width as f64 - 1.0) as u16;
+            let y_max = (center.y + half).round().clamp(0.0, height as f64 - 1.0) as u16;
+
+            if let Some(blob) = Self::refine(full, (x_min, y_min, x_max, y_max), center, bits) {
+                if let Ok(refined) = StarCandidate::measure(full, &blob) {
+                    candidates.push(refined);
+                }
+            }
+        }
+        candidates.add_overflow(coarse.overflow());
+        Ok(candidates)
+    }
+
+    /// Extracts, within the (x_min, y_min, x_max, y_max) window of the full resolution
+    /// [Frame], the [Blob] nearest to given center, see [Self::star_candidates]
+    fn refine(
+        full: &Frame<'_, XY, P>,
+        (x_min, y_min, x_max, y_max): (u16, u16, u16, u16),
+        center: Coord<f64>,
+        bits: &mut [u32],
+    ) -> Option<Blob> {
+        let (width, height) = (x_max - x_min + 1, y_max - y_min + 1);
+        let roi = full.roi(x_min, y_min, width, height).ok()?;
+
+        let mut nearest = |threshold: f64| {
+            let mut mask = Mask::new(width, height, bits).ok()?;
+            for y in 0..height {
+                for x in 0..width {
+                    let luma = roi.get(x, y).map(|p| p.luma()).unwrap_or_default();
+                    mask.set(x, y, !roi.is_ignored(x, y) && luma as f64 > threshold);
+                }
+            }
+            let blobs =
+                Blobs::<REFINEMENT_BLOBS>::extract(&roi, &mask, Connectivity::default()).ok()?;
+            let distance = |blob: &Blob| {
+                let c = blob.centroid();
+                (c.x + x_min as f64 - center.x).hypot(c.y + y_min as f64 - center.y)
+            };
+            blobs
+                .iter()
+                .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+                .copied()
+        };
+
+        let (background, rms) =
+            local_background(full, &Blob::window(full, (x_min, y_min, x_max, y_max)));
+        let threshold = background + REFINEMENT_SIGMA_K * rms;
+        let blob = nearest(threshold)?;
+
+        // keep the core of the star, whatever its brightness, so the refined
+        // blob (hence the measurement window) matches a full resolution detection
+        let (_, _, peak) = blob.peak();
+        let half_maximum = background + (peak as f64 - background) / 2.0;
+        let blob = if half_maximum > threshold {
+            nearest(half_maximum)?
+        } else {
+            blob
+        };
+        Some(blob.offset(x_min, y_min))
+    }
+
+    /// Selects the N best [StarCandidates] among the [MAX_CANDIDATES] brightest
+    /// ones detected on the given level (see [Self::star_candidates]), using the
+    /// [crate::frame::selection::Selection] policy of the full resolution [Frame].
+    pub fn select_stars<const N: usize>(
+        &self,
+        level: usize,
+        bits: &mut [u32],
+    ) -> Result<StarCandidates<N>, Error> {
+        let Some(full) = self.level(0) else {
+            return Err(Error::VideoDimensionError);
+        };
+        let candidates = self.star_candidates::<MAX_CANDIDATES>(level, bits)?;
+        Ok(full.selection.select(full, &candidates))
+    }
+}
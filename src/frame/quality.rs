@@ -0,0 +1,219 @@
+//! Frame quality assessment
+use crate::frame::{candidate::StarCandidates, component::Pixel, selection::MAX_CANDIDATES, Frame};
+
+/// Default detection limit (signal to noise ratio) of the limiting magnitude
+pub const DEFAULT_SNR_LIMIT: f64 = 5.0;
+
+/// Default FWHM (in pixels), used when no star could be measured
+pub const DEFAULT_FWHM: f64 = 2.5;
+
+/// Default sigma multiplier of the cloud detection
+pub const DEFAULT_CLOUD_K: f64 = 3.0;
+
+/// Sigma clipping iterations of the global background statistics
+const CLIPPING_ITERATIONS: usize = 5;
+
+/// [QualityCriteria] describes how a [Quality] report is built
+/// (expected star count, photometric zero point..) and which [Frame]s
+/// are worth processing. Default criteria accept any [Frame].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityCriteria {
+    /// Number of stars expected within the field of view (0 if unknown),
+    /// typically predicted from a star catalog
+    pub expected_stars: usize,
+    /// Photometric zero point: magnitude of a star of unit flux
+    /// (native bit depth), for this camera and exposure
+    pub zero_point: f64,
+    /// Detection limit (signal to noise ratio) of the limiting magnitude
+    pub snr_limit: f64,
+    /// Sigma multiplier: [crate::frame::background::Background] tiles standing out
+    /// of the median by more than k std deviations are cloudy,
+    /// see [crate::frame::background::Tile::is_cloudy]
+    pub cloud_k: f64,
+    /// Maximal median FWHM (in pixels): out of focus [Frame]s are rejected
+    pub max_fwhm: f64,
+    /// Minimal ratio between the detected and expected star counts
+    pub min_star_ratio: f64,
+    /// Maximal cloud fraction (0.0..=1.0)
+    pub max_cloud_fraction: f64,
+    /// Minimal limiting magnitude
+    pub min_limiting_magnitude: f64,
+}
+
+impl Default for QualityCriteria {
+    fn default() -> Self {
+        Self {
+            expected_stars: 0,
+            zero_point: 0.0,
+            snr_limit: DEFAULT_SNR_LIMIT,
+            cloud_k: DEFAULT_CLOUD_K,
+            max_fwhm: f64::INFINITY,
+            min_star_ratio: 0.0,
+            max_cloud_fraction: 1.0,
+            min_limiting_magnitude: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl QualityCriteria {
+    /// Returns a copy of these [QualityCriteria] expecting given number of stars
+    pub fn with_expected_stars(&self, expected_stars: usize) -> Self {
+        let mut s = *self;
+        s.expected_stars = expected_stars;
+        s
+    }
+
+    /// Returns a copy of these [QualityCriteria] with given photometric zero point
+    pub fn with_zero_point(&self, zero_point: f64) -> Self {
+        let mut s = *self;
+        s.zero_point = zero_point;
+        s
+    }
+
+    /// Returns a copy of these [QualityCriteria] rejecting the [Frame]s
+    /// whose median FWHM exceeds max_fwhm (in pixels)
+    pub fn with_max_fwhm(&self, max_fwhm: f64) -> Self {
+        let mut s = *self;
+        s.max_fwhm = max_fwhm;
+        s
+    }
+
+    /// Returns a copy of these [QualityCriteria] rejecting the [Frame]s where
+    /// fewer than min_star_ratio of the expected stars are detected
+    pub fn with_min_star_ratio(&self, min_star_ratio: f64) -> Self {
+        let mut s = *self;
+        s.min_star_ratio = min_star_ratio;
+        s
+    }
+
+    /// Returns a copy of these [QualityCriteria] rejecting the [Frame]s
+    /// whose cloud fraction exceeds max_cloud_fraction
+    pub fn with_max_cloud_fraction(&self, max_cloud_fraction: f64) -> Self {
+        let mut s = *self;
+        s.max_cloud_fraction = max_cloud_fraction;
+        s
+    }
+
+    /// Returns a copy of these [QualityCriteria] rejecting the [Frame]s
+    /// whose limiting magnitude is brighter than min_limiting_magnitude
+    pub fn with_min_limiting_magnitude(&self, min_limiting_magnitude: f64) -> Self {
+        let mut s = *self;
+        s.min_limiting_magnitude = min_limiting_magnitude;
+        s
+    }
+
+    /// Returns true if given [Quality] report meets these [QualityCriteria]
+    pub fn accepts(&self, quality: &Quality) -> bool {
+        quality.fwhm <= self.max_fwhm
+            && quality.star_ratio() >= self.min_star_ratio
+            && quality.cloud_fraction <= self.max_cloud_fraction
+            && quality.limiting_magnitude >= self.min_limiting_magnitude
+    }
+}
+
+/// [Quality] report of a [Frame], so out of focus or clouded
+/// [Frame]s can be skipped. Luminances are expressed at native bit depth.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Quality {
+    /// Median FWHM of the unsaturated stars (in pixels), 0 without stars
+    pub fwhm: f64,
+    /// Number of detected stars
+    pub stars: usize,
+    /// Number of expected stars (0 if unknown)
+    pub expected_stars: usize,
+    /// Sky background level
+    pub background: f64,
+    /// Sky background noise (std deviation)
+    pub noise: f64,
+    /// Magnitude of the faintest star detectable at the
+    /// [QualityCriteria::snr_limit], within a one FWHM aperture
+    pub limiting_magnitude: f64,
+    /// Estimated fraction of the sky covered by clouds (0.0..=1.0)
+    pub cloud_fraction: f64,
+}
+
+impl Quality {
+    /// Assesses the [Quality] of given [Frame], from its [StarCandidates]
+    /// (see [Frame::star_candidates]).
+    /// - The sky background is the median of the [crate::frame::background::Background]
+    ///   tiles when attached to the [Frame], the sigma clipped [Frame] statistics otherwise.
+    /// - The cloud fraction is the largest of the fraction of missing stars (when
+    ///   expected), and the fraction of cloudy [crate::frame::background::Background]
+    ///   tiles, whose level or noise stand out (when attached). The former detects
+    ///   overcast skies, the latter broken clouds.
+    pub fn assess<const N: usize, const XY: usize, P: Pixel>(
+        frame: &Frame<'_, XY, P>,
+        candidates: &StarCandidates<N>,
+        criteria: &QualityCriteria,
+    ) -> Self {
+        // median FWHM of the unsaturated stars
+        let mut fwhms = [0.0_f64; MAX_CANDIDATES];
+        let mut n = 0;
+        for candidate in candidates.iter().filter(|c| !c.saturated && c.fwhm > 0.0) {
+            if n < MAX_CANDIDATES {
+                fwhms[n] = candidate.fwhm;
+                n += 1;
+            }
+        }
+        let fwhms = &mut fwhms[..n];
+        fwhms.sort_unstable_by(|a, b| a.total_cmp(b));
+        let fwhm = fwhms.get(n / 2).copied().unwrap_or_default();
+
+        // sky background and broken clouds
+        let (background, noise, cloudy) = match frame.background {
+            Some(background) => {
+                let (cols, rows) = background.grid();
+                let reference = background.median();
+                let cloudy = (0..rows)
+                    .flat_map(|row| (0..cols).filter_map(move |col| background.tile(col, row)))
+                    .filter(|tile| tile.is_cloudy(&reference, criteria.cloud_k))
+                    .count();
+                let tiles = cols as usize * rows as usize;
+                (
+                    reference.mean as f64,
+                    reference.rms as f64,
+                    cloudy as f64 / tiles.max(1) as f64,
+                )
+            }
+            None => {
+                let (mean, rms) = frame.luma_clipped_stats(3.0, CLIPPING_ITERATIONS);
+                (mean, rms, 0.0)
+            }
+        };
+
+        // overcast sky
+        let stars = candidates.len();
+        let missing = if criteria.expected_stars > 0 {
+            (1.0 - stars as f64 / criteria.expected_stars as f64).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        // faintest flux reaching the snr limit (CCD equation, unit gain)
+        let aperture = if fwhm > 0.0 { fwhm } else { DEFAULT_FWHM };
+        let area = core::f64::consts::PI * (aperture / 2.0).powi(2);
+        let k = criteria.snr_limit;
+        let flux = (k * k + k * (k * k + 4.0 * area * noise * noise).sqrt()) / 2.0;
+        let limiting_magnitude = criteria.zero_point - 2.5 * flux.max(f64::MIN_POSITIVE).log10();
+
+        Self {
+            fwhm,
+            stars,
+            expected_stars: criteria.expected_stars,
+            background,
+            noise,
+            limiting_magnitude,
+            cloud_fraction: missing.max(cloudy),
+        }
+    }
+
+    /// Returns the ratio between the detected and expected star counts,
+    /// 1.0 when no star count is expected
+    pub fn star_ratio(&self) -> f64 {
+        if self.expected_stars > 0 {
+            self.stars as f64 / self.expected_stars as f64
+        } else {
+            1.0
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Star candidates selection
+use core::cmp::Ordering;
+
+use crate::frame::{
+    candidate::{StarCandidate, StarCandidates},
+    component::Pixel,
+    Frame,
+};
+
+/// Number of [StarCandidate]s measured before the [Selection]
+/// retains the N best of them
+pub const MAX_CANDIDATES: usize = 64;
+
+/// [Ranking] criteria of the [StarCandidate]s
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Ranking {
+    /// Decreasing background subtracted flux
+    #[default]
+    Flux,
+    /// Decreasing signal to noise ratio
+    Snr,
+}
+
+impl Ranking {
+    /// Compares two [StarCandidate]s, best first. Ties are broken by
+    /// decreasing peak, then by increasing (y, x) position,
+    /// so the ranking does not depend on the detection order.
+    pub fn compare(&self, a: &StarCandidate, b: &StarCandidate) -> Ordering {
+        let (key_a, key_b) = match self {
+            Self::Flux => (a.flux, b.flux),
+            Self::Snr => (a.snr, b.snr),
+        };
+        key_b
+            .total_cmp(&key_a)
+            .then(b.peak.cmp(&a.peak))
+            .then(a.centroid.coord.y.total_cmp(&b.centroid.coord.y))
+            .then(a.centroid.coord.x.total_cmp(&b.centroid.coord.x))
+    }
+}
+
+/// [Selection] policy of the N stars used by the navigation:
+/// the best ranked [StarCandidate]s that are far enough from the [Frame]
+/// edges (truncated stars) and from any better ranked star (blended stars,
+/// poor geometry), and not smeared by the camera motion
+/// (see [StarCandidate::smeared]).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Selection {
+    /// [Ranking] criteria
+    pub ranking: Ranking,
+    /// Minimal distance between two selected stars (in pixels)
+    pub min_separation: f64,
+    /// Minimal distance between a selected star and the [Frame] edges (in pixels)
+    pub edge_margin: f64,
+}
+
+impl Selection {
+    /// Builds a new [Selection] policy
+    pub fn new(ranking: Ranking, min_separation: f64, edge_margin: f64) -> Self {
+        Self {
+            ranking,
+            min_separation,
+            edge_margin,
+        }
+    }
+
+    /// Returns a copy of this [Selection] with given minimal angular separation
+    /// (in radians), for a camera of given pixel scale (in radians per pixel).
+    pub fn with_min_angular_separation(&self, angle: f64, pixel_scale: f64) -> Self {
+        let mut s = *self;
+        s.min_separation = angle / pixel_scale;
+        s
+    }
+
+    /// Returns true if given [StarCandidate] lies within the edge margin
+    fn near_edge<const XY: usize, P: Pixel>(
+        &self,
+        frame: &Frame<'_, XY, P>,
+        candidate: &StarCandidate,
+    ) -> bool {
+        let coord = candidate.centroid.coord;
+        let (x_max, y_max) = (
+            frame.width() as f64 - 1.0 - self.edge_margin,
+            frame.height() as f64 - 1.0 - self.edge_margin,
+        );
+        coord.x < self.edge_margin
+            || coord.y < self.edge_margin
+            || coord.x > x_max
+            || coord.y > y_max
+    }
+
+    /// Selects the N best [StarCandidate]s of given [Frame], out of M.
+    /// [StarCandidates::dropped] reports how many candidates were rejected,
+    /// by the edge margin, the minimal separation or the motion blur,
+    /// including those ranked below the N selected ones. N must not exceed M (checked at build time).
+    pub fn select<const M: usize, const N: usize, const XY: usize, P: Pixel>(
+        &self,
+        frame: &Frame<'_, XY, P>,
+        candidates: &StarCandidates<M>,
+    ) -> StarCandidates<N> {
+        const {
+            assert!(N <= M, "cannot select more stars than measured candidates");
+        }
+
+        // rank indices rather than copying the candidates
+        let mut ranked = [0; M];
+        for (i, index) in ranked.iter_mut().enumerate() {
+            *index = i;
+        }
+        let ranked = &mut ranked[..candidates.len()];
+        let slice = candidates.as_slice();
+        ranked.sort_unstable_by(|&a, &b| self.ranking.compare(&slice[a], &slice[b]));
+
+        let mut selected = StarCandidates::<N>::default();
+        selected.add_overflow(candidates.overflow());
+
+        for candidate in ranked.iter().map(|&i| &slice[i]) {
+            let crowded = selected.iter().any(|star| {
+                let (dx, dy) = (
+                    star.centroid.coord.x - candidate.centroid.coord.x,
+                    star.centroid.coord.y - candidate.centroid.coord.y,
+                );
+                dx.hypot(dy) < self.min_separation
+            });
+            if crowded || candidate.smeared || self.near_edge(frame, candidate) {
+                selected.drop_candidate();
+            } else if selected.len() < N {
+                selected.push(*candidate);
+            }
+        }
+        selected
+    }
+}
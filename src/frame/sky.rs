@@ -0,0 +1,222 @@
+//! Sky segmentation
+use geo::Coord;
+
+use crate::{
+    frame::{background::Background, blob::Connectivity, component::Pixel, mask::Mask, Frame},
+    Error,
+};
+
+/// [Horizon] line, expressed in full sensor frame coordinates (pixels).
+/// The sky lies on the left hand side when walking from start to end:
+/// above the line when start is on the left of the image.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Horizon {
+    /// First point of the line
+    pub start: Coord<f64>,
+    /// Second point of the line
+    pub end: Coord<f64>,
+    /// Pixels excluded above the line (in pixels), to reject the
+    /// masts, trees and hills standing over the horizon
+    pub margin: f64,
+}
+
+impl Horizon {
+    /// Builds a new [Horizon] line from two points
+    pub fn new(start: Coord<f64>, end: Coord<f64>, margin: f64) -> Self {
+        Self { start, end, margin }
+    }
+
+    /// Builds the [Horizon] line seen by a pinhole camera of given attitude.
+    /// ## Input
+    /// - pitch: elevation of the optical axis above the horizon (in radians)
+    /// - roll: rotation of the camera around its optical axis (in radians)
+    /// - focal_length: focal length (in pixels)
+    /// - center: optical center, in full sensor frame coordinates (pixels)
+    /// - margin: see [Horizon::margin]
+    pub fn from_attitude(
+        pitch: f64,
+        roll: f64,
+        focal_length: f64,
+        center: Coord<f64>,
+        margin: f64,
+    ) -> Self {
+        // horizon point closest to the optical center
+        let offset = focal_length * pitch.tan();
+        let (sin, cos) = roll.sin_cos();
+        let point = Coord {
+            x: center.x - offset * sin,
+            y: center.y + offset * cos,
+        };
+        Self {
+            start: point,
+            end: Coord {
+                x: point.x + cos,
+                y: point.y + sin,
+            },
+            margin,
+        }
+    }
+
+    /// Returns signed distance (in pixels) of (x, y) above the line:
+    /// positive within the sky, negative below the horizon
+    pub fn elevation(&self, x: f64, y: f64) -> f64 {
+        let (dx, dy) = (self.end.x - self.start.x, self.end.y - self.start.y);
+        let length = dx.hypot(dy);
+        if length == 0.0 {
+            return f64::INFINITY;
+        }
+        (dy * (x - self.start.x) - dx * (y - self.start.y)) / length
+    }
+}
+
+/// [Glare] regions: saturated areas (Moon, street lights, sun lit
+/// surfaces) and their halo, where no star can be measured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glare {
+    /// Luminance at or above which a pixel is glared (native bit depth)
+    pub level: u16,
+    /// Halo radius around the glared pixels (in pixels)
+    pub radius: u16,
+}
+
+/// [Clouds] are detected from the texture of the sky background:
+/// regions whose background level or noise stand out from the median
+/// of the [Background] tiles, see [crate::frame::background::Tile::is_cloudy]
+#[derive(Debug, Clone, Copy)]
+pub struct Clouds<'a> {
+    /// Local [Background], estimated over the full sensor frame
+    pub background: &'a Background<'a>,
+    /// Sigma multiplier
+    pub k: f64,
+}
+
+/// [SkySegmentation] flags the terrain, glare and clouds of a [Frame] within
+/// an exclusion [Mask], so the star finder ignores them,
+/// see [Frame::with_exclusion_mask].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SkySegmentation<'a> {
+    /// [Horizon] line (if any)
+    pub horizon: Option<Horizon>,
+    /// [Glare] rejection (if any)
+    pub glare: Option<Glare>,
+    /// [Clouds] rejection (if any)
+    pub clouds: Option<Clouds<'a>>,
+}
+
+impl<'a> SkySegmentation<'a> {
+    /// Returns a copy of this [SkySegmentation] rejecting the pixels
+    /// below given [Horizon]
+    pub fn with_horizon(&self, horizon: Horizon) -> Self {
+        let mut s = *self;
+        s.horizon = Some(horizon);
+        s
+    }
+
+    /// Returns a copy of this [SkySegmentation] rejecting the pixels at or
+    /// above level (native bit depth), and their neighbors within radius (in pixels).
+    /// The halo is an octagon approximating the disc of given radius (within 12%).
+    pub fn with_glare(&self, level: u16, radius: u16) -> Self {
+        let mut s = *self;
+        s.glare = Some(Glare { level, radius });
+        s
+    }
+
+    /// Returns a copy of this [SkySegmentation] rejecting the pixels whose local
+    /// [Background] level or noise exceeds the median by more than k std deviations
+    pub fn with_clouds(&self, background: &'a Background<'a>, k: f64) -> Self {
+        let mut s = *self;
+        s.clouds = Some(Clouds { background, k });
+        s
+    }
+
+    /// Sets, within the exclusion mask, the pixels of given [Frame] that do
+    /// not belong to the valid sky. Pixels already excluded (e.g. by
+    /// [crate::frame::streak::Streaks::mask]) are kept: clear the mask between
+    /// [Frame]s. exclusion is expressed in full sensor frame coordinates, like
+    /// the [Horizon], and should cover the [Frame]. bits is the [Glare] working
+    /// storage: two [Mask]s of the [Frame] dimensions (see [Mask::words]).
+    /// Returns the number of valid sky pixels.
+    /// Fails with [Error::VideoDimensionError] if the [Frame] exceeds the
+    /// exclusion mask, or if bits is too short.
+    pub fn segment<const XY: usize, P: Pixel>(
+        &self,
+        frame: &Frame<'_, XY, P>,
+        exclusion: &mut Mask<'_>,
+        bits: &mut [u32],
+    ) -> Result<usize, Error> {
+        let (width, height) = (frame.width(), frame.height());
+        let (x0, y0) = frame.origin();
+        if x0 as usize + width as usize > exclusion.width() as usize
+            || y0 as usize + height as usize > exclusion.height() as usize
+        {
+            return Err(Error::VideoDimensionError);
+        }
+
+        // median background level and noise
+        let reference = self.clouds.map(|clouds| clouds.background.median());
+
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = (x0 + x, y0 + y);
+
+                let terrain = match self.horizon {
+                    Some(horizon) => horizon.elevation(sx as f64, sy as f64) < horizon.margin,
+                    None => false,
+                };
+
+                let cloudy = match (self.clouds, reference) {
+                    (Some(clouds), Some(reference)) => clouds
+                        .background
+                        .interpolate(sx, sy)
+                        .is_cloudy(&reference, clouds.k),
+                    _ => false,
+                };
+
+                if terrain || cloudy {
+                    exclusion.set(sx, sy, true);
+                }
+            }
+        }
+
+        if let Some(glare) = self.glare {
+            let words = Mask::words(width, height);
+            if bits.len() < 2 * words {
+                return Err(Error::VideoDimensionError);
+            }
+            let (bits, scratch) = bits.split_at_mut(words);
+            let mut glared = Mask::new(width, height, bits)?;
+            let mut scratch = Mask::new(width, height, scratch)?;
+
+            for y in 0..height {
+                for x in 0..width {
+                    let luma = frame.get(x, y).map(|p| p.luma()).unwrap_or_default();
+                    glared.set(x, y, luma >= glare.level);
+                }
+            }
+
+            // alternating the connectivity grows an octagonal halo
+            for pass in 0..glare.radius {
+                let connectivity = if pass % 2 == 0 {
+                    Connectivity::Four
+                } else {
+                    Connectivity::Eight
+                };
+                glared.dilate(connectivity, &mut scratch)?;
+            }
+
+            for y in 0..height {
+                for x in 0..width {
+                    if glared.get(x, y) {
+                        exclusion.set(x0 + x, y0 + y, true);
+                    }
+                }
+            }
+        }
+
+        let count = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|(x, y)| !exclusion.get(x0 + x, y0 + y))
+            .count();
+        Ok(count)
+    }
+}
@@ -0,0 +1,431 @@
+//! Multi-frame stacking
+use geo::Coord;
+use nalgebra::{Matrix3, Rotation3, Vector3};
+
+use crate::{
+    frame::{candidate::StarCandidate, component::Pixel, BitMap, Frame},
+    Error,
+};
+
+/// Default sigma clipping factor
+pub const DEFAULT_CLIPPING: f64 = 3.0;
+
+/// Maximal number of [StarCandidate]s, brightest first,
+/// considered by [Registration::from_stars]
+pub const MAX_ALIGNMENT_STARS: usize = 16;
+
+/// [Registration] maps the pixels of the reference [Frame] of a [Stack]
+/// to the pixels of another [Frame] of the same scene.
+/// Both are expressed in full sensor frame coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Registration {
+    /// Projective transform, in homogeneous coordinates
+    homography: Matrix3<f64>,
+}
+
+impl Default for Registration {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Registration {
+    /// [Registration] of a [Frame] aligned with the reference
+    pub fn identity() -> Self {
+        Self {
+            homography: Matrix3::identity(),
+        }
+    }
+
+    /// [Registration] of a [Frame] shifted by given (dx, dy) offset (in pixels)
+    /// with respect to the reference
+    pub fn from_translation(offset: Coord<f64>) -> Self {
+        Self {
+            homography: Matrix3::new(1.0, 0.0, offset.x, 0.0, 1.0, offset.y, 0.0, 0.0, 1.0),
+        }
+    }
+
+    /// [Registration] of a [Frame] captured by a rotated pinhole camera.
+    /// ## Input
+    /// - reference: camera orientation when the reference [Frame] was captured
+    /// - orientation: camera orientation when the [Frame] was captured.
+    ///   Orientations rotate the camera frame (x toward the right of the image,
+    ///   y toward the bottom, z along the optical axis) to the same fixed frame.
+    /// - focal_length: focal length (in pixels)
+    /// - center: optical center, in full sensor frame coordinates (pixels)
+    pub fn from_attitude(
+        reference: &Rotation3<f64>,
+        orientation: &Rotation3<f64>,
+        focal_length: f64,
+        center: Coord<f64>,
+    ) -> Self {
+        let (f, cx, cy) = (focal_length, center.x, center.y);
+        let intrinsics = Matrix3::new(f, 0.0, cx, 0.0, f, cy, 0.0, 0.0, 1.0);
+        let inverse = Matrix3::new(1.0 / f, 0.0, -cx / f, 0.0, 1.0 / f, -cy / f, 0.0, 0.0, 1.0);
+        let rotation = orientation.inverse() * reference;
+        Self {
+            homography: intrinsics * rotation.matrix() * inverse,
+        }
+    }
+
+    /// Estimates the translation [Registration] of a [Frame] from its stars,
+    /// when the attitude is unknown or not accurate enough. Stars are given
+    /// brightest first (see [Frame::star_candidates]), and both [Frame]s should
+    /// share the same origin. Every offset between the [MAX_ALIGNMENT_STARS]
+    /// brightest stars is voted for: the offset matched (within tolerance, in pixels)
+    /// by the most stars wins, and is refined by averaging.
+    /// Returns None when fewer than two stars could be matched.
+    pub fn from_stars(
+        reference: &[StarCandidate],
+        stars: &[StarCandidate],
+        tolerance: f64,
+    ) -> Option<Self> {
+        let reference = &reference[..reference.len().min(MAX_ALIGNMENT_STARS)];
+        let stars = &stars[..stars.len().min(MAX_ALIGNMENT_STARS)];
+
+        let offset = |a: &StarCandidate, b: &StarCandidate| Coord {
+            x: b.centroid.coord.x - a.centroid.coord.x,
+            y: b.centroid.coord.y - a.centroid.coord.y,
+        };
+
+        // matched offsets, averaged
+        let matches = |candidate: Coord<f64>| {
+            let (mut n, mut sum) = (0, Coord::zero());
+            for a in reference {
+                let matched = stars
+                    .iter()
+                    .map(|b| offset(a, b))
+                    .find(|o| (o.x - candidate.x).hypot(o.y - candidate.y) <= tolerance);
+                if let Some(o) = matched {
+                    n += 1;
+                    sum = sum + o;
+                }
+            }
+            (n, sum / n.max(1) as f64)
+        };
+
+        let (n, offset) = reference
+            .iter()
+            .flat_map(|a| stars.iter().map(move |b| offset(a, b)))
+            .map(matches)
+            .fold((0, Coord::zero()), |best, candidate| {
+                if candidate.0 > best.0 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+
+        if n < 2 {
+            return None;
+        }
+
+        Some(Self::from_translation(offset))
+    }
+
+    /// Maps given reference point to the registered [Frame]
+    /// (full sensor frame coordinates). Returns None for points
+    /// projected behind the camera.
+    pub fn map(&self, point: Coord<f64>) -> Option<Coord<f64>> {
+        let p = self.homography * Vector3::new(point.x, point.y, 1.0);
+        if p[2] <= f64::EPSILON {
+            return None;
+        }
+        Some(Coord {
+            x: p[0] / p[2],
+            y: p[1] / p[2],
+        })
+    }
+}
+
+/// [Stacking] settings of the [crate::solver::Solver]: consecutive [Frame]s
+/// are registered using the rover orientation, then co-added by a [Stack].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stacking {
+    /// Number of [Frame]s to stack
+    pub depth: usize,
+    /// Focal length (in pixels)
+    pub focal_length: f64,
+    /// Optical center, in full sensor frame coordinates (pixels)
+    pub center: Coord<f64>,
+    /// Sigma clipping factor, see [Stack::clipping]
+    pub clipping: f64,
+}
+
+impl Stacking {
+    /// Builds new [Stacking] settings, see [Registration::from_attitude]
+    pub fn new(depth: usize, focal_length: f64, center: Coord<f64>) -> Self {
+        Self {
+            depth: depth.max(1),
+            focal_length,
+            center,
+            clipping: DEFAULT_CLIPPING,
+        }
+    }
+
+    /// Returns a copy of these [Stacking] settings with given sigma clipping factor
+    pub fn with_clipping(&self, clipping: f64) -> Self {
+        let mut s = *self;
+        s.clipping = clipping;
+        s
+    }
+}
+
+/// Per-pixel [Accumulator] of a [Stack]: running mean and variance
+/// of the samples kept so far (Welford's method).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Accumulator {
+    /// Running mean
+    mean: f32,
+    /// Sum of squared deviations from the running mean
+    m2: f32,
+    /// Number of samples
+    count: u16,
+}
+
+impl Accumulator {
+    /// Returns the standard deviation of the samples, null below two samples
+    fn sigma(&self) -> f32 {
+        if self.count < 2 {
+            0.0
+        } else {
+            (self.m2 / (self.count - 1) as f32).sqrt()
+        }
+    }
+
+    /// Adds a sample, unless it lies further than clipping standard deviations
+    /// (at least noise) from the running mean. A single previous sample,
+    /// far above the new one, was the outlier: it is replaced.
+    fn add(&mut self, sample: f32, clipping: f32, noise: f32) {
+        if self.count > 0 {
+            let deviation = sample - self.mean;
+            if deviation.abs() > clipping * self.sigma().max(noise) {
+                if deviation > 0.0 || self.count > 1 {
+                    return;
+                }
+                *self = Self::default();
+            }
+        }
+        self.count = self.count.saturating_add(1);
+        let delta = sample - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (sample - self.mean);
+    }
+}
+
+/// Reference [Frame] geometry of a [Stack]
+#[derive(Debug, Clone, Copy)]
+struct Reference {
+    /// Origin, in full sensor frame coordinates
+    origin: (u16, u16),
+    /// Width (in pixels)
+    width: u16,
+    /// Height (in pixels)
+    height: u16,
+    /// Bit depth of the luminances
+    bit_depth: u8,
+    /// Background noise (standard deviation)
+    noise: f32,
+}
+
+/// [Stack] co-adds consecutive [Frame]s of the same scene, registered to the
+/// first (reference) one, so stars too faint for a single [Frame] can be detected.
+/// Each pushed [Frame] is accumulated into caller provided per-pixel [Accumulator]s
+/// then released: memory does not depend on the number of [Frame]s, which may come
+/// from a [crate::VideoSource] reusing its buffer. Each output pixel is the clipped
+/// mean of its samples: samples deviating from the running mean by more than
+/// [Stack::clipping] standard deviations (at least the reference background noise)
+/// are rejected, like cosmic rays, satellites and flickering pixels.
+pub struct Stack<'a> {
+    /// Per-pixel [Accumulator]s, row after row
+    accumulators: &'a mut [Accumulator],
+    /// Reference geometry, once the first [Frame] is pushed
+    reference: Option<Reference>,
+    /// Number of [Frame]s
+    len: usize,
+    /// Sigma clipping factor: samples further than clipping standard
+    /// deviations from the running mean are rejected
+    pub clipping: f64,
+}
+
+impl<'a> Stack<'a> {
+    /// Builds a new empty [Stack], accumulating into given storage:
+    /// one [Accumulator] per reference pixel.
+    pub fn new(accumulators: &'a mut [Accumulator]) -> Self {
+        Self {
+            accumulators,
+            reference: None,
+            len: 0,
+            clipping: DEFAULT_CLIPPING,
+        }
+    }
+
+    /// Returns this [Stack] with given sigma clipping factor
+    pub fn with_clipping(mut self, clipping: f64) -> Self {
+        self.clipping = clipping;
+        self
+    }
+
+    /// Empties this [Stack]: the next [Frame] pushed becomes the reference
+    pub fn clear(&mut self) {
+        self.reference = None;
+        self.len = 0;
+    }
+
+    /// Accumulates a [Frame] and its [Registration] into this [Stack].
+    /// The first [Frame] is the reference (use [Registration::identity]).
+    /// Fails with [Error::VideoDimensionError] if the reference [Frame]
+    /// exceeds the [Accumulator] storage.
+    pub fn push<const XY: usize, P: Pixel>(
+        &mut self,
+        frame: &Frame<'_, XY, P>,
+        registration: Registration,
+    ) -> Result<(), Error> {
+        let reference = match self.reference {
+            Some(reference) => reference,
+            None => {
+                let (width, height) = (frame.width(), frame.height());
+                let size = width as usize * height as usize;
+                if size > self.accumulators.len() {
+                    return Err(Error::VideoDimensionError);
+                }
+                self.accumulators[..size].fill(Accumulator::default());
+                // quantization: at least one LSB
+                let (_, noise) = frame.luma_clipped_stats(DEFAULT_CLIPPING, 5);
+                let reference = Reference {
+                    origin: frame.origin(),
+                    width,
+                    height,
+                    bit_depth: frame.bitmap().bit_depth(),
+                    noise: (noise as f32).max(1.0),
+                };
+                self.reference = Some(reference);
+                reference
+            }
+        };
+
+        let clipping = self.clipping as f32;
+        let (x0, y0) = reference.origin;
+        for y in 0..reference.height {
+            for x in 0..reference.width {
+                let point = Coord {
+                    x: x0 as f64 + x as f64,
+                    y: y0 as f64 + y as f64,
+                };
+                let sample = registration.map(point).and_then(|p| Self::sample(frame, p));
+                if let Some(sample) = sample {
+                    let index = y as usize * reference.width as usize + x as usize;
+                    self.accumulators[index].add(sample as f32, clipping, reference.noise);
+                }
+            }
+        }
+
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Returns the number of stacked [Frame]s
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if this [Stack] is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns bilinear interpolation of the [Frame] at given sensor point,
+    /// None if any of the four neighbors is outside or ignored
+    fn sample<const XY: usize, P: Pixel>(
+        frame: &Frame<'_, XY, P>,
+        point: Coord<f64>,
+    ) -> Option<f64> {
+        let (x0, y0) = frame.origin();
+        let (x, y) = (point.x - x0 as f64, point.y - y0 as f64);
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+
+        let (ix, iy) = (x.floor(), y.floor());
+        let (fx, fy) = (x - ix, y - iy);
+        let (ix, iy) = (ix as u32, iy as u32);
+        if ix >= frame.width() as u32 || iy >= frame.height() as u32 {
+            return None;
+        }
+
+        let mut value = 0.0;
+        for (dy, wy) in [(0, 1.0 - fy), (1, fy)] {
+            for (dx, wx) in [(0, 1.0 - fx), (1, fx)] {
+                let weight = wx * wy;
+                if weight == 0.0 {
+                    continue;
+                }
+                let (sx, sy) = (ix + dx, iy + dy);
+                if sx >= frame.width() as u32 || sy >= frame.height() as u32 {
+                    return None;
+                }
+                let (sx, sy) = (sx as u16, sy as u16);
+                if frame.is_ignored(sx, sy) {
+                    return None;
+                }
+                let luma = frame.get(sx, sy).map(|p| p.luma()).unwrap_or_default();
+                value += weight * luma as f64;
+            }
+        }
+        Some(value)
+    }
+
+    /// Writes the stacked [Frame] into buf. Pixels without samples (ignored
+    /// in every [Frame]) are filled with the mean of the stacked pixels.
+    /// Choose Q deeper than the stacked [Frame]s (e.g. Gray16 for Gray8 frames)
+    /// to retain the precision below one LSB: luminances are scaled to the
+    /// extra bits, and clamped to [Pixel::MAX_LUMA].
+    /// The stacked [Frame] has the reference origin, and retains the settings
+    /// of given [Frame] (typically the last pushed one) that do not depend on
+    /// the pixel format, but its [crate::frame::background::Background]
+    /// which no longer matches the stacked noise.
+    /// Fails with [Error::VideoDimensionError] if this [Stack] is empty
+    /// or buf is too small.
+    pub fn combine<'b, const XY: usize, P: Pixel, Q: Pixel>(
+        &self,
+        frame: &Frame<'b, XY, P>,
+        buf: &'b mut [Q; XY],
+    ) -> Result<Frame<'b, XY, Q>, Error> {
+        let Some(reference) = self.reference else {
+            return Err(Error::VideoDimensionError);
+        };
+
+        let (width, height) = (reference.width, reference.height);
+        let size = width as usize * height as usize;
+        if size > XY {
+            return Err(Error::VideoDimensionError);
+        }
+
+        let accumulators = &self.accumulators[..size];
+        let (n, sum) = accumulators
+            .iter()
+            .filter(|acc| acc.count > 0)
+            .fold((0, 0.0), |(n, sum), acc| (n + 1, sum + acc.mean as f64));
+        let fill = sum / n.max(1) as f64;
+
+        let bit_depth = reference.bit_depth.min(Q::BIT_DEPTH);
+        let shift = Q::BIT_DEPTH - bit_depth;
+        let scale = (1_u32 << shift) as f64;
+        let max_luma = ((1_u32 << bit_depth) - 1) as f64 * scale;
+        for (pixel, acc) in buf.iter_mut().zip(accumulators) {
+            let value = if acc.count > 0 { acc.mean as f64 } else { fill };
+            *pixel = Q::from_luma((value * scale).round().clamp(0.0, max_luma) as u16);
+        }
+
+        let bitmap = BitMap::from_buffer(width, height, buf)?.with_bit_depth(bit_depth + shift);
+        let mut stacked = frame.with_bitmap(bitmap);
+        stacked.origin = reference.origin;
+        stacked.background = None;
+        stacked.saturation = Some(
+            (frame.saturation_level() as f64 * scale)
+                .round()
+                .clamp(0.0, max_luma) as u16,
+        );
+        Ok(stacked)
+    }
+}
@@ -0,0 +1,374 @@
+//! Satellite, aircraft and meteor streaks
+use geo::Coord;
+
+use crate::frame::{
+    blob::{Blob, Blobs},
+    component::Pixel,
+    mask::Mask,
+    Frame,
+};
+
+/// Maximal number of [Streak]s rejected by the star finder,
+/// see [Frame::with_streak_rejection]
+pub const MAX_STREAKS: usize = 8;
+
+/// Variance of a uniformly lit pixel (in squared pixels)
+const PIXEL_VARIANCE: f64 = 1.0 / 12.0;
+
+/// [Streak] is a straight trail (satellite, aircraft, meteor..)
+/// crossing the [Frame], expressed in [Frame] coordinates (pixels).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Streak {
+    /// First end point
+    pub start: Coord<f64>,
+    /// Second end point
+    pub end: Coord<f64>,
+    /// Trail width (in pixels)
+    pub width: f64,
+    /// Total luminance, at native bit depth
+    pub flux: f64,
+}
+
+impl Streak {
+    /// Returns length of this [Streak] (in pixels)
+    pub fn length(&self) -> f64 {
+        (self.end.x - self.start.x).hypot(self.end.y - self.start.y)
+    }
+
+    /// Returns orientation of this [Streak], counter clockwise
+    /// from the x axis (in radians)
+    pub fn angle(&self) -> f64 {
+        (self.end.y - self.start.y).atan2(self.end.x - self.start.x)
+    }
+
+    /// Returns distance (in pixels) between given point and this [Streak] segment
+    pub fn distance(&self, point: Coord<f64>) -> f64 {
+        let (dx, dy) = (self.end.x - self.start.x, self.end.y - self.start.y);
+        let length2 = dx * dx + dy * dy;
+        let t = if length2 > 0.0 {
+            (((point.x - self.start.x) * dx + (point.y - self.start.y) * dy) / length2)
+                .clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        (point.x - self.start.x - t * dx).hypot(point.y - self.start.y - t * dy)
+    }
+
+    /// Returns true if given point lies within this [Streak],
+    /// widened by margin (in pixels)
+    pub fn contains(&self, point: Coord<f64>, margin: f64) -> bool {
+        self.distance(point) <= self.width / 2.0 + margin
+    }
+
+    /// Returns unit vector from start to end point
+    fn direction(&self) -> Coord<f64> {
+        let length = self.length();
+        if length > 0.0 {
+            Coord {
+                x: (self.end.x - self.start.x) / length,
+                y: (self.end.y - self.start.y) / length,
+            }
+        } else {
+            Coord { x: 1.0, y: 0.0 }
+        }
+    }
+
+    /// Returns distance (in pixels) between given point and the
+    /// (infinite) axis of this [Streak]
+    fn axis_distance(&self, point: Coord<f64>) -> f64 {
+        let u = self.direction();
+        ((point.x - self.start.x) * u.y - (point.y - self.start.y) * u.x).abs()
+    }
+
+    /// Returns the [Streak] spanning both this [Streak] and other,
+    /// along the axis of this [Streak]
+    fn link(&self, other: &Self) -> Self {
+        let u = self.direction();
+        let project = |p: Coord<f64>| (p.x - self.start.x) * u.x + (p.y - self.start.y) * u.y;
+        let (min, max) = [self.start, self.end, other.start, other.end]
+            .into_iter()
+            .map(project)
+            .fold((f64::MAX, f64::MIN), |(min, max), t| {
+                (min.min(t), max.max(t))
+            });
+        Self {
+            start: Coord {
+                x: self.start.x + min * u.x,
+                y: self.start.y + min * u.y,
+            },
+            end: Coord {
+                x: self.start.x + max * u.x,
+                y: self.start.y + max * u.y,
+            },
+            width: self.width.max(other.width),
+            flux: self.flux + other.flux,
+        }
+    }
+
+    /// Sets, within mask, the pixels covered by this [Streak] widened by margin
+    /// (in pixels). The [Streak] is detected on given [Frame], while mask is
+    /// expressed in full sensor frame coordinates, as expected by
+    /// [Frame::with_exclusion_mask].
+    pub fn mask<const XY: usize, P: Pixel>(
+        &self,
+        frame: &Frame<'_, XY, P>,
+        mask: &mut Mask<'_>,
+        margin: f64,
+    ) {
+        let (x0, y0) = frame.origin();
+        self.for_each_pixel(frame.width(), frame.height(), margin, |x, y| {
+            mask.set(x0.saturating_add(x), y0.saturating_add(y), true);
+        });
+    }
+
+    /// Calls f for each (x, y) pixel of a (width, height) [Frame]
+    /// covered by this [Streak] widened by margin (in pixels)
+    pub(crate) fn for_each_pixel(
+        &self,
+        width: u16,
+        height: u16,
+        margin: f64,
+        mut f: impl FnMut(u16, u16),
+    ) {
+        let reach = self.width / 2.0 + margin;
+        let x_min = (self.start.x.min(self.end.x) - reach).floor().max(0.0) as u32;
+        let y_min = (self.start.y.min(self.end.y) - reach).floor().max(0.0) as u32;
+        let x_max = (self.start.x.max(self.end.x) + reach).ceil().max(0.0) as u32;
+        let y_max = (self.start.y.max(self.end.y) + reach).ceil().max(0.0) as u32;
+        for y in y_min..=y_max.min(height.saturating_sub(1) as u32) {
+            for x in x_min..=x_max.min(width.saturating_sub(1) as u32) {
+                let point = Coord {
+                    x: x as f64,
+                    y: y as f64,
+                };
+                if self.contains(point, margin) {
+                    f(x as u16, y as u16);
+                }
+            }
+        }
+    }
+}
+
+/// Default maximal angle between two linked [Streak] pieces (in radians)
+pub const DEFAULT_ALIGNMENT: f64 = 0.1;
+
+/// [StreakDetector] identifies the [Streak]s among the detected [Blob]s:
+/// elongated components, modelled by the principal axis of their luminance
+/// distribution. Collinear pieces are linked together, so a trail broken
+/// by the detection threshold (faint, flashing or tumbling objects)
+/// is reported as a single [Streak].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StreakDetector {
+    /// Minimal length (in pixels), once the pieces are linked
+    pub min_length: f64,
+    /// Minimal length / width ratio of each piece
+    pub min_elongation: f64,
+    /// Pixels added around each [Streak] when rejecting
+    /// the star candidates, or linking the pieces (in pixels)
+    pub margin: f64,
+    /// Maximal angle between two linked pieces (in radians)
+    pub alignment: f64,
+}
+
+impl Default for StreakDetector {
+    fn default() -> Self {
+        Self {
+            min_length: 20.0,
+            min_elongation: 5.0,
+            margin: 2.0,
+            alignment: DEFAULT_ALIGNMENT,
+        }
+    }
+}
+
+impl StreakDetector {
+    /// Builds a new [StreakDetector], with [DEFAULT_ALIGNMENT]
+    pub fn new(min_length: f64, min_elongation: f64, margin: f64) -> Self {
+        Self {
+            min_length,
+            min_elongation,
+            margin,
+            alignment: DEFAULT_ALIGNMENT,
+        }
+    }
+
+    /// Returns a copy of this [StreakDetector] with given alignment,
+    /// see [StreakDetector::alignment]
+    pub fn with_alignment(&self, alignment: f64) -> Self {
+        let mut s = *self;
+        s.alignment = alignment;
+        s
+    }
+
+    /// Returns the [Streak] modelling given [Blob], if it is one
+    /// on its own (without linking)
+    pub fn classify(&self, blob: &Blob) -> Option<Streak> {
+        self.piece(blob)
+            .filter(|streak| streak.length() >= self.min_length)
+    }
+
+    /// Returns true if given piece extends given [Streak]: both are
+    /// aligned, and the piece lies along the [Streak] axis, widened by margin
+    fn extends(&self, streak: &Streak, piece: &Streak) -> bool {
+        let (u, v) = (streak.direction(), piece.direction());
+        let tolerance = (streak.width + piece.width) / 2.0 + self.margin;
+        (u.x * v.y - u.y * v.x).abs() <= self.alignment.sin()
+            && streak.axis_distance(piece.start) <= tolerance
+            && streak.axis_distance(piece.end) <= tolerance
+    }
+
+    /// Returns the [Streak] piece modelling given elongated [Blob], if it is one
+    fn piece(&self, blob: &Blob) -> Option<Streak> {
+        let (xx, yy, xy) = blob.second_moments();
+        let delta = ((xx - yy) / 2.0).hypot(xy);
+        let major = ((xx + yy) / 2.0 + delta).max(0.0) + PIXEL_VARIANCE;
+        let minor = ((xx + yy) / 2.0 - delta).max(0.0) + PIXEL_VARIANCE;
+
+        // uniformly lit segment: variance = length² / 12
+        let length = (12.0 * major).sqrt();
+        let width = (12.0 * minor).sqrt();
+        if length < self.min_elongation * width {
+            return None;
+        }
+
+        let angle = 0.5 * (2.0 * xy).atan2(xx - yy);
+        let (dx, dy) = (angle.cos() * length / 2.0, angle.sin() * length / 2.0);
+        let center = blob.centroid();
+        Some(Streak {
+            start: Coord {
+                x: center.x - dx,
+                y: center.y - dy,
+            },
+            end: Coord {
+                x: center.x + dx,
+                y: center.y + dy,
+            },
+            width,
+            flux: blob.flux(),
+        })
+    }
+
+    /// Returns the (up to N) [Streak]s among given [Blobs], by decreasing flux.
+    /// Elongated [Blob]s are linked to the longest collinear piece first,
+    /// the [StreakDetector::min_length] then applies to the linked [Streak]s.
+    pub fn detect<const M: usize, const N: usize>(&self, blobs: &Blobs<M>) -> Streaks<N> {
+        let mut pieces = [Streak::default(); M];
+        let mut len = 0;
+        for piece in blobs.iter().filter_map(|blob| self.piece(blob)) {
+            pieces[len] = piece;
+            len += 1;
+        }
+        let pieces = &mut pieces[..len];
+        pieces.sort_unstable_by(|a, b| b.length().total_cmp(&a.length()));
+
+        let mut linked = [false; M];
+        let mut streaks = Streaks::default();
+        for i in 0..pieces.len() {
+            if linked[i] {
+                continue;
+            }
+            let mut streak = pieces[i];
+            for j in i + 1..pieces.len() {
+                if !linked[j] && self.extends(&streak, &pieces[j]) {
+                    streak = streak.link(&pieces[j]);
+                    linked[j] = true;
+                }
+            }
+            if streak.length() >= self.min_length {
+                streaks.push(streak);
+            }
+        }
+        streaks
+            .as_mut_slice()
+            .sort_unstable_by(|a, b| b.flux.total_cmp(&a.flux));
+        streaks
+    }
+}
+
+/// [Streaks] is the fixed capacity list of (at most N) [Streak]s of a [Frame]
+#[derive(Debug, Clone, Copy)]
+pub struct Streaks<const N: usize> {
+    streaks: [Streak; N],
+    len: usize,
+    overflow: usize,
+}
+
+impl<const N: usize> Default for Streaks<N> {
+    fn default() -> Self {
+        Self {
+            streaks: [Streak::default(); N],
+            len: 0,
+            overflow: 0,
+        }
+    }
+}
+
+impl<const N: usize> Streaks<N> {
+    /// Stores a [Streak], replacing the faintest one when full
+    fn push(&mut self, streak: Streak) {
+        if self.len < N {
+            self.streaks[self.len] = streak;
+            self.len += 1;
+            return;
+        }
+        self.overflow += 1;
+        if let Some(faintest) = self
+            .streaks
+            .iter_mut()
+            .min_by(|a, b| a.flux.total_cmp(&b.flux))
+        {
+            if streak.flux > faintest.flux {
+                *faintest = streak;
+            }
+        }
+    }
+
+    /// Returns number of [Streak]s
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no [Streak] was found
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns number of [Streak]s that could not be stored
+    pub fn overflow(&self) -> usize {
+        self.overflow
+    }
+
+    /// Returns [Streak]s as a slice
+    pub fn as_slice(&self) -> &[Streak] {
+        &self.streaks[..self.len]
+    }
+
+    /// Returns mutable [Streak]s slice
+    fn as_mut_slice(&mut self) -> &mut [Streak] {
+        &mut self.streaks[..self.len]
+    }
+
+    /// Iterates the [Streak]s
+    pub fn iter(&self) -> core::slice::Iter<'_, Streak> {
+        self.as_slice().iter()
+    }
+
+    /// Returns true if given point lies within one of the [Streak]s,
+    /// widened by margin (in pixels)
+    pub fn contains(&self, point: Coord<f64>, margin: f64) -> bool {
+        self.iter().any(|streak| streak.contains(point, margin))
+    }
+
+    /// Sets, within mask, the pixels covered by the [Streak]s.
+    /// See [Streak::mask].
+    pub fn mask<const XY: usize, P: Pixel>(
+        &self,
+        frame: &Frame<'_, XY, P>,
+        mask: &mut Mask<'_>,
+        margin: f64,
+    ) {
+        for streak in self.iter() {
+            streak.mask(frame, mask, margin);
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! Star detection threshold strategies
+use crate::{
+    frame::{
+        background::{Background, Tile},
+        component::{Pixel, UnderlyingComponent},
+        histogram::Histogram,
+        mask::Mask,
+        Frame,
+    },
+    Error,
+};
+
+/// Default sigma multiplier of the [SigmaK] strategy
+pub const DEFAULT_SIGMA_K: f64 = 5.0;
+
+/// Number of bins of the histogram based strategies
+const HISTOGRAM_BINS: usize = 1024;
+
+/// [Threshold] strategies decide which pixels of a [Frame] may belong to a star.
+/// Implement this trait to tune the detection to your camera, and
+/// attach it with [Frame::with_threshold] or [crate::prelude::Solver::with_threshold].
+pub trait Threshold<const XY: usize, P: Pixel = UnderlyingComponent> {
+    /// Flags, within mask, the pixels of given [Frame] that may belong to a star.
+    /// Pixels ignored by the [Frame] (bad pixels) should not be flagged.
+    /// Returns the number of flagged pixels.
+    /// Should fail with [Error::VideoDimensionError] if mask dimensions
+    /// do not match the [Frame].
+    fn detect(&self, frame: &Frame<'_, XY, P>, mask: &mut Mask<'_>) -> Result<usize, Error>;
+}
+
+/// Flags pixels at or above given luminance
+fn detect_above<const XY: usize, P: Pixel>(
+    frame: &Frame<'_, XY, P>,
+    threshold: u16,
+    mask: &mut Mask<'_>,
+) -> Result<usize, Error> {
+    let (width, height) = (frame.width(), frame.height());
+    if (mask.width(), mask.height()) != (width, height) {
+        return Err(Error::VideoDimensionError);
+    }
+
+    let mut count = 0;
+    for y in 0..height {
+        for x in 0..width {
+            let luma = frame.get(x, y).map(|p| p.luma()).unwrap_or_default();
+            let detected = !frame.is_ignored(x, y) && luma >= threshold;
+            mask.set(x, y, detected);
+            count += detected as usize;
+        }
+    }
+    Ok(count)
+}
+
+/// [SigmaK] flags pixels brighter than the background by more than k std deviations.
+/// The local [Background] is used when attached to the [Frame],
+/// the global statistics otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SigmaK {
+    /// Sigma multiplier
+    pub k: f64,
+}
+
+impl Default for SigmaK {
+    fn default() -> Self {
+        Self { k: DEFAULT_SIGMA_K }
+    }
+}
+
+impl SigmaK {
+    /// Builds a new [SigmaK] strategy
+    pub fn new(k: f64) -> Self {
+        Self { k }
+    }
+}
+
+impl<const XY: usize, P: Pixel> Threshold<XY, P> for SigmaK {
+    fn detect(&self, frame: &Frame<'_, XY, P>, mask: &mut Mask<'_>) -> Result<usize, Error> {
+        frame.detection_mask(self.k, mask)
+    }
+}
+
+/// [Percentile] flags the brightest pixels: those above the luminance
+/// below which lies the given fraction of the [Frame] (bin resolution).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Percentile {
+    /// Fraction of the [Frame] considered as background (0.0..=1.0)
+    pub fraction: f64,
+}
+
+impl Percentile {
+    /// Builds a new [Percentile] strategy
+    pub fn new(fraction: f64) -> Self {
+        Self { fraction }
+    }
+}
+
+impl<const XY: usize, P: Pixel> Threshold<XY, P> for Percentile {
+    fn detect(&self, frame: &Frame<'_, XY, P>, mask: &mut Mask<'_>) -> Result<usize, Error> {
+        let histogram = Histogram::<HISTOGRAM_BINS>::from_frame(frame);
+        let threshold = histogram.percentile(self.fraction);
+        let threshold = threshold.saturating_add(histogram.bin_width() as u16);
+        detect_above(frame, threshold, mask)
+    }
+}
+
+/// [Otsu] flags pixels at or above the luminance threshold
+/// selected by Otsu's method. See [Histogram::otsu].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Otsu;
+
+impl<const XY: usize, P: Pixel> Threshold<XY, P> for Otsu {
+    fn detect(&self, frame: &Frame<'_, XY, P>, mask: &mut Mask<'_>) -> Result<usize, Error> {
+        let threshold = Histogram::<HISTOGRAM_BINS>::from_frame(frame).otsu();
+        detect_above(frame, threshold, mask)
+    }
+}
+
+/// [AdaptiveLocal] estimates the local [Background] of each [Frame]
+/// (with square tiles of tile_size pixels), then flags pixels brighter
+/// than their local background by more than k std deviations.
+/// T is the maximal number of tiles, see [Background::tiles].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveLocal<const T: usize = 256> {
+    /// Tile size (in pixels)
+    pub tile_size: u16,
+    /// Sigma multiplier
+    pub k: f64,
+}
+
+impl<const T: usize> AdaptiveLocal<T> {
+    /// Builds a new [AdaptiveLocal] strategy
+    pub fn new(tile_size: u16, k: f64) -> Self {
+        Self { tile_size, k }
+    }
+}
+
+impl<const T: usize> Default for AdaptiveLocal<T> {
+    fn default() -> Self {
+        Self {
+            tile_size: 64,
+            k: DEFAULT_SIGMA_K,
+        }
+    }
+}
+
+impl<const T: usize, const XY: usize, P: Pixel> Threshold<XY, P> for AdaptiveLocal<T> {
+    fn detect(&self, frame: &Frame<'_, XY, P>, mask: &mut Mask<'_>) -> Result<usize, Error> {
+        let mut tiles = [Tile::default(); T];
+        let background = Background::estimate(frame, self.tile_size, &mut tiles)?;
+        frame
+            .with_background(&background)
+            .detection_mask(self.k, mask)
+    }
+}
@@ -18,12 +18,19 @@ extern crate std;
 pub mod frame;
 pub mod solver;
 
+#[allow(dead_code)]
 pub(crate) mod tracker;
 
-use frame::Frame;
+use frame::{
+    component::{Pixel, UnderlyingComponent},
+    Frame,
+};
 
 pub mod prelude {
     pub use crate::frame::{Frame, BitMap};
+    pub use crate::frame::component::{Pixel, Gray8, Gray16, Rgb8, YCbCr8};
+    #[cfg(feature = "std")]
+    pub use crate::frame::OwnedBitMap;
     pub use crate::solver::Solver;
     pub use nalgebra::{Rotation3, Matrix3};
     pub use hifitime::Epoch;
@@ -33,11 +40,13 @@ pub mod prelude {
 /// Video [Frame]s to the solver.
 /// Generics:
 /// - XY: maximal total video frame size (in pixels)
-/// to ever be published. For memory allocation purposes.
-pub trait VideoSource<const XY: usize> {
-    fn next(&mut self) -> Option<Frame<XY>>;
+///   to ever be published. For memory allocation purposes.
+/// - P: [Pixel] format of the published [Frame]s
+pub trait VideoSource<const XY: usize, P: Pixel = UnderlyingComponent> {
+    fn next(&mut self) -> Option<Frame<'_, XY, P>>;
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Error {
     /// Internal error due to bad video format
     /// considerations. Should never happen!
@@ -45,4 +54,13 @@ pub enum Error {
     /// Internal error due to bad dimensions.
     /// Should never happen!
     VideoDimensionError,
+    /// Star centroid could not be estimated: no signal
+    /// above the background, or diverging PSF fit.
+    CentroidingError,
+    /// Invalid filter kernel: even size,
+    /// PSF of wrong dimensions or null sum.
+    KernelError,
+    /// Missing or inconsistent exposure metadata:
+    /// bracketed frames must describe their exposure.
+    ExposureError,
 }
@@ -1,14 +1,27 @@
 //! Celestian Navigation Solver
 
 use crate::{
-    prelude::{Epoch, Frame, Matrix3, Rotation3},
-    VideoSource,
+    frame::{
+        bad_pixels::BadPixelMap,
+        calibration::Calibration,
+        candidate::StarCandidates,
+        centroid::Centroiding,
+        component::{Pixel, UnderlyingComponent},
+        filter::{Border, Filter},
+        motion::Motion,
+        pyramid::{Binning, Pyramid, MAX_LEVELS},
+        quality::{Quality, QualityCriteria},
+        selection::{Selection, MAX_CANDIDATES},
+        stack::{Accumulator, Registration, Stack, Stacking},
+        threshold::Threshold,
+    },
+    prelude::{Epoch, Frame, Rotation3},
+    Error, VideoSource,
 };
 
-use nalgebra::{DMatrix, Matrix1x4};
+use nalgebra::{Matrix1x4, Vector3};
 
 use geo::Coord;
-use crate::tracker::Tracker;
 
 /// Internal Finite [State] Machine
 #[derive(Debug, Default, Clone)]
@@ -22,25 +35,177 @@ enum State {
     PostProcessing,
 }
 
+/// Star detection [Pipeline] of the [Solver]: settings and storage
+/// applied to each captured [Frame], kept apart from the [VideoSource]
+/// that the captured [Frame] borrows. Each optional step is stored
+/// along with its [Frame] storage.
+struct Pipeline<'a, const XY: usize, P: Pixel> {
+    /// Dark, bias and flat field [Calibration], and calibrated [Frame] storage (if any)
+    calibration: Option<(Calibration<'a>, &'a mut [P; XY])>,
+    /// Hot and dead pixels (if any)
+    bad_pixels: Option<&'a BadPixelMap<'a>>,
+    /// Star detection [Threshold] strategy (if any)
+    threshold: Option<&'a dyn Threshold<XY, P>>,
+    /// Detection mask storage (empty until [Solver::with_mask_storage])
+    mask_bits: &'a mut [u32],
+    /// Star [Centroiding] method
+    centroiding: Centroiding,
+    /// Star [Selection] policy
+    selection: Selection,
+    /// Denoising [Filter], and filtered [Frame] storage (if any)
+    filter: Option<(Filter<'a>, &'a mut [P; XY])>,
+    /// Camera [Motion] during the exposure (if any)
+    motion: Option<Motion>,
+    /// Multi-frame [Stacking], [Stack] being accumulated,
+    /// and stacked [Frame] storage (if any)
+    stacking: Option<(Stacking, Stack<'a>, &'a mut [P; XY])>,
+    /// Camera orientation of the reference [Frame] of the [Stack]
+    stack_rot3: Rotation3<f64>,
+    /// Coarse detection [Binning], [Pyramid] level,
+    /// and binned levels storage (if any)
+    coarse_detection: Option<(Binning, usize, &'a mut [P; XY])>,
+    /// Frame [QualityCriteria] (if any)
+    quality_criteria: Option<QualityCriteria>,
+    /// [Quality] of the latest processed [Frame]
+    quality: Option<Quality>,
+}
+
+impl<'a, const XY: usize, P: Pixel> Pipeline<'a, XY, P> {
+    /// Builds a new [Pipeline], without detection mask storage
+    fn new() -> Self {
+        Self {
+            calibration: None,
+            bad_pixels: None,
+            threshold: None,
+            mask_bits: &mut [],
+            centroiding: Default::default(),
+            selection: Default::default(),
+            filter: None,
+            motion: None,
+            stacking: None,
+            stack_rot3: Default::default(),
+            coarse_detection: None,
+            quality_criteria: None,
+            quality: None,
+        }
+    }
+
+    /// Runs the star detection on a captured [Frame], the camera having
+    /// given orientation. Returns the selected stars, None when the [Frame]
+    /// was stacked or skipped.
+    fn process<const K: usize>(
+        &mut self,
+        captured: Frame<'_, XY, P>,
+        orientation: Rotation3<f64>,
+    ) -> Result<Option<StarCandidates<4>>, Error> {
+        // The algorithm is divded in several steps
+        // - apply brightness threshold detector
+        // - brightness histogram sorting and isolation
+        // - finaly isolate central coordinates
+
+        // correct the sensor prior to any other processing
+        let mut frame = captured;
+        if let Some((calibration, buf)) = &mut self.calibration {
+            frame = calibration.apply(&captured, buf)?;
+        }
+
+        if let Some(bad_pixels) = self.bad_pixels {
+            frame = frame.with_bad_pixels(bad_pixels);
+        }
+
+        // co-add consecutive frames, registered using the rover orientation
+        if let Some((stacking, stack, buf)) = &mut self.stacking {
+            let registration = if stack.is_empty() {
+                self.stack_rot3 = orientation;
+                Registration::identity()
+            } else {
+                Registration::from_attitude(
+                    &self.stack_rot3,
+                    &orientation,
+                    stacking.focal_length,
+                    stacking.center,
+                )
+            };
+
+            if let Err(e) = stack.push(&frame, registration) {
+                stack.clear();
+                return Err(e);
+            }
+
+            if stack.len() < stacking.depth {
+                return Ok(None);
+            }
+
+            let stacked = stack.combine(&frame, buf);
+            stack.clear();
+            frame = stacked?;
+        }
+
+        let mut frame = frame
+            .with_centroiding(self.centroiding)
+            .with_selection(self.selection);
+
+        if let Some(threshold) = self.threshold {
+            frame = frame.with_threshold(threshold);
+        }
+
+        if let Some(motion) = self.motion {
+            frame = frame.with_motion(motion);
+        }
+
+        // denoise prior to the detection
+        if let Some((filter, buf)) = &mut self.filter {
+            frame = frame.filter::<K>(filter, Border::default(), buf)?;
+        }
+
+        // evaluate stars location within snapshot frame,
+        // coarsely on a binned level when requested
+        let candidates = match &mut self.coarse_detection {
+            Some((binning, level, buf)) => Pyramid::new(frame, *binning, *level + 1, buf)?
+                .star_candidates::<MAX_CANDIDATES>(*level, self.mask_bits)?,
+            None => frame.star_candidates::<MAX_CANDIDATES>(self.mask_bits)?,
+        };
+
+        // skip poor frames
+        if let Some(criteria) = self.quality_criteria {
+            let quality = Quality::assess(&frame, &candidates, &criteria);
+            self.quality = Some(quality);
+            if !criteria.accepts(&quality) {
+                return Ok(None);
+            }
+        }
+
+        let stars = self
+            .selection
+            .select::<MAX_CANDIDATES, 4, XY, _>(&frame, &candidates);
+        Ok(Some(stars))
+    }
+}
+
 /// [Solver] to run the Celestial navigation computation.
 /// ## Generics:
 /// - V: [VideoSource] implementation
 /// - XY: [usize] maximal snapshot size to ever be supported.
-/// For memory allocation purposes.
-pub struct Solver<'a, const XY: usize, V: VideoSource<XY>> {
+///   For memory allocation purposes.
+/// - P: [Pixel] format of the [VideoSource]
+pub struct Solver<'a, const XY: usize, V: VideoSource<XY, P>, P: Pixel = UnderlyingComponent> {
     /// [VideoSource] implementation
     video_src: V,
-    /// Latest video [Frame] snapshot
-    video_frame: Option<Frame<'a, XY>>,
     /// Internal state
     state: State,
     /// Fixed Body / Camera rotation matrix
     body_camera_rot3: Rotation3<f64>,
     /// Zenith angles of 4 stars in sight
     zeniths: Matrix1x4<f64>,
+    /// Star detection [Pipeline]
+    pipeline: Pipeline<'a, XY, P>,
+    /// Stars selected in the latest processed [Frame]
+    stars: StarCandidates<4>,
+    /// [Error] that interrupted the processing of the latest [Frame] (if any)
+    error: Option<Error>,
 }
 
-impl<'a, const XY: usize, V: VideoSource<XY>> Solver<'a, XY, V> {
+impl<'a, const XY: usize, V: VideoSource<XY, P>, P: Pixel> Solver<'a, XY, V, P> {
 
     /// Builds a new [Solver].
     /// ## Inputs
@@ -48,16 +213,18 @@ impl<'a, const XY: usize, V: VideoSource<XY>> Solver<'a, XY, V> {
     /// - video_src: [VideoSource] is a [Frame] provider.
     /// 
     /// - body_camera: is the direct cosine rotation matrix,
-    /// expressed as [Rotation3] that can transform the camera frame
-    /// to the fixed body frame of the rover. Ideally you should calibrate
-    /// this matrix and the camera mount should remain stable throughout navigation.
+    ///   expressed as [Rotation3] that can transform the camera frame
+    ///   to the fixed body frame of the rover. Ideally you should calibrate
+    ///   this matrix and the camera mount should remain stable throughout navigation.
     pub fn new_fixed_body_camera(video_src: V, body_camera_rot3: Rotation3<f64>) -> Self {
         Self {
             body_camera_rot3,
-            video_frame: None,
-            video_src: video_src,
+            video_src,
             state: Default::default(),
             zeniths: Default::default(),
+            pipeline: Pipeline::new(),
+            stars: Default::default(),
+            error: None,
         }
     }
 
@@ -72,14 +239,150 @@ impl<'a, const XY: usize, V: VideoSource<XY>> Solver<'a, XY, V> {
     /// - video_src: [VideoSource] is a [Frame] provider.
     pub fn new(video_src: V) -> Self {
         Self {
-            video_frame: None,
-            video_src: video_src,
+            video_src,
             state: Default::default(),
             zeniths: Default::default(),
             body_camera_rot3: Default::default(),
+            pipeline: Pipeline::new(),
+            stars: Default::default(),
+            error: None,
+        }
+    }
+
+    /// Returns this [Solver] detecting stars within given mask storage,
+    /// of [crate::frame::mask::Mask::words] words for the largest [Frame]
+    /// to ever be processed. Without mask storage, the star detection
+    /// fails with [Error::VideoDimensionError] (see [Self::error]).
+    pub fn with_mask_storage(mut self, bits: &'a mut [u32]) -> Self {
+        self.pipeline.mask_bits = bits;
+        self
+    }
+
+    /// Returns this [Solver] applying given dark, bias and flat field
+    /// [Calibration] to each captured [Frame], ahead of any other processing
+    /// (stacking, filter..). buf stores the calibrated [Frame].
+    pub fn with_calibration(
+        mut self,
+        calibration: Calibration<'a>,
+        buf: &'a mut [P; XY],
+    ) -> Self {
+        self.pipeline.calibration = Some((calibration, buf));
+        self
+    }
+
+    /// Returns this [Solver] ignoring the hot and dead pixels of given
+    /// [BadPixelMap] (full sensor frame coordinates) in each captured [Frame].
+    pub fn with_bad_pixels(mut self, bad_pixels: &'a BadPixelMap<'a>) -> Self {
+        self.pipeline.bad_pixels = Some(bad_pixels);
+        self
+    }
+
+    /// Returns this [Solver] using given star detection [Threshold]
+    /// strategy, instead of the default [crate::frame::threshold::SigmaK].
+    pub fn with_threshold(mut self, threshold: &'a dyn Threshold<XY, P>) -> Self {
+        self.pipeline.threshold = Some(threshold);
+        self
+    }
+
+    /// Returns this [Solver] using given star [Centroiding] method
+    pub fn with_centroiding(mut self, centroiding: Centroiding) -> Self {
+        self.pipeline.centroiding = centroiding;
+        self
+    }
+
+    /// Returns this [Solver] using given star [Selection] policy.
+    /// The zenith solution relies on well separated bright stars.
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        self.pipeline.selection = selection;
+        self
+    }
+
+    /// Returns this [Solver] applying given denoising [Filter] before the
+    /// star detection, over the K x K window of [Self::resolve].
+    /// buf stores the filtered [Frame].
+    pub fn with_filter(
+        mut self,
+        filter: Filter<'a>,
+        buf: &'a mut [P; XY],
+    ) -> Self {
+        self.pipeline.filter = Some((filter, buf));
+        self
+    }
+
+    /// Returns this [Solver] compensating the camera [Motion] during the exposure.
+    /// Motion describes the camera optics and exposure, while its rate is
+    /// updated with [Self::set_angular_rate].
+    pub fn with_motion(mut self, motion: Motion) -> Self {
+        self.pipeline.motion = Some(motion);
+        self
+    }
+
+    /// Updates the rover angular rate (in rad/s), expressed in the fixed body frame,
+    /// typically measured by the IMU. Requires [Self::with_motion].
+    pub fn set_angular_rate(&mut self, rate: Vector3<f64>) {
+        if let Some(motion) = &mut self.pipeline.motion {
+            motion.rate = self.body_camera_rot3.inverse() * rate;
         }
     }
 
+    /// Returns this [Solver] stacking consecutive [Frame]s prior to the star
+    /// detection, to reach fainter stars. Frames are registered using the
+    /// orientation passed to [Self::resolve], and accumulated into accumulators
+    /// (one per pixel of the largest [Frame]): each captured [Frame] is released
+    /// once accumulated. buf stores the stacked [Frame].
+    pub fn with_stacking(
+        mut self,
+        stacking: Stacking,
+        accumulators: &'a mut [Accumulator],
+        buf: &'a mut [P; XY],
+    ) -> Self {
+        let stack = Stack::new(accumulators).with_clipping(stacking.clipping);
+        self.pipeline.stacking = Some((stacking, stack, buf));
+        self
+    }
+
+    /// Returns this [Solver] detecting the stars coarsely on given [Pyramid] level
+    /// (1 being the first binned level), then refining them at full resolution.
+    /// Much faster on small targets. buf stores the binned levels.
+    /// Fails with [Error::VideoDimensionError] if level is not within 1..[MAX_LEVELS].
+    pub fn with_coarse_detection(
+        mut self,
+        binning: Binning,
+        level: usize,
+        buf: &'a mut [P; XY],
+    ) -> Result<Self, Error> {
+        if !(1..MAX_LEVELS).contains(&level) {
+            return Err(Error::VideoDimensionError);
+        }
+        self.pipeline.coarse_detection = Some((binning, level, buf));
+        Ok(self)
+    }
+
+    /// Returns this [Solver] skipping the [Frame]s (out of focus, clouded..)
+    /// that do not meet given [QualityCriteria]. See [Self::quality].
+    pub fn with_quality_criteria(mut self, criteria: QualityCriteria) -> Self {
+        self.pipeline.quality_criteria = Some(criteria);
+        self
+    }
+
+    /// Returns the [Quality] of the latest processed [Frame],
+    /// when [Self::with_quality_criteria] is used
+    pub fn quality(&self) -> Option<Quality> {
+        self.pipeline.quality
+    }
+
+    /// Returns the stars selected in the latest processed [Frame]
+    pub fn stars(&self) -> &StarCandidates<4> {
+        &self.stars
+    }
+
+    /// Returns the [Error] that interrupted the processing of the latest
+    /// [Frame] (if any): typically undersized storage for the [Frame]
+    /// dimensions, or mismatching [Calibration] frames.
+    pub fn error(&self) -> Option<Error> {
+        self.error
+    }
+
     /// Reset the navigation filter.
     /// Use this in case of a bumpy ride to restart the camera mounting
     /// point coordinates mitigation. Expect errors until the filter has converged.
@@ -92,18 +395,19 @@ impl<'a, const XY: usize, V: VideoSource<XY>> Solver<'a, XY, V> {
     ///
     /// Generics:
     ///   - K: constant kernel size (in pixels)
-    ///   when isolating stars in [Frame] snapshot
+    ///     when isolating stars in [Frame] snapshot,
+    ///     see [Self::with_filter]
     ///
     /// Inputs
     /// - mutable [Solver]
     /// - t: snapshot [Epoch]
     /// - orientation_rot3: rover current orientation, coming from IMU or navigation
-    /// framework, expressed as [Rotation3]
+    ///   framework, expressed as [Rotation3]
     ///
     /// Outputs:
     /// - 3D coordinates update
     ///
-    pub fn resolve<const K: usize>(&'a mut self, t: Epoch, orientation_rot3: Rotation3<f64>) -> Option<Coord> {
+    pub fn resolve<const K: usize>(&mut self, _t: Epoch, orientation_rot3: Rotation3<f64>) -> Option<Coord> {
         match self.state {
             State::Capture => {
                 self.video_capture();
@@ -117,44 +421,53 @@ impl<'a, const XY: usize, V: VideoSource<XY>> Solver<'a, XY, V> {
         }
     }
 
-    /// Attempts to update the Video [Frame] snapshot.
-    /// Call this any time the [VideoSource] is ready.
-    /// This method is infaillible: if your [VideoSource] fails
-    /// to provide a new [Frame] we'll just work on the previous one.
-    pub fn video_capture(&'a mut self) {
-        self.video_frame = self.video_src.next();
+    /// Signals that the [VideoSource] is ready.
+    /// Call this any time the [VideoSource] is ready: the new [Frame]
+    /// is captured and released by [Self::video_processing], so the
+    /// [VideoSource] may reuse its buffer.
+    pub fn video_capture(&mut self) {
         self.state = State::VideoProcessing;
     }
 
-    /// Run the star detection algorithm on latest video [Frame] snapshot
+    /// Captures a new video [Frame] snapshot and runs the star detection algorithm on it.
+    /// This method is infaillible: if your [VideoSource] fails to provide a new [Frame],
+    /// or the [Frame] is stacked or skipped, a new capture is requested.
+    /// Processing failures are reported by [Self::error].
     pub fn video_processing<const K: usize>(&mut self, rot3: Rotation3<f64>) {
-        // The algorithm is divded in several steps
-        // - apply brightness threshold detector
-        // - brightness histogram sorting and isolation
-        // - finaly isolate central coordinates
+        let orientation = rot3 * self.body_camera_rot3;
 
-        if self.video_frame.is_none() {
+        let Some(captured) = self.video_src.next() else {
             self.state = State::Capture;
             return;
-        }
-
-        let mut frame = self.video_frame.as_ref().unwrap();
+        };
 
-        // evaluate stars location within snapshot frame
-        let coords = frame.star_coordinates_finder::<4>();
-        
+        match self.pipeline.process::<K>(captured, orientation) {
+            Ok(Some(stars)) => {
+                self.stars = stars;
+                self.error = None;
+                self.state = State::PostProcessing;
+            }
+            Ok(None) => {
+                self.error = None;
+                self.state = State::Capture;
+            }
+            Err(e) => {
+                self.error = Some(e);
+                self.state = State::Capture;
+            }
+        }
     }
 
     /// Run the 3D calculations and final projection
     ///
     /// ## Returns
     /// - [Coord] where x is the projected latitude (in radians)
-    /// and y the projected longitude (in radians) of the rover.
+    ///   and y the projected longitude (in radians) of the rover.
     pub fn post_processing(&mut self) -> Option<Coord> {
 
         // form p matrix
         // [p0, p1, ... p_n] where p_i = cos(zenith_i);
-        let p = Matrix1x4::from([
+        let _p = Matrix1x4::from([
             self.zeniths[0].cos(),
             self.zeniths[1].cos(),
             self.zeniths[2].cos(),
@@ -3,6 +3,8 @@
 use geo::Coord;
 use hifitime::Epoch;
 
+use crate::frame::candidate::StarCandidate;
+
 pub struct Star {
     // (x, y) coordinates within the video frame (fraction of X)
     pub xy: Coord<f64>,
@@ -14,6 +16,23 @@ pub struct Star {
     pub past_xy: Coord<f64>,
     // past t
     pub past_t: Epoch,
+    // latest measurements (flux, FWHM, SNR..), used to weight the identification
+    pub candidate: StarCandidate,
+}
+
+impl Star {
+    /// Builds a new [Star] from a [StarCandidate] measured at t
+    pub fn new(candidate: StarCandidate, t: Epoch) -> Self {
+        let xy = candidate.centroid.coord;
+        Self {
+            xy,
+            r: candidate.fwhm / 2.0,
+            t,
+            past_xy: xy,
+            past_t: t,
+            candidate,
+        }
+    }
 }
 
 /// Star [Tracker] designed to track N coordinates at the same time
@@ -22,6 +41,6 @@ pub struct Tracker<const N: usize> {
 }
 
 impl<const N: usize> Tracker<N> {
-    pub fn update(&mut self, xy: Coord, t: Epoch) {
+    pub fn update(&mut self, _xy: Coord, _t: Epoch) {
     }
 }
\ No newline at end of file
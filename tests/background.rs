@@ -0,0 +1,95 @@
+use celestial_nav::{
+    frame::{
+        background::{Background, Tile},
+        mask::Mask,
+        threshold::SigmaK,
+    },
+    prelude::{BitMap, Frame, Gray8},
+};
+
+use crate::common::render;
+
+const WIDTH: u16 = 64;
+const HEIGHT: u16 = 64;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Builds a sky with a strong horizontal gradient,
+/// and one faint star at (5, 5)
+fn gradient() -> [Gray8; XY] {
+    let mut sky = render(WIDTH, HEIGHT, |_, x, _| 3.0 * x);
+    sky[5 * WIDTH as usize + 5] = 120;
+    sky
+}
+
+#[test]
+fn background_grid() {
+    let sky = gradient();
+    let frame = Frame::new(64, 64, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+
+    let mut tiles = [Tile::default(); Background::tiles(WIDTH, HEIGHT, 16)];
+    assert_eq!(tiles.len(), 16);
+    assert!(Background::estimate(&frame, 16, &mut tiles[..8]).is_err());
+    assert!(Background::estimate(&frame, 0, &mut tiles).is_err());
+
+    let background = Background::estimate(&frame, 16, &mut tiles).unwrap();
+    assert_eq!(background.grid(), (4, 4));
+    assert_eq!(background.dimensions(), (WIDTH, HEIGHT));
+
+    // the star is rejected from the tile statistics
+    let tile = background.tile(0, 0).unwrap();
+    assert!((tile.mean - 22.5).abs() < 0.1);
+    assert!(background.tile(4, 0).is_none());
+
+    // the gradient is followed between tile centers
+    for x in 8..56 {
+        assert!((background.mean(x, 30) - 3.0 * x as f64).abs() < 1.0E-3);
+    }
+
+    // subtraction flattens the gradient
+    let mut buf = [0; XY];
+    let flat = background.subtract(&frame, &mut buf).unwrap();
+    assert!(flat.luma_mean() < 3.0);
+
+    // regions of interest keep their origin and settings
+    let threshold = SigmaK::new(5.0);
+    let roi = frame
+        .with_background(&background)
+        .with_threshold(&threshold)
+        .roi(2, 2, 16, 16)
+        .unwrap();
+    let mut buf = [0; XY];
+    let flat = background.subtract(&roi, &mut buf).unwrap();
+    assert_eq!(flat.origin(), (2, 2));
+    assert!(flat.luma_mean() < 3.0);
+
+    let mut bits = [0; Mask::words(16, 16)];
+    let mut mask = Mask::new(16, 16, &mut bits).unwrap();
+    assert_eq!(flat.detect(&mut mask), Ok(1));
+    assert!(mask.get(3, 3));
+}
+
+#[test]
+fn local_detection() {
+    let sky = gradient();
+    let frame = Frame::new(64, 64, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut mask = Mask::new(WIDTH, HEIGHT, &mut bits).unwrap();
+
+    // the global threshold misses the star
+    assert_eq!(frame.detection_mask(3.0, &mut mask).unwrap(), 0);
+
+    // the local threshold only detects the star
+    let mut tiles = [Tile::default(); Background::tiles(WIDTH, HEIGHT, 16)];
+    let background = Background::estimate(&frame, 16, &mut tiles).unwrap();
+    let frame = frame.with_background(&background);
+    assert_eq!(frame.detection_mask(3.0, &mut mask).unwrap(), 1);
+    assert!(mask.get(5, 5));
+
+    // regions of interest use the full frame background
+    let roi = frame.roi(4, 4, 8, 8).unwrap();
+    let mut bits = [0; Mask::words(8, 8)];
+    let mut mask = Mask::new(8, 8, &mut bits).unwrap();
+    assert_eq!(roi.detection_mask(3.0, &mut mask).unwrap(), 1);
+    assert!(mask.get(1, 1));
+}
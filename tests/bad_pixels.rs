@@ -0,0 +1,138 @@
+use celestial_nav::{
+    frame::{
+        bad_pixels::BadPixelMap, calibration::MasterFrame, component::UnderlyingComponent,
+        mask::Mask,
+    },
+    prelude::{BitMap, Frame, Gray8, Rotation3, Solver},
+    VideoSource,
+};
+
+/// Builds a 5x5 noisy dark background with one hot pixel at (2, 3)
+fn dark() -> [Gray8; 25] {
+    let mut dark = [0; 25];
+    for (i, p) in dark.iter_mut().enumerate() {
+        *p = 10 + (i % 3) as u8;
+    }
+    dark[3 * 5 + 2] = 200;
+    dark
+}
+
+#[test]
+fn mask() {
+    let mut bits = [0; Mask::words(10, 10)];
+    assert_eq!(bits.len(), 4);
+    let mut mask = Mask::new(10, 10, &mut bits).unwrap();
+    mask.set(9, 9, true);
+    mask.set(0, 3, true);
+    mask.set(10, 0, true);
+    assert!(mask.get(9, 9));
+    assert!(mask.get(0, 3));
+    assert!(!mask.get(10, 0));
+    assert_eq!(mask.count(), 2);
+    mask.set(9, 9, false);
+    assert_eq!(mask.count(), 1);
+
+    let mut bits = [0; 3];
+    assert!(Mask::new(10, 10, &mut bits).is_err());
+}
+
+#[test]
+fn hot_pixels_from_dark() {
+    let dark = dark();
+    let frame = Frame::new(5, 5, BitMap::from_slice(5, 5, &dark).unwrap()).unwrap();
+
+    let mut bits = [0; Mask::words(5, 5)];
+    let map = BadPixelMap::from_dark(&frame, 3.0, &mut bits).unwrap();
+    assert_eq!(map.count(), 1);
+    assert!(map.is_bad(2, 3));
+
+    // flagged pixels are ignored by the statistics
+    let mean = frame.luma_mean();
+    let frame = frame.with_bad_pixels(&map);
+    assert!(frame.luma_mean() < mean);
+    assert!((frame.luma_mean() - 262.0 / 24.0).abs() < 1.0E-9);
+
+    // and within regions of interest
+    let roi = frame.roi(1, 2, 3, 3).unwrap();
+    assert_eq!(roi.origin(), (1, 2));
+    assert!(roi.luma_mean() < 12.0);
+
+    // interpolation
+    let mut buf = [0; 25];
+    let interpolated = map.interpolate(&frame, &mut buf).unwrap();
+    let p = *interpolated.get(2, 3).unwrap();
+    assert!((10..=12).contains(&p));
+    assert_eq!(interpolated.get(0, 0), frame.get(0, 0));
+
+    // the region of interest settings are retained
+    let roi = roi.with_saturation_level(150);
+    let mut buf = [0; 25];
+    let interpolated = map.interpolate(&roi, &mut buf).unwrap();
+    assert_eq!(interpolated.origin(), (1, 2));
+    assert_eq!(interpolated.saturation_level(), 150);
+    assert!((10..=12).contains(interpolated.get(1, 1).unwrap()));
+    assert!(interpolated.luma_mean() < 12.0);
+}
+
+#[test]
+fn stuck_pixels() {
+    // stars move from one frame to another, the stuck pixel remains
+    let mut captures = [dark(), dark(), dark()];
+    captures[0][7] = 250;
+    captures[1][8] = 250;
+    captures[2][12] = 250;
+    let frames = [
+        Frame::new(5, 5, BitMap::from_slice(5, 5, &captures[0]).unwrap()).unwrap(),
+        Frame::new(5, 5, BitMap::from_slice(5, 5, &captures[1]).unwrap()).unwrap(),
+        Frame::new(5, 5, BitMap::from_slice(5, 5, &captures[2]).unwrap()).unwrap(),
+    ];
+
+    let mut bits = [0; Mask::words(5, 5)];
+    let map = BadPixelMap::from_fixed_pixels(&frames, 2, 3.0, &mut bits).unwrap();
+    assert_eq!(map.count(), 1);
+    assert!(map.is_bad(2, 3));
+}
+
+#[test]
+fn dead_pixels_from_flat() {
+    let gains = [1.0, 1.1, 0.05, 0.9];
+    let flat = MasterFrame::new(2, 2, &gains).unwrap();
+
+    let mut bits = [0; Mask::words(2, 2)];
+    let map = BadPixelMap::from_flat(&flat, 0.5, &mut bits).unwrap();
+    assert_eq!(map.count(), 1);
+    assert!(map.is_bad(0, 1));
+}
+
+/// Uniform sky, with a cluster of hot pixels
+struct HotPixels([UnderlyingComponent; 256]);
+
+impl VideoSource<256> for HotPixels {
+    fn next(&mut self) -> Option<Frame<'_, 256>> {
+        Frame::new(16, 16, BitMap::from_slice(16, 16, &self.0).ok()?).ok()
+    }
+}
+
+#[test]
+fn solver_bad_pixels() {
+    let mut raw = [UnderlyingComponent::gray8(30); 256];
+    let mut bits = [0; Mask::words(16, 16)];
+    let mut map = BadPixelMap::new(16, 16, &mut bits).unwrap();
+    for (x, y) in [(9, 4), (10, 4), (9, 5)] {
+        raw[y * 16 + x] = UnderlyingComponent::gray8(240);
+        map.flag(x as u16, y as u16);
+    }
+
+    // the hot pixels look like a star
+    let mut bits = [0; Mask::words(16, 16)];
+    let mut solver = Solver::new(HotPixels(raw)).with_mask_storage(&mut bits);
+    solver.video_processing::<1>(Rotation3::identity());
+    assert_eq!(solver.stars().len(), 1);
+
+    let mut bits = [0; Mask::words(16, 16)];
+    let mut solver = Solver::new(HotPixels(raw))
+        .with_mask_storage(&mut bits)
+        .with_bad_pixels(&map);
+    solver.video_processing::<1>(Rotation3::identity());
+    assert!(solver.stars().is_empty());
+}
@@ -0,0 +1,108 @@
+use celestial_nav::{
+    frame::{
+        blob::{Blobs, Connectivity},
+        mask::Mask,
+    },
+    prelude::{BitMap, Frame, Gray8},
+};
+
+/// Fills sky and the detection mask from a text pattern,
+/// where digits are luminance tens
+fn pattern<'a>(rows: &[&str], sky: &mut [Gray8], bits: &'a mut [u32]) -> Mask<'a> {
+    let (width, height) = (rows[0].len() as u16, rows.len() as u16);
+    let mut mask = Mask::new(width, height, bits).unwrap();
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let luma = c.to_digit(10).unwrap_or_default() as u8 * 10;
+            sky[y * width as usize + x] = luma;
+            mask.set(x as u16, y as u16, luma > 0);
+        }
+    }
+    mask
+}
+
+#[test]
+fn connectivity() {
+    let rows = ["1...", ".1..", "..1.", "...."];
+    let mut sky = [0; 16];
+    let mut bits = [0; 1];
+    let mask = pattern(&rows, &mut sky, &mut bits);
+    let frame = Frame::new(4, 4, BitMap::from_slice(4, 4, &sky).unwrap()).unwrap();
+
+    let blobs = Blobs::<8>::extract(&frame, &mask, Connectivity::Eight).unwrap();
+    assert_eq!(blobs.len(), 1);
+    assert_eq!(blobs.as_slice()[0].area(), 3);
+    assert_eq!(blobs.as_slice()[0].bbox(), (0, 0, 2, 2));
+
+    let blobs = Blobs::<8>::extract(&frame, &mask, Connectivity::Four).unwrap();
+    assert_eq!(blobs.len(), 3);
+    assert_eq!(blobs.overflow(), 0);
+}
+
+#[test]
+fn merged_components() {
+    // U shape: two components merging on the last line
+    let rows = ["1...1", "1...2", "1...1", "11911"];
+    let mut sky = [0; 20];
+    let mut bits = [0; 1];
+    let mask = pattern(&rows, &mut sky, &mut bits);
+    let frame = Frame::new(5, 4, BitMap::from_slice(5, 4, &sky).unwrap()).unwrap();
+
+    let blobs = Blobs::<4>::extract(&frame, &mask, Connectivity::Four).unwrap();
+    assert_eq!(blobs.len(), 1);
+
+    let blob = blobs.as_slice()[0];
+    assert_eq!(blob.area(), 11);
+    assert_eq!(blob.bbox(), (0, 0, 4, 3));
+    assert_eq!((blob.width(), blob.height()), (5, 4));
+    assert_eq!(blob.peak(), (2, 3, 90));
+    assert_eq!(blob.flux(), 200.0);
+}
+
+#[test]
+fn moments() {
+    let rows = ["......", ".1.1..", "..4...", ".1.1..", "......"];
+    let mut sky = [0; 30];
+    let mut bits = [0; 1];
+    let mask = pattern(&rows, &mut sky, &mut bits);
+    let frame = Frame::new(6, 5, BitMap::from_slice(6, 5, &sky).unwrap()).unwrap();
+
+    let blobs = Blobs::<4>::extract(&frame, &mask, Connectivity::Eight).unwrap();
+    assert_eq!(blobs.len(), 1);
+
+    let blob = blobs.as_slice()[0];
+    let centroid = blob.centroid();
+    assert!((centroid.x - 2.0).abs() < 1.0E-9);
+    assert!((centroid.y - 2.0).abs() < 1.0E-9);
+
+    let (xx, yy, xy) = blob.second_moments();
+    assert!((xx - 0.5).abs() < 1.0E-9);
+    assert!((yy - 0.5).abs() < 1.0E-9);
+    assert!(xy.abs() < 1.0E-9);
+}
+
+#[test]
+fn capacity() {
+    // the brightest components are retained
+    let rows = ["1.3.2", ".....", "4.1.5"];
+    let mut sky = [0; 15];
+    let mut bits = [0; 1];
+    let mask = pattern(&rows, &mut sky, &mut bits);
+    let frame = Frame::new(5, 3, BitMap::from_slice(5, 3, &sky).unwrap()).unwrap();
+
+    let mut blobs = Blobs::<2>::extract(&frame, &mask, Connectivity::Eight).unwrap();
+    assert_eq!(blobs.len(), 2);
+    assert_eq!(blobs.overflow(), 4);
+
+    blobs.sort_by_flux();
+    assert_eq!(blobs.as_slice()[0].peak(), (4, 2, 50));
+    assert_eq!(blobs.as_slice()[1].peak(), (0, 2, 40));
+
+    // dimensions mismatch
+    let mut bits = [0; 1];
+    let mask = Mask::new(4, 4, &mut bits).unwrap();
+    assert!(Blobs::<2>::extract(&frame, &mask, Connectivity::Eight).is_err());
+
+    let blobs = Blobs::<0>::default();
+    assert!(blobs.is_empty());
+}
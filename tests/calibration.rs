@@ -0,0 +1,234 @@
+use celestial_nav::{
+    frame::{
+        bad_pixels::BadPixelMap,
+        calibration::{Calibration, MasterFrame},
+        component::{color::Conversion, UnderlyingComponent},
+        mask::Mask,
+        threshold::Percentile,
+    },
+    prelude::{BitMap, Frame, Gray16, Pixel, Rgb8, Rotation3, Solver, YCbCr8},
+    Error, VideoSource,
+};
+
+#[test]
+fn median_combine() {
+    let captures: [[Gray16; 4]; 3] = [[10, 20, 30, 40], [12, 18, 1000, 40], [11, 22, 31, 39]];
+    let frames = [
+        Frame::new(2, 2, BitMap::from_slice(2, 2, &captures[0]).unwrap()).unwrap(),
+        Frame::new(2, 2, BitMap::from_slice(2, 2, &captures[1]).unwrap()).unwrap(),
+        Frame::new(2, 2, BitMap::from_slice(2, 2, &captures[2]).unwrap()).unwrap(),
+    ];
+
+    let mut buf = [0.0; 4];
+    let master = MasterFrame::median_combine(&frames, &mut buf).unwrap();
+    assert_eq!(master.dimensions(), (2, 2));
+    // the cosmic ray is rejected
+    assert_eq!(buf, [11.0, 20.0, 31.0, 40.0]);
+
+    let mut buf = [0.0; 3];
+    assert!(MasterFrame::median_combine(&frames, &mut buf).is_err());
+}
+
+#[test]
+fn flat_combine() {
+    let bias = [100.0; 4];
+    let bias = MasterFrame::new(2, 2, &bias).unwrap();
+
+    // vignetted flats
+    let captures: [[Gray16; 4]; 2] = [[1225, 975, 975, 1225], [1225, 975, 975, 1225]];
+    let frames = [
+        Frame::new(2, 2, BitMap::from_slice(2, 2, &captures[0]).unwrap()).unwrap(),
+        Frame::new(2, 2, BitMap::from_slice(2, 2, &captures[1]).unwrap()).unwrap(),
+    ];
+
+    let mut buf = [0.0; 4];
+    MasterFrame::median_combine_flat(&frames, Some(&bias), &mut buf).unwrap();
+    assert_eq!(buf, [1.125, 0.875, 0.875, 1.125]);
+}
+
+#[test]
+fn frame_correction() {
+    let bias = [100.0; 4];
+    let dark = [110.0, 110.0, 150.0, 110.0]; // one warm pixel
+    let flat = [1.125, 0.875, 0.875, 1.125];
+
+    let bias = MasterFrame::new(2, 2, &bias).unwrap();
+    let dark = MasterFrame::new(2, 2, &dark).unwrap();
+    let flat = MasterFrame::new(2, 2, &flat).unwrap();
+
+    // uniform sky of 800 ADU above the dark level
+    let raw: [Gray16; 4] = [1010, 810, 850, 1010];
+    let frame = Frame::new(2, 2, BitMap::from_slice(2, 2, &raw).unwrap()).unwrap();
+
+    let calibration = Calibration::default()
+        .with_bias(bias)
+        .with_dark(dark)
+        .with_flat(flat);
+
+    let mut buf = [0; 4];
+    let corrected = calibration.apply(&frame, &mut buf).unwrap();
+    assert!(corrected.bitmap().iter().all(|p| *p == 800));
+
+    // dark current scaling: twice longer darks
+    let calibration = Calibration::default()
+        .with_bias(bias)
+        .with_dark(dark)
+        .with_dark_scaling(1.0, 2.0)
+        .unwrap();
+    let mut buf = [0; 4];
+    let corrected = calibration.apply(&frame, &mut buf).unwrap();
+    assert_eq!(corrected.get(0, 1), Some(&725));
+
+    // dark scaling requires a bias
+    let calibration = Calibration::default()
+        .with_dark(dark)
+        .with_dark_scaling(1.0, 2.0)
+        .unwrap();
+    assert!(calibration.apply(&frame, &mut buf).is_err());
+
+    // exposure times must be positive
+    let calibration = Calibration::default().with_bias(bias).with_dark(dark);
+    for (frame_exposure, dark_exposure) in [(1.0, 0.0), (-1.0, 2.0), (1.0, f32::NAN)] {
+        assert_eq!(
+            calibration
+                .with_dark_scaling(frame_exposure, dark_exposure)
+                .err(),
+            Some(Error::ExposureError)
+        );
+    }
+
+    // dimensions must match
+    let small = [0.0; 1];
+    let small = MasterFrame::new(1, 1, &small).unwrap();
+    let calibration = Calibration::default().with_bias(small);
+    assert!(calibration.apply(&frame, &mut buf).is_err());
+}
+
+#[test]
+fn roi_correction() {
+    // 8x4 sensor: one warm pixel, one hot pixel and one star pixel
+    let mut raw: [Gray16; 32] = [1100; 32];
+    raw[8 + 5] = 1500;
+    raw[2 * 8 + 6] = 3000;
+    raw[3 * 8 + 7] = 2000;
+    let mut dark = [100.0; 32];
+    dark[8 + 5] = 1500.0;
+    let dark = MasterFrame::new(8, 4, &dark).unwrap();
+
+    let mut bits = [0; Mask::words(8, 4)];
+    let mut bad_pixels = BadPixelMap::new(8, 4, &mut bits).unwrap();
+    bad_pixels.flag(6, 2);
+
+    let threshold = Percentile::new(0.8);
+    let frame = Frame::new(8, 4, BitMap::from_slice(8, 4, &raw).unwrap())
+        .unwrap()
+        .with_bad_pixels(&bad_pixels)
+        .with_threshold(&threshold);
+    let roi = frame.roi(4, 0, 4, 4).unwrap();
+
+    // the master dark is looked up in sensor coordinates
+    let calibration = Calibration::default().with_dark(dark);
+    let mut buf = [0; 32];
+    let corrected = calibration.apply(&roi, &mut buf).unwrap();
+    assert_eq!(corrected.origin(), (4, 0));
+    assert_eq!(corrected.get(1, 1), Some(&0));
+    assert_eq!(corrected.get(0, 0), Some(&1000));
+
+    // the bad pixels and threshold are retained
+    let mut bits = [0; Mask::words(4, 4)];
+    let mut mask = Mask::new(4, 4, &mut bits).unwrap();
+    assert_eq!(corrected.detect(&mut mask), Ok(1));
+    assert!(mask.get(3, 3));
+
+    // the master frames must cover the region of interest
+    let small = [0.0; 12];
+    let small = MasterFrame::new(6, 2, &small).unwrap();
+    let calibration = Calibration::default().with_bias(small);
+    assert_eq!(
+        calibration.apply(&roi, &mut buf).err(),
+        Some(Error::VideoDimensionError)
+    );
+}
+
+#[test]
+fn color_correction() {
+    let bias = [20.0; 4];
+    let bias = MasterFrame::new(2, 2, &bias).unwrap();
+    let calibration = Calibration::default().with_bias(bias);
+
+    // the chrominance is kept
+    let raw = [Rgb8 {
+        r: 120,
+        g: 70,
+        b: 40,
+    }; 4];
+    let frame = Frame::new(2, 2, BitMap::from_slice(2, 2, &raw).unwrap()).unwrap();
+    let mut buf = [Rgb8::default(); 4];
+    let corrected = calibration.apply(&frame, &mut buf).unwrap();
+    let pixel = *corrected.get(1, 1).unwrap();
+    assert_eq!((pixel.r, pixel.g, pixel.b), (100, 50, 20));
+    assert_eq!(pixel.luma() + 20, raw[0].luma());
+
+    let raw = [YCbCr8 {
+        y: 90,
+        cb: 100,
+        cr: 160,
+    }; 4];
+    let frame = Frame::new(2, 2, BitMap::from_slice(2, 2, &raw).unwrap()).unwrap();
+    let mut buf = [YCbCr8::default(); 4];
+    let corrected = calibration.apply(&frame, &mut buf).unwrap();
+    let pixel = *corrected.get(0, 0).unwrap();
+    assert_eq!((pixel.y, pixel.cb, pixel.cr), (70, 100, 160));
+
+    let raw = [UnderlyingComponent::rgb8(120, 70, 40); 4];
+    let frame = Frame::new(2, 2, BitMap::from_slice(2, 2, &raw).unwrap()).unwrap();
+    let mut buf = [UnderlyingComponent::default(); 4];
+    let corrected = calibration.apply(&frame, &mut buf).unwrap();
+    let pixel = corrected
+        .get(0, 1)
+        .unwrap()
+        .to_gray8_with(&Conversion::default());
+    assert!(matches!(
+        corrected.get(0, 1),
+        Some(UnderlyingComponent::Rgb8(Rgb8 {
+            r: 100,
+            g: 50,
+            b: 20
+        }))
+    ));
+    assert_eq!(pixel as u16 + 20, raw[0].luma());
+}
+
+/// Uniform sky, with a warm spot of the sensor
+struct WarmSpot([UnderlyingComponent; 256]);
+
+impl VideoSource<256> for WarmSpot {
+    fn next(&mut self) -> Option<Frame<'_, 256>> {
+        Frame::new(16, 16, BitMap::from_slice(16, 16, &self.0).ok()?).ok()
+    }
+}
+
+#[test]
+fn solver_calibration() {
+    let mut dark = [0.0; 256];
+    let mut raw = [UnderlyingComponent::gray8(50); 256];
+    for i in [119, 120, 135, 136] {
+        dark[i] = 150.0;
+        raw[i] = UnderlyingComponent::gray8(200);
+    }
+    let dark = MasterFrame::new(16, 16, &dark).unwrap();
+
+    // the warm spot looks like a star
+    let mut bits = [0; Mask::words(16, 16)];
+    let mut solver = Solver::new(WarmSpot(raw)).with_mask_storage(&mut bits);
+    solver.video_processing::<1>(Rotation3::identity());
+    assert_eq!(solver.stars().len(), 1);
+
+    let mut bits = [0; Mask::words(16, 16)];
+    let mut buf = [UnderlyingComponent::default(); 256];
+    let mut solver = Solver::new(WarmSpot(raw))
+        .with_mask_storage(&mut bits)
+        .with_calibration(Calibration::default().with_dark(dark), &mut buf);
+    solver.video_processing::<1>(Rotation3::identity());
+    assert!(solver.stars().is_empty());
+}
@@ -0,0 +1,90 @@
+use celestial_nav::{
+    frame::{mask::Mask, threshold::SigmaK},
+    prelude::{BitMap, Frame, Gray16},
+};
+
+use crate::common::{noise, render, BACKGROUND};
+
+const WIDTH: u16 = 40;
+const HEIGHT: u16 = 24;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Adds an elliptical Gaussian star, of given amplitude,
+/// (sigma_x, sigma_y) and clipped to saturation, to the sky
+fn add_star(sky: &mut [f64; XY], (x0, y0): (f64, f64), amplitude: f64, (sx, sy): (f64, f64)) {
+    for y in 0..HEIGHT as usize {
+        for x in 0..WIDTH as usize {
+            let (dx, dy) = (x as f64 - x0, y as f64 - y0);
+            sky[y * WIDTH as usize + x] +=
+                amplitude * (-dx * dx / (2.0 * sx * sx) - dy * dy / (2.0 * sy * sy)).exp();
+        }
+    }
+}
+
+/// Builds a sky with a faint round star, and a bright elongated star
+fn sky(saturation: f64) -> [Gray16; XY] {
+    let mut sky = [BACKGROUND; XY];
+    add_star(&mut sky, (10.2, 11.6), 1500.0, (1.5, 1.5));
+    add_star(&mut sky, (28.4, 12.1), 4000.0, (2.5, 1.2));
+    render(WIDTH, HEIGHT, |i, _, _| (sky[i] + noise(i)).min(saturation))
+}
+
+#[test]
+fn star_candidates() {
+    let sky = sky(f64::MAX);
+    let threshold = SigmaK::new(2.0);
+    let frame = Frame::new(40, 24, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold);
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let candidates = frame.star_candidates::<4>(&mut bits).unwrap();
+    assert_eq!(candidates.len(), 2);
+    assert_eq!(candidates.overflow(), 0);
+
+    let (bright, faint) = (candidates.as_slice()[0], candidates.as_slice()[1]);
+
+    assert!((bright.centroid.coord.x - 28.4).abs() < 0.1);
+    assert!((faint.centroid.coord.y - 11.6).abs() < 0.1);
+
+    assert!(bright.flux > faint.flux);
+    assert!(bright.peak > faint.peak);
+    assert!(bright.area > faint.area);
+    assert!(bright.snr > faint.snr && faint.snr > 10.0);
+    assert!(faint.background > 100.0 && faint.background < 110.0);
+
+    // round star: FWHM = 2.3548 sigma
+    assert!((faint.fwhm - 2.3548 * 1.5).abs() < 0.5, "{:?}", faint);
+    assert!(faint.ellipticity < 0.1);
+
+    // elongated along x
+    assert!(bright.ellipticity > 0.3, "{:?}", bright);
+    assert!(bright.orientation.abs() < 0.1);
+
+    assert!(!bright.saturated && !faint.saturated);
+}
+
+#[test]
+fn saturation() {
+    let sky = sky(2000.0);
+    let threshold = SigmaK::new(2.0);
+    let frame = Frame::new(40, 24, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold);
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+
+    // not saturated at full scale
+    let candidates = frame.star_candidates::<4>(&mut bits).unwrap();
+    assert!(candidates.iter().all(|c| !c.saturated));
+
+    // sensor clipping
+    let candidates = frame
+        .with_saturation_level(2000)
+        .star_candidates::<4>(&mut bits)
+        .unwrap();
+    assert_eq!(candidates.len(), 2);
+    assert!(candidates.as_slice()[0].saturated);
+    assert_eq!(candidates.as_slice()[0].peak, 2000);
+    assert!(!candidates.as_slice()[1].saturated);
+}
@@ -0,0 +1,105 @@
+use celestial_nav::{
+    frame::{
+        blob::{Blobs, Connectivity},
+        centroid::Centroiding,
+        mask::Mask,
+    },
+    prelude::{BitMap, Frame, Gray16},
+    Error,
+};
+
+use crate::common::{noise, render, star, BACKGROUND};
+
+const WIDTH: u16 = 24;
+const HEIGHT: u16 = 20;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+const X0: f64 = 12.3;
+const Y0: f64 = 9.7;
+
+/// Builds a sky with one Gaussian star at (X0, Y0),
+/// over a noisy background
+fn sky() -> [Gray16; XY] {
+    render(WIDTH, HEIGHT, |i, x, y| {
+        BACKGROUND + noise(i) + star(x, y, (X0, Y0, 2000.0), 1.5)
+    })
+}
+
+#[test]
+fn centroiding_methods() {
+    let sky = sky();
+    let frame = Frame::new(24, 20, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let blobs = frame
+        .find_blobs::<4>(&mut bits, Connectivity::Eight)
+        .unwrap();
+    assert_eq!(blobs.len(), 1);
+    let blob = blobs.as_slice()[0];
+
+    let mut errors = [0.0; 4];
+
+    for (error, (method, tolerance)) in errors.iter_mut().zip([
+        (Centroiding::CenterOfMass, 0.3),
+        (Centroiding::WeightedCenterOfGravity, 0.05),
+        (
+            Centroiding::Iterative {
+                sigma: 1.5,
+                iterations: 20,
+            },
+            0.02,
+        ),
+        (Centroiding::Gaussian, 0.01),
+    ]) {
+        let centroid = frame.with_centroiding(method).centroid(&blob).unwrap();
+        *error = (centroid.coord.x - X0).hypot(centroid.coord.y - Y0);
+        assert!(*error < tolerance, "{:?}: {:?}", method, centroid);
+        assert!(centroid.uncertainty.x > 0.0 && centroid.uncertainty.x < 0.1);
+        assert!(centroid.uncertainty.y > 0.0 && centroid.uncertainty.y < 0.1);
+    }
+
+    // background subtraction removes the center of mass bias
+    assert!(errors[1] < errors[0]);
+
+    // the iterative window must be strictly positive
+    for sigma in [0.0, -1.5, f64::NAN, f64::INFINITY] {
+        let method = Centroiding::Iterative {
+            sigma,
+            iterations: 20,
+        };
+        assert_eq!(
+            frame.with_centroiding(method).centroid(&blob),
+            Err(Error::CentroidingError)
+        );
+    }
+
+    // default method
+    let centroids = frame.star_coordinates_finder::<2>(&mut bits).unwrap();
+    let centroid = centroids[0].unwrap();
+    assert!((centroid.coord.x - X0).abs() < 0.05);
+    assert!((centroid.coord.y - Y0).abs() < 0.05);
+    assert!(centroid.uncertainty.x > 0.0 && centroid.uncertainty.y > 0.0);
+    assert_eq!(centroids[1], None);
+}
+
+#[test]
+fn no_signal() {
+    let sky: [Gray16; XY] = [100; XY];
+    let frame = Frame::new(24, 20, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut mask = Mask::new(WIDTH, HEIGHT, &mut bits).unwrap();
+    mask.set(10, 10, true);
+
+    let blobs = Blobs::<1>::extract(&frame, &mask, Connectivity::Eight).unwrap();
+    let blob = blobs.as_slice()[0];
+
+    assert_eq!(
+        Centroiding::WeightedCenterOfGravity.estimate(&frame, &blob),
+        Err(Error::CentroidingError)
+    );
+
+    // the raw center of mass remains defined
+    let centroid = Centroiding::CenterOfMass.estimate(&frame, &blob).unwrap();
+    assert!((centroid.coord.x - 10.0).abs() < 1.0E-9);
+}
@@ -0,0 +1,57 @@
+//! Synthetic skies shared by the tests
+#![allow(dead_code)]
+
+use celestial_nav::prelude::Pixel;
+
+/// Sky background level
+pub const BACKGROUND: f64 = 100.0;
+
+/// Star PSF standard deviation (in pixels)
+pub const SIGMA: f64 = 1.2;
+
+/// Deterministic background noise of the i-th pixel (0..5)
+pub fn noise(i: usize) -> f64 {
+    ((i * 7) % 5) as f64
+}
+
+/// Linear congruential generator, for noisier backgrounds
+pub fn lcg(state: &mut u32) -> u32 {
+    *state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+    *state
+}
+
+/// Returns the (x, y) contribution of a Gaussian star of given
+/// (x0, y0, amplitude) and standard deviation (in pixels)
+pub fn star(x: f64, y: f64, (x0, y0, amplitude): (f64, f64, f64), sigma: f64) -> f64 {
+    let r2 = (x - x0).powi(2) + (y - y0).powi(2);
+    amplitude * (-r2 / (2.0 * sigma * sigma)).exp()
+}
+
+/// Renders a (width, height) sky, row after row: luma returns the value
+/// of the (i, x, y) pixel, rounded and saturated to the [Pixel] range.
+pub fn render<const XY: usize, P: Pixel>(
+    width: u16,
+    height: u16,
+    luma: impl Fn(usize, f64, f64) -> f64,
+) -> [P; XY] {
+    let mut sky = [P::default(); XY];
+    let size = width as usize * height as usize;
+    for (i, pixel) in sky.iter_mut().take(size).enumerate() {
+        let (x, y) = ((i % width as usize) as f64, (i / width as usize) as f64);
+        let value = luma(i, x, y).round().clamp(0.0, P::MAX_LUMA as f64);
+        *pixel = P::from_luma(value as u16);
+    }
+    sky
+}
+
+/// Builds a (width, height) noisy sky with given (x, y, amplitude)
+/// Gaussian stars, of [SIGMA] standard deviation
+pub fn sky<const XY: usize, P: Pixel>(
+    width: u16,
+    height: u16,
+    stars: &[(f64, f64, f64)],
+) -> [P; XY] {
+    render(width, height, |i, x, y| {
+        BACKGROUND + noise(i) + stars.iter().map(|s| star(x, y, *s, SIGMA)).sum::<f64>()
+    })
+}
@@ -0,0 +1,127 @@
+use celestial_nav::{
+    frame::filter::{Border, Filter},
+    prelude::{BitMap, Frame, Gray8},
+    Error,
+};
+
+use crate::common::{render, BACKGROUND};
+
+const WIDTH: u16 = 16;
+const HEIGHT: u16 = 12;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Builds a noisy flat sky with one hot pixel
+fn sky() -> [Gray8; XY] {
+    let mut sky = render(WIDTH, HEIGHT, |i, _, _| BACKGROUND + ((i * 7) % 9) as f64);
+    sky[5 * WIDTH as usize + 8] = 250;
+    sky
+}
+
+#[test]
+fn denoising() {
+    let sky = sky();
+    let frame = Frame::new(16, 12, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+    let noise = frame.luma_clipped_stats(3.0, 5).1;
+
+    let mut buf = [0; XY];
+
+    // the median removes the hot pixel
+    let median = frame
+        .filter::<3>(&Filter::Median, Border::default(), &mut buf)
+        .unwrap();
+    assert!(*median.get(8, 5).unwrap() < 110);
+
+    // linear filters spread it
+    let filtered = frame
+        .filter::<3>(&Filter::Box, Border::default(), &mut buf)
+        .unwrap();
+    let (hot, neighbor) = (*filtered.get(8, 5).unwrap(), *filtered.get(9, 6).unwrap());
+    assert!(hot > 110 && hot < 130);
+    assert_eq!(hot, neighbor);
+    assert!(filtered.luma_clipped_stats(3.0, 5).1 < noise);
+
+    let gaussian = Filter::Gaussian { sigma: 1.0 };
+    let filtered = frame
+        .filter::<5>(&gaussian, Border::default(), &mut buf)
+        .unwrap();
+    assert!(*filtered.get(8, 5).unwrap() > *filtered.get(9, 6).unwrap());
+    assert!(filtered.luma_clipped_stats(3.0, 5).1 < noise);
+
+    // matched filter, with a measured PSF
+    let psf = [1.0, 2.0, 1.0, 2.0, 4.0, 2.0, 1.0, 2.0, 1.0];
+    let matched = frame
+        .filter::<3>(&Filter::Matched(&psf), Border::default(), &mut buf)
+        .unwrap();
+    assert!(*matched.get(8, 5).unwrap() > 120);
+}
+
+#[test]
+fn borders() {
+    let mut sky = [0; XY];
+    for (i, pixel) in sky.iter_mut().enumerate() {
+        *pixel = 10 * (i % WIDTH as usize) as u8;
+    }
+    let frame = Frame::new(16, 12, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+
+    let mut buf = [0; XY];
+
+    // left column: (0 + 0 + 10) / 3 when replicated
+    let filtered = frame
+        .filter::<3>(&Filter::Box, Border::Replicate, &mut buf)
+        .unwrap();
+    assert_eq!(*filtered.get(0, 0).unwrap(), 3);
+    assert_eq!(*filtered.get(5, 5).unwrap(), 50);
+
+    // (10 + 0 + 10) / 3 when reflected
+    let filtered = frame
+        .filter::<3>(&Filter::Box, Border::Reflect, &mut buf)
+        .unwrap();
+    assert_eq!(*filtered.get(0, 0).unwrap(), 7);
+    assert_eq!(*filtered.get(15, 11).unwrap(), 143);
+
+    // copied
+    let filtered = frame
+        .filter::<3>(&Filter::Box, Border::Unfiltered, &mut buf)
+        .unwrap();
+    assert_eq!(*filtered.get(0, 0).unwrap(), 0);
+    assert_eq!(*filtered.get(15, 4).unwrap(), 150);
+}
+
+#[test]
+fn settings_and_kernels() {
+    let sky = sky();
+    let frame = Frame::new(16, 12, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+    let mut buf = [0; XY];
+
+    // region of interest
+    let roi = frame.roi(4, 2, 8, 6).unwrap();
+    let filtered = roi
+        .filter::<3>(&Filter::Median, Border::default(), &mut buf)
+        .unwrap();
+    assert_eq!(filtered.origin(), (4, 2));
+    assert_eq!((filtered.width(), filtered.height()), (8, 6));
+
+    // invalid kernels
+    assert_eq!(
+        frame
+            .filter::<4>(&Filter::Box, Border::default(), &mut buf)
+            .err(),
+        Some(Error::KernelError)
+    );
+    assert_eq!(
+        frame
+            .filter::<5>(&Filter::Matched(&[1.0; 9]), Border::default(), &mut buf)
+            .err(),
+        Some(Error::KernelError)
+    );
+    assert_eq!(
+        frame
+            .filter::<3>(
+                &Filter::Gaussian { sigma: 0.0 },
+                Border::default(),
+                &mut buf
+            )
+            .err(),
+        Some(Error::KernelError)
+    );
+}
@@ -0,0 +1,345 @@
+use celestial_nav::{
+    frame::{
+        component::{
+            bayer::BayerPattern,
+            color::{Conversion, Matrix, Range},
+            packed::PackedFormat,
+            UnderlyingComponent,
+        },
+        mask::Mask,
+        threshold::SigmaK,
+        YuvBitMap, YuvFormat,
+    },
+    prelude::{BitMap, Frame, Gray16, Gray8, Pixel, Rgb8, Rotation3, Solver, YCbCr8},
+    VideoSource,
+};
+
+use crate::common;
+
+/// Reference 12 bit pixels
+const PIXELS_12: [Gray16; 4] = [0xabc, 0x123, 0xfff, 0x001];
+
+/// Reference 10 bit pixels
+const PIXELS_10: [Gray16; 4] = [0x2bc, 0x123, 0x3ff, 0x001];
+
+#[test]
+fn mono12_unpacking() {
+    for (format, packed) in [
+        (PackedFormat::Mono12p, [0xbc, 0x3a, 0x12, 0xff, 0x1f, 0x00]),
+        (
+            PackedFormat::Mono12Packed,
+            [0xab, 0x3c, 0x12, 0xff, 0x1f, 0x00],
+        ),
+        (PackedFormat::Raw12, [0xab, 0x12, 0x3c, 0xff, 0x00, 0x1f]),
+    ] {
+        assert_eq!(format.bit_depth(), 12);
+        assert_eq!(format.packed_len(4), 6);
+        let mut dst = [0; 4];
+        format.unpack(&packed, &mut dst).unwrap();
+        assert_eq!(dst, PIXELS_12, "{:?}", format);
+        assert!(format.unpack(&packed[..5], &mut dst).is_err());
+    }
+}
+
+#[test]
+fn mono10_unpacking() {
+    // LSB first bit stream
+    let mut mono10p = [0_u8; 5];
+    let mut stream = 0_u64;
+    for (i, p) in PIXELS_10.iter().enumerate() {
+        stream |= (*p as u64) << (10 * i);
+    }
+    for (i, byte) in mono10p.iter_mut().enumerate() {
+        *byte = (stream >> (8 * i)) as u8;
+    }
+
+    for (format, packed) in [
+        (PackedFormat::Mono10p, mono10p.to_vec()),
+        (
+            PackedFormat::Mono10Packed,
+            vec![0xaf, 0x30, 0x48, 0xff, 0x13, 0x00],
+        ),
+        (PackedFormat::Raw10, vec![0xaf, 0x48, 0xff, 0x00, 0x7c]),
+    ] {
+        assert_eq!(format.bit_depth(), 10);
+        assert_eq!(format.max_luma(), 1023);
+        let mut dst = [0; 4];
+        format.unpack(&packed, &mut dst).unwrap();
+        assert_eq!(dst, PIXELS_10, "{:?}", format);
+    }
+}
+
+#[test]
+fn mono12_full_scale() {
+    // 8x6 Mono12 frame with a full scale 2x2 star
+    let (width, height) = (8, 6);
+    let mut pixels = [0x100; 48];
+    for (x, y) in [(3, 2), (4, 2), (3, 3), (4, 3)] {
+        pixels[y * width + x] = 0xfff;
+    }
+    let mut packed = [0_u8; 72];
+    for (bytes, p) in packed.chunks_mut(3).zip(pixels.chunks(2)) {
+        bytes[0] = p[0] as u8;
+        bytes[1] = (p[0] >> 8) as u8 | (p[1] << 4) as u8;
+        bytes[2] = (p[1] >> 4) as u8;
+    }
+
+    let mut buf = [0; 48];
+    let bitmap = BitMap::from_packed(8, 6, PackedFormat::Mono12p, &packed, &mut buf).unwrap();
+    assert_eq!(bitmap.bit_depth(), 12);
+    assert_eq!(bitmap.max_luma(), 0xfff);
+    assert_eq!(bitmap.get(3, 2), Some(&0xfff));
+    assert_eq!(bitmap.gray8(3, 2), Some(255));
+    assert_eq!(bitmap.gray8(0, 0), Some(16));
+    assert_eq!(bitmap.get(4, 3).unwrap().to_gray8_at(12), 255);
+
+    let threshold = SigmaK::new(2.0);
+    let frame = Frame::new(width, height, bitmap)
+        .unwrap()
+        .with_threshold(&threshold);
+    assert_eq!(frame.saturation_level(), 0xfff);
+
+    let mut bits = [0; Mask::words(8, 6)];
+    let candidates = frame.star_candidates::<4>(&mut bits).unwrap();
+    assert_eq!(candidates.len(), 1);
+    assert!(candidates.as_slice()[0].saturated);
+
+    // 16 bit interpretation never saturates
+    let frame = frame.with_bit_depth(16);
+    assert_eq!(frame.saturation_level(), u16::MAX);
+    let candidates = frame.star_candidates::<4>(&mut bits).unwrap();
+    assert!(!candidates.as_slice()[0].saturated);
+}
+
+/// Mono12 [VideoSource], published as is
+struct Mono12([Gray16; 384]);
+
+impl VideoSource<384, Gray16> for Mono12 {
+    fn next(&mut self) -> Option<Frame<'_, 384, Gray16>> {
+        let bitmap = BitMap::from_slice(24, 16, &self.0).ok()?.with_bit_depth(12);
+        Frame::new(24, 16, bitmap).ok()
+    }
+}
+
+#[test]
+fn solver_mono12() {
+    // both stars exceed the 8 bit range
+    let sky = common::sky::<384, Gray16>(24, 16, &[(7.3, 8.2, 2000.0), (16.6, 7.4, 2500.0)]);
+    let mut bits = [0; Mask::words(24, 16)];
+    let mut solver = Solver::new(Mono12(sky)).with_mask_storage(&mut bits);
+    solver.video_processing::<1>(Rotation3::identity());
+    assert_eq!(solver.error(), None);
+
+    let stars = solver.stars();
+    assert_eq!(stars.len(), 2);
+    for (star, (x, y)) in stars.iter().zip([(16.6, 7.4), (7.3, 8.2)]) {
+        assert!(!star.saturated);
+        let coord = star.centroid.coord;
+        assert!((coord.x - x).abs() < 0.2 && (coord.y - y).abs() < 0.2);
+    }
+}
+
+#[test]
+fn packed_bitmap() {
+    // 2x2 Mono12 frame with one bright pixel
+    let packed = [0x10, 0x01, 0x01, 0xff, 0x0f, 0x01];
+    let mut buf = [0; 4];
+    let bitmap = BitMap::from_packed(2, 2, PackedFormat::Mono12p, &packed, &mut buf).unwrap();
+    assert_eq!(bitmap.get(0, 1), Some(&0xfff));
+
+    let frame = Frame::new(2, 2, bitmap).unwrap();
+    let mean = frame.luma_mean();
+    assert_eq!(mean, (0x110 + 0x010 + 0xfff + 0x010) as f64 / 4.0);
+    assert!(frame.luma_std_dev(mean) > 1000.0);
+
+    let mut buf = [0; 3];
+    assert!(BitMap::from_packed(2, 2, PackedFormat::Mono12p, &packed, &mut buf).is_err());
+}
+
+/// Builds a 4x4 mosaic of a uniform (r, g, b) scene
+fn uniform_mosaic(pattern: BayerPattern, r: u8, g: u8, b: u8) -> [Gray8; 16] {
+    // position of the red photosite in the 2x2 cell
+    let (red_x, red_y) = match pattern {
+        BayerPattern::Rggb => (0, 0),
+        BayerPattern::Bggr => (1, 1),
+        BayerPattern::Grbg => (1, 0),
+        BayerPattern::Gbrg => (0, 1),
+    };
+    let mut mosaic = [0; 16];
+    for (i, p) in mosaic.iter_mut().enumerate() {
+        let (x, y) = (i % 4, i / 4);
+        *p = if x % 2 == red_x && y % 2 == red_y {
+            r
+        } else if x % 2 != red_x && y % 2 != red_y {
+            b
+        } else {
+            g
+        };
+    }
+    mosaic
+}
+
+#[test]
+fn bayer_demosaicing() {
+    for pattern in [
+        BayerPattern::Rggb,
+        BayerPattern::Bggr,
+        BayerPattern::Grbg,
+        BayerPattern::Gbrg,
+    ] {
+        let raw = uniform_mosaic(pattern, 200, 100, 50);
+        let mosaic = BitMap::from_slice(4, 4, &raw).unwrap();
+
+        let mut buf = [Rgb8::default(); 16];
+        let rgb = BitMap::from_bayer_bilinear(&mosaic, pattern, &mut buf).unwrap();
+        assert_eq!(rgb.width(), 4);
+        for p in rgb.iter() {
+            assert_eq!((p.r, p.g, p.b), (200, 100, 50), "{:?}", pattern);
+        }
+
+        let mut buf = [0; 16];
+        let binned = BitMap::from_bayer_binning(&mosaic, pattern, &mut buf).unwrap();
+        assert_eq!((binned.width(), binned.height()), (2, 2));
+        let expected = (0.299_f64 * 200.0 + 0.587 * 100.0 + 0.114 * 50.0).round() as u8;
+        assert!(binned.iter().all(|p| *p == expected), "{:?}", pattern);
+
+        // binned frame feeds the detection
+        let frame = Frame::new(2, 2, binned).unwrap();
+        assert_eq!(frame.luma_mean(), expected as f64);
+    }
+}
+
+#[test]
+fn bayer_native_depth() {
+    let raw: [Gray16; 4] = [4000, 2000, 2000, 1000];
+    let mosaic = BitMap::from_slice(2, 2, &raw).unwrap();
+    let mut buf = [0; 4];
+    let binned = BitMap::from_bayer_binning(&mosaic, BayerPattern::Rggb, &mut buf).unwrap();
+    assert_eq!(binned.get(0, 0), Some(&2484));
+}
+
+/// 4x2 luma plane used by the YUV tests
+const LUMA: [u8; 8] = [10, 20, 30, 40, 50, 60, 70, 80];
+
+#[test]
+fn nv12_and_i420() {
+    // 4x2 image: two chroma samples per plane
+    let mut nv12 = LUMA.to_vec();
+    nv12.extend_from_slice(&[100, 200, 110, 210]);
+    let mut i420 = LUMA.to_vec();
+    i420.extend_from_slice(&[100, 110, 200, 210]);
+
+    for (format, buf) in [(YuvFormat::Nv12, nv12), (YuvFormat::I420, i420)] {
+        assert_eq!(format.buffer_len(4, 2), 12);
+
+        let yuv = YuvBitMap::<8>::new(format, 4, 2, &buf).unwrap();
+        let luma = yuv.luma();
+        assert_eq!(luma.iter().copied().collect::<Vec<_>>(), LUMA);
+
+        let p = yuv.get(1, 1).unwrap();
+        assert_eq!((p.y, p.cb, p.cr), (60, 100, 200), "{:?}", format);
+        let p = yuv.get(3, 0).unwrap();
+        assert_eq!((p.y, p.cb, p.cr), (40, 110, 210), "{:?}", format);
+        assert!(yuv.get(4, 0).is_none());
+
+        assert!(YuvBitMap::<8>::new(format, 4, 2, &buf[..11]).is_err());
+        assert!(YuvBitMap::<7>::new(format, 4, 2, &buf).is_err());
+    }
+}
+
+#[test]
+fn yuyv() {
+    let mut buf = Vec::new();
+    for pair in LUMA.chunks(2) {
+        buf.extend_from_slice(&[pair[0], 100 + pair[0], pair[1], 150 + pair[0]]);
+    }
+
+    let yuv = YuvBitMap::<8>::from_yuyv(4, 2, &buf).unwrap();
+    let luma = yuv.luma();
+    assert_eq!(luma.iter().copied().collect::<Vec<_>>(), LUMA);
+
+    // luma region of interest, read straight from the packed buffer
+    let roi = luma.roi(1, 1, 2, 1).unwrap();
+    assert_eq!(roi.iter().copied().collect::<Vec<_>>(), [60, 70]);
+
+    let p = yuv.get(3, 1).unwrap();
+    assert_eq!((p.y, p.cb, p.cr), (80, 170, 220));
+
+    let frame = Frame::new(4, 2, luma).unwrap();
+    assert_eq!(frame.luma_mean(), 45.0);
+
+    assert!(YuvBitMap::<9>::from_yuyv(3, 3, &buf).is_err());
+}
+
+#[test]
+fn ycbcr8_whole_range() {
+    // chroma below 128 used to overflow
+    for y in (0..=255).step_by(5) {
+        for cb in (0..=255).step_by(5) {
+            for cr in (0..=255).step_by(5) {
+                let _ = YCbCr8 { y, cb, cr }.to_rgb8();
+            }
+        }
+    }
+    let rgb = YCbCr8 {
+        y: 128,
+        cb: 0,
+        cr: 0,
+    }
+    .to_rgb8();
+    assert_eq!((rgb.r, rgb.g, rgb.b), (0, 255, 0));
+}
+
+#[test]
+fn color_conversions() {
+    for matrix in [Matrix::Bt601, Matrix::Bt709, Matrix::Bt2020] {
+        let (kr, kg, kb) = matrix.luma_coefficients();
+        assert!((kr + kg + kb - 1.0).abs() < 1e-6);
+
+        for range in [Range::Full, Range::Limited] {
+            let conversion = Conversion::new(matrix, range);
+
+            // round trips
+            for (r, g, b) in [(0, 0, 0), (255, 255, 255), (200, 30, 90), (12, 250, 128)] {
+                let ycbcr8 = conversion.rgb8_to_ycbcr8(Rgb8 { r, g, b });
+                let rgb8 = conversion.ycbcr8_to_rgb8(ycbcr8);
+                for (a, b) in [(rgb8.r, r), (rgb8.g, g), (rgb8.b, b)] {
+                    assert!(
+                        a.abs_diff(b) <= 2,
+                        "{:?} {:?}: {} != {}",
+                        matrix,
+                        range,
+                        a,
+                        b
+                    );
+                }
+            }
+
+            // white and black references
+            let white = conversion.rgb8_to_ycbcr8(Rgb8 {
+                r: 255,
+                g: 255,
+                b: 255,
+            });
+            let black = conversion.gray8_to_ycbcr8(0);
+            match range {
+                Range::Full => assert_eq!((white.y, black.y), (255, 0)),
+                Range::Limited => assert_eq!((white.y, black.y), (235, 16)),
+            }
+            assert_eq!((white.cb, white.cr), (128, 128));
+            assert_eq!(conversion.ycbcr8_to_gray8(white), 255);
+            assert_eq!(conversion.ycbcr8_to_gray8(black), 0);
+        }
+    }
+
+    // luma weights
+    let green = Rgb8 { r: 0, g: 255, b: 0 };
+    let bt601 = Conversion::new(Matrix::Bt601, Range::Full);
+    let bt709 = Conversion::new(Matrix::Bt709, Range::Full);
+    assert_eq!(bt601.rgb8_to_gray8(green), 150);
+    assert_eq!(bt709.rgb8_to_gray8(green), 182);
+
+    let pixel = UnderlyingComponent::Rgb8(green);
+    assert_eq!(pixel.to_gray8(), 150);
+    assert_eq!(pixel.to_gray8_with(&bt709), 182);
+}
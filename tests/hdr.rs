@@ -0,0 +1,106 @@
+use celestial_nav::{
+    frame::{
+        centroid::Centroiding,
+        hdr::{Bracket, Exposure},
+        mask::Mask,
+        threshold::AdaptiveLocal,
+    },
+    prelude::{BitMap, Frame, Gray16, Gray8},
+    Error,
+};
+
+use crate::common::{render, star};
+
+const WIDTH: u16 = 48;
+const HEIGHT: u16 = 32;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Bright planet (x, y, peak radiance)
+const PLANET: (f64, f64, f64) = (12.3, 10.6, 200.0);
+
+/// Faint star (x, y, peak radiance)
+const STAR: (f64, f64, f64) = (34.4, 20.2, 3.0);
+
+const BLACK_LEVEL: u16 = 10;
+
+/// Captures the twilight sky with given sensitivity
+fn sky(sensitivity: f64) -> [Gray8; XY] {
+    render(WIDTH, HEIGHT, |i, x, y| {
+        let mut radiance = 1.0 + ((i * 7) % 3) as f64 * 0.05;
+        for planet in [PLANET, STAR] {
+            radiance += star(x, y, planet, 1.5);
+        }
+        BLACK_LEVEL as f64 + radiance * sensitivity
+    })
+}
+
+#[test]
+fn bracket() {
+    let (short, long) = (sky(1.0), sky(16.0));
+    let threshold = AdaptiveLocal::<8>::new(16, 5.0);
+
+    let short = Frame::new(48, 32, BitMap::from_slice(WIDTH, HEIGHT, &short).unwrap())
+        .unwrap()
+        .with_threshold(&threshold)
+        .with_centroiding(Centroiding::Gaussian)
+        .with_exposure(Exposure::new(0.01, 1.0).with_black_level(BLACK_LEVEL));
+    let long = Frame::new(48, 32, BitMap::from_slice(WIDTH, HEIGHT, &long).unwrap())
+        .unwrap()
+        .with_threshold(&threshold)
+        .with_centroiding(Centroiding::Gaussian)
+        .with_exposure(Exposure::new(0.04, 4.0).with_black_level(BLACK_LEVEL));
+
+    let unknown = Frame::new(48, 32, BitMap::from_slice(WIDTH, HEIGHT, &[0; XY]).unwrap()).unwrap();
+    assert_eq!(
+        Bracket::new(short, unknown).err(),
+        Some(Error::ExposureError)
+    );
+
+    // exposures are sorted
+    let bracket = Bracket::new(long, short).unwrap();
+    assert_eq!(bracket.ratio(), 16.0);
+    assert_eq!(bracket.short().exposure(), short.exposure());
+
+    // the planet saturates the long exposure
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let candidates = long.star_candidates::<4>(&mut bits).unwrap();
+    assert_eq!(candidates.len(), 2);
+    assert!(candidates.as_slice()[0].saturated);
+
+    // and is measured in the short one
+    let candidates = bracket.star_candidates::<4>(&mut bits).unwrap();
+    assert_eq!(candidates.len(), 2);
+    let (planet, star) = (candidates.as_slice()[0], candidates.as_slice()[1]);
+    assert!(!planet.saturated);
+    assert!((planet.centroid.coord.x - PLANET.0).abs() < 0.05);
+    assert!((planet.centroid.coord.y - PLANET.1).abs() < 0.05);
+    assert!((star.centroid.coord.x - STAR.0).abs() < 0.1);
+    assert!((star.centroid.coord.y - STAR.1).abs() < 0.1);
+
+    // fluxes in long exposure units
+    let expected = 2.0 * core::f64::consts::PI * 1.5 * 1.5 * 16.0;
+    assert!((planet.flux / (PLANET.2 * expected) - 1.0).abs() < 0.1);
+    assert!((star.flux / (STAR.2 * expected) - 1.0).abs() < 0.2);
+
+    // high dynamic range frame
+    let mut buf: [Gray16; XY] = [0; XY];
+    let merged = bracket.merge(&mut buf).unwrap();
+    assert_eq!(merged.saturation_level(), 10 + 245 * 16);
+    assert_eq!(merged.exposure(), bracket.long().exposure());
+    assert_eq!(merged.origin(), bracket.long().origin());
+    assert_eq!(
+        merged.get(34, 20),
+        long.get(34, 20).map(|p| *p as u16).as_ref()
+    );
+    let planet = *merged.get(12, 11).unwrap() as f64;
+    let radiance = 1.0 + PLANET.2 * (-(0.09_f64 + 0.16) / (2.0 * 1.5 * 1.5)).exp();
+    assert!((planet - (10.0 + 16.0 * radiance)).abs() < 16.0);
+
+    let candidates = merged
+        .with_threshold(&threshold)
+        .star_candidates::<4>(&mut bits)
+        .unwrap();
+    assert_eq!(candidates.len(), 2);
+    let coord = candidates.as_slice()[0].centroid.coord;
+    assert!((coord.x - PLANET.0).abs() < 0.05 && (coord.y - PLANET.1).abs() < 0.05);
+}
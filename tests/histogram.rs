@@ -0,0 +1,95 @@
+use celestial_nav::{
+    frame::histogram::Histogram,
+    prelude::{BitMap, Frame, Gray16, Gray8},
+};
+
+#[test]
+fn histogram() {
+    let mut sky = [0; 100];
+    for (i, p) in sky.iter_mut().enumerate() {
+        *p = i as Gray8;
+    }
+    let frame = Frame::new(10, 10, BitMap::from_slice(10, 10, &sky).unwrap()).unwrap();
+
+    let histogram = Histogram::<256>::from_frame(&frame);
+    assert_eq!(histogram.bins(), 256);
+    assert_eq!(histogram.bin_width(), 1);
+    assert_eq!(histogram.total(), 100);
+    assert_eq!(histogram.count(42), 1);
+    assert_eq!(histogram.count(142), 0);
+    assert_eq!(histogram.median(), 49);
+    assert_eq!(histogram.percentile(0.0), 0);
+    assert_eq!(histogram.percentile(0.9), 89);
+    assert_eq!(histogram.percentile(1.0), 99);
+
+    // coarse bins
+    let histogram = Histogram::<16>::from_frame(&frame);
+    assert_eq!(histogram.bin_width(), 16);
+    assert_eq!(histogram.count(0), 16);
+    assert_eq!(histogram.count(6), 4);
+    assert_eq!(histogram.median(), 48);
+}
+
+#[test]
+fn high_bit_depth_histogram() {
+    let mut histogram = Histogram::<1024>::new(4095);
+    assert_eq!(histogram.bin_width(), 4);
+    histogram.add(4095);
+    histogram.add(4);
+    histogram.add(u16::MAX);
+    assert_eq!(histogram.total(), 3);
+    assert_eq!(histogram.count(1023), 2);
+    assert_eq!(histogram.count(1), 1);
+
+    let sky: [Gray16; 4] = [100, 100, 4000, 4000];
+    let frame = Frame::new(2, 2, BitMap::from_slice(2, 2, &sky).unwrap()).unwrap();
+    let histogram = Histogram::<4096>::from_frame(&frame);
+    assert_eq!(histogram.bin_width(), 16);
+    assert_eq!(histogram.bin_luma(histogram.bin(4000)), 4000);
+}
+
+#[test]
+fn otsu() {
+    // bimodal histogram
+    let mut histogram = Histogram::<256>::new(255);
+    for luma in 20..40 {
+        for _ in 0..10 {
+            histogram.add(luma);
+        }
+    }
+    for luma in 180..200 {
+        for _ in 0..5 {
+            histogram.add(luma);
+        }
+    }
+    let threshold = histogram.otsu();
+    assert!(threshold > 39 && threshold <= 180, "{}", threshold);
+}
+
+#[test]
+fn triangle() {
+    // dominant background peak with a faint bright tail
+    let mut histogram = Histogram::<256>::new(255);
+    for (luma, count) in [
+        (8, 50),
+        (9, 200),
+        (10, 1000),
+        (11, 400),
+        (12, 100),
+        (13, 20),
+    ] {
+        for _ in 0..count {
+            histogram.add(luma);
+        }
+    }
+    for luma in 14..200 {
+        histogram.add(luma);
+    }
+    let threshold = histogram.triangle();
+    assert!(threshold > 10 && threshold < 20, "{}", threshold);
+
+    // degenerated histogram
+    let mut histogram = Histogram::<256>::new(255);
+    histogram.add(10);
+    assert_eq!(histogram.triangle(), 11);
+}
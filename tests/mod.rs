@@ -1 +1,21 @@
+mod common;
 mod stars;
+mod formats;
+mod calibration;
+mod bad_pixels;
+mod background;
+mod histogram;
+mod threshold;
+mod blobs;
+mod centroid;
+mod candidate;
+mod selection;
+mod filter;
+mod morphology;
+mod streak;
+mod sky;
+mod motion;
+mod pyramid;
+mod quality;
+mod hdr;
+mod stack;
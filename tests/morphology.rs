@@ -0,0 +1,157 @@
+use celestial_nav::{
+    frame::{
+        blob::{Blobs, Connectivity},
+        mask::{Mask, Morphology},
+        threshold::SigmaK,
+    },
+    prelude::{BitMap, Frame, Gray16},
+};
+
+use crate::common;
+
+#[test]
+fn morphology() {
+    let mut bits = [0; Mask::words(12, 10)];
+    let mut scratch_bits = [0; Mask::words(12, 10)];
+    let mut mask = Mask::new(12, 10, &mut bits).unwrap();
+    let mut scratch = Mask::new(12, 10, &mut scratch_bits).unwrap();
+
+    // 3x3 star with a hole, a noise pixel, and a star on the edge
+    for y in 2..5 {
+        for x in 2..5 {
+            mask.set(x, y, true);
+        }
+    }
+    mask.set(3, 3, false);
+    mask.set(9, 8, true);
+    for y in 4..7 {
+        mask.set(10, y, true);
+        mask.set(11, y, true);
+    }
+
+    // closing fills the hole
+    mask.close(Connectivity::Eight, &mut scratch).unwrap();
+    assert!(mask.get(3, 3));
+    assert!(!mask.get(1, 1));
+
+    // opening removes the noise pixel, preserves the stars
+    mask.open(Connectivity::Eight, &mut scratch).unwrap();
+    assert!(!mask.get(9, 8));
+    assert_eq!(mask.count(), 9 + 6);
+
+    // erosion
+    mask.erode(Connectivity::Four, &mut scratch).unwrap();
+    assert!(mask.get(3, 3));
+    assert!(!mask.get(2, 3));
+    assert!(mask.get(11, 5));
+    assert_eq!(mask.count(), 1 + 1);
+
+    // dilation
+    mask.dilate(Connectivity::Four, &mut scratch).unwrap();
+    assert!(mask.get(2, 3) && mask.get(3, 2));
+    assert!(!mask.get(2, 2));
+    assert_eq!(mask.count(), 5 + 4);
+
+    // dimensions mismatch
+    let mut bits = [0; Mask::words(8, 8)];
+    let mut scratch = Mask::new(8, 8, &mut bits).unwrap();
+    assert!(mask.erode(Connectivity::Eight, &mut scratch).is_err());
+}
+
+const WIDTH: u16 = 32;
+const HEIGHT: u16 = 24;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Builds a sky with a double star, and a single star
+fn sky() -> [Gray16; XY] {
+    common::sky(
+        WIDTH,
+        HEIGHT,
+        &[
+            (10.3, 11.2, 3000.0),
+            (14.6, 12.7, 1500.0),
+            (25.0, 6.0, 2000.0),
+        ],
+    )
+}
+
+#[test]
+fn deblending() {
+    let sky = sky();
+    let threshold = SigmaK::new(1.0);
+    let frame = Frame::new(32, 24, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold);
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+
+    // touching stars
+    let blobs = frame
+        .find_blobs::<8>(&mut bits, Connectivity::Eight)
+        .unwrap();
+    assert_eq!(blobs.len(), 2);
+
+    let mut blobs = frame
+        .with_deblending(0.05)
+        .find_blobs::<8>(&mut bits, Connectivity::Eight)
+        .unwrap();
+    assert_eq!(blobs.len(), 3);
+    blobs.sort_by_flux();
+
+    let expected = [(10.3, 11.2), (25.0, 6.0), (14.6, 12.7)];
+    for (blob, (x, y)) in blobs.iter().zip(expected) {
+        let (peak_x, peak_y, _) = blob.peak();
+        assert!((peak_x as f64 - x).abs() <= 1.0, "{:?}", blob);
+        assert!((peak_y as f64 - y).abs() <= 1.0, "{:?}", blob);
+    }
+
+    // all pixels are retained
+    let mut mask = Mask::new(WIDTH, HEIGHT, &mut bits).unwrap();
+    frame.with_threshold(&threshold).detect(&mut mask).unwrap();
+    let merged = Blobs::<8>::extract(&frame, &mask, Connectivity::Eight).unwrap();
+    let split = merged.deblend(&frame, &mask, Connectivity::Eight, 0.05);
+    let area = |blobs: &Blobs<8>| blobs.iter().map(|b| b.area()).sum::<u32>();
+    assert_eq!(area(&merged), area(&split));
+
+    // a high contrast keeps the double star
+    let blobs = merged.deblend(&frame, &mask, Connectivity::Eight, 0.9);
+    assert_eq!(blobs.len(), 2);
+}
+
+#[test]
+fn detection_morphology() {
+    let mut sky: [Gray16; XY] = common::sky(
+        WIDTH,
+        HEIGHT,
+        &[(10.0, 8.0, 20000.0), (22.0, 16.0, 20000.0)],
+    );
+    // hot pixels
+    for (x, y) in [(3, 3), (20, 4), (28, 20)] {
+        sky[y * WIDTH as usize + x] = 20000;
+    }
+    let threshold = SigmaK::new(3.0);
+    let frame = Frame::new(32, 24, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold);
+
+    let mut bits = [0; 2 * Mask::words(WIDTH, HEIGHT)];
+
+    let blobs = frame
+        .find_blobs::<8>(&mut bits, Connectivity::Eight)
+        .unwrap();
+    assert_eq!(blobs.len(), 5);
+
+    // opening removes the hot pixels, keeps the stars
+    let opened = frame.with_morphology(Morphology::Opening, Connectivity::Eight);
+    let blobs = opened
+        .find_blobs::<8>(&mut bits, Connectivity::Eight)
+        .unwrap();
+    assert_eq!(blobs.len(), 2);
+    assert!(blobs.iter().all(|blob| blob.area() > 1));
+
+    // the scratch mask is required
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    assert!(opened
+        .find_blobs::<8>(&mut bits, Connectivity::Eight)
+        .is_err());
+}
@@ -0,0 +1,176 @@
+use celestial_nav::{
+    frame::{
+        centroid::Centroiding,
+        component::UnderlyingComponent,
+        filter::{Border, Filter},
+        mask::Mask,
+        motion::Motion,
+    },
+    prelude::{BitMap, Frame, Gray16, Rotation3, Solver},
+    VideoSource,
+};
+
+use geo::Coord;
+use nalgebra::Vector3;
+
+use crate::common::{self, noise, render, star, BACKGROUND, SIGMA};
+
+const WIDTH: u16 = 64;
+const HEIGHT: u16 = 48;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Star position at mid-exposure
+const MID: Coord<f64> = Coord { x: 20.3, y: 20.6 };
+
+/// Pitching camera: 0.1 s exposure, 500 pixels focal length
+fn motion() -> Motion {
+    Motion::new(
+        Vector3::new(0.04, -0.12, 0.0),
+        0.1,
+        500.0,
+        Coord { x: 32.0, y: 24.0 },
+    )
+}
+
+/// Builds a sky with one star smeared by the camera [Motion]
+fn sky() -> [Gray16; XY] {
+    let blur = motion().blur(MID);
+    render(WIDTH, HEIGHT, |i, x, y| {
+        let mut value = BACKGROUND + noise(i);
+        for step in 0..64 {
+            let t = (step as f64 + 0.5) / 64.0 - 0.5;
+            let (x0, y0) = (MID.x + t * blur.x, MID.y + t * blur.y);
+            value += star(x, y, (x0, y0, 3000.0 / 64.0), SIGMA);
+        }
+        value
+    })
+}
+
+#[test]
+fn image_velocity() {
+    let center = Coord { x: 32.0, y: 24.0 };
+
+    // yaw: the image moves horizontally
+    let yaw = Motion::new(Vector3::new(0.0, 0.1, 0.0), 0.1, 500.0, center);
+    let velocity = yaw.velocity(center);
+    assert!((velocity.x + 50.0).abs() < 1.0E-9 && velocity.y.abs() < 1.0E-9);
+
+    // roll: the image rotates around the optical center
+    let roll = Motion::new(Vector3::new(0.0, 0.0, 0.1), 0.1, 500.0, center);
+    let blur = roll.blur(Coord { x: 42.0, y: 24.0 });
+    assert!(blur.x.abs() < 1.0E-9 && (blur.y + 0.1).abs() < 1.0E-9);
+    assert_eq!(roll.blur(center), Coord { x: 0.0, y: 0.0 });
+}
+
+#[test]
+fn smeared_centroiding() {
+    let sky = sky();
+    let blur = motion().blur(MID);
+    assert!(blur.x.hypot(blur.y) > 5.0);
+
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+    let blurred = frame.with_motion(motion());
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let blobs = frame
+        .find_blobs::<4>(&mut bits, Default::default())
+        .unwrap();
+    assert_eq!(blobs.len(), 1);
+    let blob = blobs.as_slice()[0];
+
+    for method in [
+        Centroiding::WeightedCenterOfGravity,
+        Centroiding::Iterative {
+            sigma: 1.2,
+            iterations: 20,
+        },
+        Centroiding::Gaussian,
+    ] {
+        let centroid = blurred.with_centroiding(method).centroid(&blob).unwrap();
+        let error = (centroid.coord.x - MID.x).hypot(centroid.coord.y - MID.y);
+        assert!(error < 0.1, "{:?}: {:?}", method, centroid);
+    }
+}
+
+#[test]
+fn blur_quality() {
+    let sky = sky();
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+
+    let candidates = frame.star_candidates::<4>(&mut bits).unwrap();
+    assert!(!candidates.as_slice()[0].smeared);
+    assert_eq!(candidates.as_slice()[0].blur, Coord { x: 0.0, y: 0.0 });
+
+    let candidates = frame
+        .with_motion(motion())
+        .star_candidates::<4>(&mut bits)
+        .unwrap();
+    let candidate = candidates.as_slice()[0];
+    assert!(!candidate.smeared);
+    assert!((candidate.blur.x - motion().blur(MID).x).abs() < 0.1);
+
+    let candidates = frame
+        .with_motion(motion().with_max_blur(3.0))
+        .star_candidates::<4>(&mut bits)
+        .unwrap();
+    assert!(candidates.as_slice()[0].smeared);
+
+    // detection: motion matched filter
+    let mut buf = [0; XY];
+    let filter = Filter::Motion {
+        blur: motion().blur(MID),
+    };
+    let filtered = frame
+        .filter::<9>(&filter, Border::default(), &mut buf)
+        .unwrap();
+    let candidates = filtered
+        .with_motion(motion())
+        .star_candidates::<4>(&mut bits)
+        .unwrap();
+    let coord = candidates.as_slice()[0].centroid.coord;
+    assert!((coord.x - MID.x).abs() < 0.2 && (coord.y - MID.y).abs() < 0.2);
+}
+
+/// [VideoSource] capturing a still sky, with two sharp stars
+struct Still([UnderlyingComponent; XY]);
+
+impl VideoSource<XY> for Still {
+    fn next(&mut self) -> Option<Frame<'_, XY>> {
+        Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &self.0).ok()?).ok()
+    }
+}
+
+#[test]
+fn solver_motion() {
+    let sky = common::sky(WIDTH, HEIGHT, &[(20.3, 20.6, 120.0), (44.0, 30.0, 100.0)]);
+    let still = motion().with_max_blur(4.0);
+    let yaw = |rate| Vector3::new(0.0, rate, 0.0);
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut solver = Solver::new_fixed_body_camera(Still(sky), Rotation3::identity())
+        .with_mask_storage(&mut bits)
+        .with_motion(Motion {
+            rate: yaw(0.0),
+            ..still
+        });
+    solver.video_processing::<1>(Rotation3::identity());
+    assert_eq!(solver.stars().len(), 2);
+    assert!(solver.stars().iter().all(|star| !star.smeared));
+
+    // the IMU reports a 5 pixels blur: the stars are not trusted
+    solver.set_angular_rate(yaw(0.1));
+    solver.video_processing::<1>(Rotation3::identity());
+    assert_eq!(solver.error(), None);
+    assert!(solver.stars().is_empty());
+    assert_eq!(solver.stars().dropped(), 2);
+
+    // absurd rates do not overflow the centroiding window
+    solver.set_angular_rate(yaw(1.0E12));
+    solver.video_processing::<1>(Rotation3::identity());
+    assert!(solver.stars().is_empty());
+
+    solver.set_angular_rate(yaw(0.0));
+    solver.video_processing::<1>(Rotation3::identity());
+    assert_eq!(solver.stars().len(), 2);
+}
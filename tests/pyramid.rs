@@ -0,0 +1,113 @@
+use celestial_nav::{
+    frame::{
+        mask::Mask,
+        pyramid::{Binning, Pyramid, MAX_LEVELS},
+        threshold::AdaptiveLocal,
+    },
+    prelude::{BitMap, Frame, Gray16},
+};
+
+use crate::common::{lcg, render, star, BACKGROUND};
+
+const WIDTH: u16 = 128;
+const HEIGHT: u16 = 96;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// (x, y, amplitude) of the stars, brightest first
+const STARS: [(f64, f64, f64); 4] = [
+    (20.3, 16.7, 3000.0),
+    (90.6, 30.2, 2000.0),
+    (40.1, 70.4, 1200.0),
+    (105.8, 80.5, 800.0),
+];
+
+/// Builds a noisy sky with defocused stars (2 pixels sigma)
+fn sky() -> [Gray16; XY] {
+    let mut state = 7_u32;
+    let noise = core::array::from_fn::<_, XY, _>(|_| (lcg(&mut state) >> 16) % 11);
+    render(WIDTH, HEIGHT, |i, x, y| {
+        let mut value = 2.0 * BACKGROUND + noise[i] as f64;
+        for s in STARS {
+            value += star(x, y, s, 2.0);
+        }
+        value
+    })
+}
+
+#[test]
+fn coarse_to_fine() {
+    let sky = sky();
+    let threshold = AdaptiveLocal::<64>::new(16, 5.0);
+    let frame = Frame::new(128, 96, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold);
+
+    let mut buf = [0; XY];
+    let pyramid = Pyramid::new(frame, Binning::X2, 3, &mut buf).unwrap();
+    assert_eq!(pyramid.len(), 3);
+    assert_eq!(pyramid.scale(2), 4);
+
+    let level = pyramid.level(2).unwrap();
+    assert_eq!((level.width(), level.height()), (32, 24));
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let candidates = frame.star_candidates::<8>(&mut bits).unwrap();
+    assert_eq!(candidates.len(), 4);
+
+    for level in 1..3 {
+        let candidates = pyramid.star_candidates::<8>(level, &mut bits).unwrap();
+        assert_eq!(candidates.len(), 4, "level {}", level);
+
+        for (candidate, (x, y, _)) in candidates.iter().zip(STARS) {
+            let coord = candidate.centroid.coord;
+            assert!(
+                (coord.x - x).abs() < 0.1 && (coord.y - y).abs() < 0.1,
+                "level {}: {:?}",
+                level,
+                coord
+            );
+        }
+    }
+
+    let stars = pyramid.select_stars::<2>(2, &mut bits).unwrap();
+    assert_eq!(stars.len(), 2);
+    assert!((stars.as_slice()[0].centroid.coord.x - STARS[0].0).abs() < 0.1);
+
+    assert!(pyramid.star_candidates::<8>(3, &mut bits).is_err());
+}
+
+#[test]
+fn refinement_neighbour() {
+    // faint star, whose refinement window reaches a bright neighbour
+    const FAINT: (f64, f64, f64) = (40.0, 40.0, 600.0);
+    const BRIGHT: (f64, f64, f64) = (49.0, 49.0, 20000.0);
+
+    let mut state = 7_u32;
+    let noise = core::array::from_fn::<_, XY, _>(|_| (lcg(&mut state) >> 16) % 11);
+    let sky: [Gray16; XY] = render(WIDTH, HEIGHT, |i, x, y| {
+        2.0 * BACKGROUND + noise[i] as f64 + star(x, y, FAINT, 1.5) + star(x, y, BRIGHT, 1.5)
+    });
+    let threshold = AdaptiveLocal::<64>::new(16, 5.0);
+    let frame = Frame::new(128, 96, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold);
+
+    let mut buf = [0; XY];
+    let pyramid = Pyramid::new(frame, Binning::X2, 2, &mut buf).unwrap();
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let candidates = pyramid.star_candidates::<8>(1, &mut bits).unwrap();
+    assert_eq!(candidates.len(), 2);
+
+    // the faint star is measured on its own pixels
+    let faint = candidates.as_slice()[1];
+    let coord = faint.centroid.coord;
+    assert!((coord.x - FAINT.0).abs() < 0.5 && (coord.y - FAINT.1).abs() < 0.5);
+    assert!(faint.peak < 1000, "{:?}", faint);
+    assert!(faint.area < 100, "{:?}", faint);
+
+    // levels are validated
+    let mut buf = [0; XY];
+    assert!(Pyramid::new(frame, Binning::X2, MAX_LEVELS + 1, &mut buf).is_err());
+    assert!(Pyramid::new(frame, Binning::X2, 0, &mut buf).is_err());
+}
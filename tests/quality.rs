@@ -0,0 +1,181 @@
+use celestial_nav::{
+    frame::{
+        background::{Background, Tile},
+        component::UnderlyingComponent,
+        mask::Mask,
+        pyramid::{Binning, MAX_LEVELS},
+        quality::QualityCriteria,
+    },
+    prelude::{BitMap, Frame, Gray16, Rotation3, Solver},
+    Error, VideoSource,
+};
+
+use crate::common::{lcg, render, star, BACKGROUND};
+
+const WIDTH: u16 = 64;
+const HEIGHT: u16 = 48;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Star positions
+const STARS: [(f64, f64); 6] = [
+    (10.2, 8.6),
+    (30.7, 12.1),
+    (52.4, 9.3),
+    (12.8, 36.5),
+    (33.1, 30.9),
+    (50.6, 38.2),
+];
+
+/// Builds a sky of given PSF sigma (constant star flux) and noise amplitude.
+/// Clouds cover the right half of the sky, hiding its stars.
+fn sky(sigma: f64, noise: u32, clouds: bool) -> [Gray16; XY] {
+    let mut state = 3_u32;
+    let randoms = core::array::from_fn::<_, XY, _>(|_| lcg(&mut state));
+    render(WIDTH, HEIGHT, |i, x, y| {
+        let random = randoms[i];
+        let mut value = BACKGROUND + ((random >> 16) % (2 * noise + 1)) as f64;
+        if clouds && x >= 32.0 {
+            value += 400.0 + ((random >> 8) % (8 * noise + 1)) as f64;
+        } else {
+            for (sx, sy) in STARS {
+                value += star(x, y, (sx, sy, 2000.0 * (1.2 / sigma).powi(2)), sigma);
+            }
+        }
+        value
+    })
+}
+
+#[test]
+fn frame_quality() {
+    let criteria = QualityCriteria::default()
+        .with_expected_stars(6)
+        .with_zero_point(20.0)
+        .with_max_fwhm(4.0)
+        .with_max_cloud_fraction(0.3);
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut tiles = [Tile::default(); Background::tiles(WIDTH, HEIGHT, 16)];
+
+    // clear sky
+    let clear = sky(1.2, 3, false);
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &clear).unwrap()).unwrap();
+    let background = Background::estimate(&frame, 16, &mut tiles).unwrap();
+    let quality = frame
+        .with_background(&background)
+        .quality(&criteria, &mut bits)
+        .unwrap();
+    assert_eq!(quality.stars, 6);
+    assert_eq!(quality.star_ratio(), 1.0);
+    assert!((quality.fwhm - 2.355 * 1.2).abs() < 0.3, "{:?}", quality);
+    assert!((quality.background - 103.0).abs() < 1.0);
+    assert!(quality.noise > 1.0 && quality.noise < 3.0);
+    assert_eq!(quality.cloud_fraction, 0.0);
+    assert!(criteria.accepts(&quality));
+
+    // without background: global statistics
+    let global = frame.quality(&criteria, &mut bits).unwrap();
+    assert!((global.background - 103.0).abs() < 1.0);
+    assert!((global.limiting_magnitude - quality.limiting_magnitude).abs() < 0.5);
+
+    // noisier sky: brighter limiting magnitude
+    let noisy = sky(1.2, 20, false);
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &noisy).unwrap()).unwrap();
+    let noisy = frame.quality(&criteria, &mut bits).unwrap();
+    assert!(noisy.limiting_magnitude < quality.limiting_magnitude - 1.0);
+    assert!(!criteria
+        .with_min_limiting_magnitude(quality.limiting_magnitude - 0.5)
+        .accepts(&noisy));
+
+    // out of focus
+    let blurred = sky(3.0, 3, false);
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &blurred).unwrap()).unwrap();
+    let background = Background::estimate(&frame, 16, &mut tiles).unwrap();
+    let blurred = frame
+        .with_background(&background)
+        .quality(&criteria, &mut bits)
+        .unwrap();
+    assert!(blurred.fwhm > 6.0, "{:?}", blurred);
+    assert!(!criteria.accepts(&blurred));
+
+    // clouded
+    let cloudy = sky(1.2, 3, true);
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &cloudy).unwrap()).unwrap();
+    let background = Background::estimate(&frame, 16, &mut tiles).unwrap();
+    let cloudy = frame
+        .with_background(&background)
+        .quality(&criteria, &mut bits)
+        .unwrap();
+    assert_eq!(cloudy.stars, 3);
+    assert_eq!(cloudy.star_ratio(), 0.5);
+    assert!(cloudy.cloud_fraction >= 0.5, "{:?}", cloudy);
+    assert!(!criteria.accepts(&cloudy));
+    assert!(QualityCriteria::default().accepts(&cloudy));
+}
+
+/// [VideoSource] capturing the same 8 bit sky of given PSF sigma (constant
+/// star amplitude), over and over
+struct Still([UnderlyingComponent; XY]);
+
+impl Still {
+    fn new(sigma: f64) -> Self {
+        let mut state = 5_u32;
+        let randoms = core::array::from_fn::<_, XY, _>(|_| lcg(&mut state));
+        Self(render(WIDTH, HEIGHT, |i, x, y| {
+            let mut value = 20.0 + ((randoms[i] >> 16) % 7) as f64;
+            for (sx, sy) in STARS {
+                value += star(x, y, (sx, sy, 150.0), sigma);
+            }
+            value
+        }))
+    }
+}
+
+impl VideoSource<XY> for Still {
+    fn next(&mut self) -> Option<Frame<'_, XY>> {
+        Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &self.0).ok()?).ok()
+    }
+}
+
+#[test]
+fn solver_coarse_quality() {
+    let criteria = QualityCriteria::default()
+        .with_expected_stars(6)
+        .with_max_fwhm(3.0)
+        .with_min_star_ratio(0.8);
+
+    // in focus: the quality is measured on the refined stars, at full resolution
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut buf = [UnderlyingComponent::default(); XY];
+    let mut solver = Solver::new_fixed_body_camera(Still::new(1.2), Rotation3::identity())
+        .with_mask_storage(&mut bits)
+        .with_coarse_detection(Binning::X2, 1, &mut buf)
+        .unwrap()
+        .with_quality_criteria(criteria);
+    solver.video_processing::<1>(Rotation3::identity());
+    let quality = solver.quality().unwrap();
+    assert_eq!(quality.stars, 6, "{:?}", quality);
+    assert!((quality.fwhm - 2.355 * 1.2).abs() < 0.3, "{:?}", quality);
+    assert_eq!(solver.stars().len(), 4);
+
+    // out of focus
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut buf = [UnderlyingComponent::default(); XY];
+    let mut solver = Solver::new_fixed_body_camera(Still::new(2.2), Rotation3::identity())
+        .with_mask_storage(&mut bits)
+        .with_coarse_detection(Binning::X2, 1, &mut buf)
+        .unwrap()
+        .with_quality_criteria(criteria);
+    solver.video_processing::<1>(Rotation3::identity());
+    let quality = solver.quality().unwrap();
+    assert_eq!(quality.stars, 6, "{:?}", quality);
+    assert!(quality.fwhm > criteria.max_fwhm, "{:?}", quality);
+    assert!(solver.stars().is_empty());
+
+    // the coarse level must be binned, and within the pyramid
+    for level in [0, MAX_LEVELS] {
+        let mut buf = [UnderlyingComponent::default(); XY];
+        let solver =
+            Solver::new(Still::new(1.2)).with_coarse_detection(Binning::X2, level, &mut buf);
+        assert_eq!(solver.err(), Some(Error::VideoDimensionError));
+    }
+}
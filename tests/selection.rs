@@ -0,0 +1,137 @@
+use celestial_nav::{
+    frame::{
+        candidate::StarCandidate,
+        mask::Mask,
+        selection::{Ranking, Selection},
+        threshold::SigmaK,
+    },
+    prelude::{BitMap, Frame, Gray16},
+};
+
+use core::cmp::Ordering;
+use geo::Coord;
+
+use crate::common;
+
+const WIDTH: u16 = 64;
+const HEIGHT: u16 = 48;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// (x, y, amplitude) of the stars: the brightest lies on the edge,
+/// the second has a close and fainter neighbour
+const STARS: [(f64, f64, f64); 6] = [
+    (1.0, 20.0, 8000.0),
+    (30.2, 24.4, 4000.0),
+    (36.4, 26.5, 3000.0),
+    (12.3, 10.8, 2500.0),
+    (50.5, 38.2, 2000.0),
+    (48.1, 8.7, 1500.0),
+];
+
+/// Builds a sky made of [STARS]
+fn sky() -> [Gray16; XY] {
+    common::sky(WIDTH, HEIGHT, &STARS)
+}
+
+#[test]
+fn star_selection() {
+    let sky = sky();
+    let threshold = SigmaK::new(3.0);
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold);
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+
+    // brightest first, no policy
+    let stars = frame.select_stars::<4>(&mut bits).unwrap();
+    assert_eq!(stars.len(), 4);
+    assert_eq!(stars.dropped(), 0);
+    assert!(stars.as_slice()[0].centroid.coord.x < 2.0);
+
+    // edge margin and minimal separation
+    let selection = Selection::new(Ranking::Flux, 8.0, 4.0);
+    let frame = frame.with_selection(selection);
+
+    let stars = frame.select_stars::<4>(&mut bits).unwrap();
+    assert_eq!(stars.len(), 4);
+    assert_eq!(stars.dropped(), 2);
+
+    let expected = [(30.2, 24.4), (12.3, 10.8), (50.5, 38.2), (48.1, 8.7)];
+    for (star, (x, y)) in stars.iter().zip(expected) {
+        assert!((star.centroid.coord.x - x).abs() < 0.2, "{:?}", star);
+        assert!((star.centroid.coord.y - y).abs() < 0.2, "{:?}", star);
+    }
+
+    // rejections ranked below the selected stars are reported too
+    let stars = frame.select_stars::<1>(&mut bits).unwrap();
+    assert_eq!(stars.len(), 1);
+    assert_eq!(stars.dropped(), 2);
+
+    let centroids = frame.star_coordinates_finder::<6>(&mut bits).unwrap();
+    assert_eq!(centroids[0], Some(stars.as_slice()[0].centroid));
+    assert!(centroids[3].is_some());
+    assert_eq!(centroids[4], None);
+    assert_eq!(centroids[5], None);
+
+    // angular separation: 4 pixels at 0.5 mrad/pixel
+    let selection = selection.with_min_angular_separation(2.0E-3, 0.5E-3);
+    assert!((selection.min_separation - 4.0).abs() < 1.0E-9);
+
+    let stars = frame
+        .with_selection(selection)
+        .select_stars::<6>(&mut bits)
+        .unwrap();
+    assert_eq!(stars.len(), 5);
+    assert_eq!(stars.dropped(), 1);
+}
+
+#[test]
+fn snr_ranking() {
+    let sky = sky();
+    let threshold = SigmaK::new(3.0);
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold)
+        .with_selection(Selection::new(Ranking::Snr, 0.0, 0.0));
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let stars = frame.select_stars::<6>(&mut bits).unwrap();
+    assert_eq!(stars.len(), 6);
+
+    for pair in stars.as_slice().windows(2) {
+        assert!(pair[0].snr >= pair[1].snr);
+    }
+
+    // deterministic
+    let again = frame.select_stars::<6>(&mut bits).unwrap();
+    assert_eq!(stars.as_slice(), again.as_slice());
+}
+
+#[test]
+fn tie_breaking() {
+    let star = |x, y, peak| {
+        let mut candidate = StarCandidate {
+            flux: 1000.0,
+            snr: 20.0,
+            peak,
+            ..Default::default()
+        };
+        candidate.centroid.coord = Coord { x, y };
+        candidate
+    };
+
+    let ranking = Ranking::Flux;
+    assert_eq!(
+        ranking.compare(&star(5.0, 5.0, 100), &star(5.0, 5.0, 200)),
+        Ordering::Greater
+    );
+    assert_eq!(
+        ranking.compare(&star(9.0, 5.0, 100), &star(1.0, 6.0, 100)),
+        Ordering::Less
+    );
+    assert_eq!(
+        ranking.compare(&star(1.0, 5.0, 100), &star(2.0, 5.0, 100)),
+        Ordering::Less
+    );
+}
@@ -0,0 +1,148 @@
+use celestial_nav::{
+    frame::{
+        background::{Background, Tile},
+        mask::Mask,
+        sky::{Horizon, SkySegmentation},
+    },
+    prelude::{BitMap, Frame, Gray8},
+};
+
+use geo::Coord;
+
+use crate::common::{noise, render};
+
+const WIDTH: u16 = 64;
+const HEIGHT: u16 = 32;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Builds a night sky with one star, terrain lights below y = 24,
+/// the Moon at (12, 6), and a lit cloud at the top right
+fn sky() -> [Gray8; XY] {
+    let mut sky = render(WIDTH, HEIGHT, |i, x, y| {
+        let cloud = if x >= 48.0 && y < 16.0 { 60.0 } else { 0.0 };
+        let moon = (x - 12.0).powi(2) + (y - 6.0).powi(2) <= 4.0;
+        if moon {
+            255.0
+        } else {
+            20.0 + noise(i) + cloud
+        }
+    });
+    sky[10 * WIDTH as usize + 30] = 200;
+    sky[28 * WIDTH as usize + 40] = 220;
+    sky[27 * WIDTH as usize + 8] = 230;
+    sky
+}
+
+#[test]
+fn horizon() {
+    let horizon = Horizon::new(Coord { x: 0.0, y: 24.0 }, Coord { x: 63.0, y: 24.0 }, 2.0);
+    assert!((horizon.elevation(5.0, 20.0) - 4.0).abs() < 1.0E-9);
+    assert!(horizon.elevation(5.0, 30.0) < 0.0);
+
+    // level camera looking up: the horizon lies f * tan(pitch) below the center
+    let attitude =
+        Horizon::from_attitude(0.1_f64.atan(), 0.0, 80.0, Coord { x: 32.0, y: 16.0 }, 0.0);
+    assert!(attitude.elevation(0.0, 23.9) > 0.0);
+    assert!(attitude.elevation(63.0, 24.1) < 0.0);
+
+    // rolled camera: the horizon tilts
+    let rolled = Horizon::from_attitude(0.0, 0.1, 80.0, Coord { x: 32.0, y: 16.0 }, 0.0);
+    assert!(rolled.elevation(0.0, 12.5) > 0.0);
+    assert!(rolled.elevation(0.0, 13.5) < 0.0);
+    assert!(rolled.elevation(63.0, 18.5) > 0.0);
+    assert!(rolled.elevation(63.0, 19.5) < 0.0);
+}
+
+#[test]
+fn sky_segmentation() {
+    let sky = sky();
+    let frame = Frame::new(64, 32, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+
+    let mut tiles = [Tile::default(); Background::tiles(WIDTH, HEIGHT, 16)];
+    let background = Background::estimate(&frame, 16, &mut tiles).unwrap();
+
+    let segmentation = SkySegmentation::default()
+        .with_horizon(Horizon::new(
+            Coord { x: 0.0, y: 24.0 },
+            Coord { x: 63.0, y: 24.0 },
+            2.0,
+        ))
+        .with_glare(250, 4)
+        .with_clouds(&background, 5.0);
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut exclusion = Mask::new(WIDTH, HEIGHT, &mut bits).unwrap();
+    let mut glare_bits = [0; 2 * Mask::words(WIDTH, HEIGHT)];
+    let count = segmentation
+        .segment(&frame, &mut exclusion, &mut glare_bits)
+        .unwrap();
+    assert_eq!(count, WIDTH as usize * HEIGHT as usize - exclusion.count());
+
+    // terrain
+    assert!(!exclusion.get(30, 10));
+    assert!(exclusion.get(30, 23));
+    assert!(exclusion.get(40, 28));
+
+    // Moon glare
+    assert!(exclusion.get(12, 6));
+    assert!(exclusion.get(15, 7));
+    assert!(exclusion.get(16, 6) && exclusion.get(12, 10));
+    assert!(!exclusion.get(20, 6));
+
+    // cloud
+    assert!(exclusion.get(56, 8));
+    assert!(!exclusion.get(24, 16));
+
+    // the glare storage is required
+    let mut short = [0; Mask::words(WIDTH, HEIGHT)];
+    assert!(segmentation
+        .segment(&frame, &mut exclusion, &mut short)
+        .is_err());
+
+    // the star finder ignores everything outside the sky
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let candidates = frame.star_candidates::<8>(&mut bits).unwrap();
+    assert!(candidates.len() > 1);
+
+    let candidates = frame
+        .with_exclusion_mask(&exclusion)
+        .star_candidates::<8>(&mut bits)
+        .unwrap();
+    assert_eq!(candidates.len(), 1);
+    let coord = candidates.as_slice()[0].centroid.coord;
+    assert!((coord.x - 30.0).abs() < 0.1 && (coord.y - 10.0).abs() < 0.1);
+}
+
+#[test]
+fn glare_halo() {
+    let mut sky = [20; XY];
+    sky[16 * WIDTH as usize + 32] = 255;
+    let frame = Frame::new(
+        64,
+        32,
+        BitMap::<XY, Gray8>::from_slice(WIDTH, HEIGHT, &sky).unwrap(),
+    )
+    .unwrap();
+    let segmentation = SkySegmentation::default().with_glare(250, 6);
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut exclusion = Mask::new(WIDTH, HEIGHT, &mut bits).unwrap();
+    let mut glare_bits = [0; 2 * Mask::words(WIDTH, HEIGHT)];
+    segmentation
+        .segment(&frame, &mut exclusion, &mut glare_bits)
+        .unwrap();
+
+    // octagon approximating the disc
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let r = (x as f64 - 32.0).hypot(y as f64 - 16.0);
+            if r <= 5.0 {
+                assert!(exclusion.get(x, y), "({}, {})", x, y);
+            } else if r > 6.0 * 1.12 {
+                assert!(!exclusion.get(x, y), "({}, {})", x, y);
+            }
+        }
+    }
+    assert!(exclusion.get(38, 16) && exclusion.get(32, 10));
+    assert!(!exclusion.get(39, 16));
+}
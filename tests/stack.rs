@@ -0,0 +1,228 @@
+use celestial_nav::{
+    frame::{
+        component::UnderlyingComponent,
+        mask::Mask,
+        stack::{Accumulator, Registration, Stack, Stacking},
+        threshold::AdaptiveLocal,
+    },
+    prelude::{BitMap, Frame, Gray16, Gray8, Rotation3, Solver},
+    VideoSource,
+};
+
+use geo::Coord;
+use nalgebra::Vector3;
+
+use crate::common::{lcg, render, star, BACKGROUND};
+
+const WIDTH: u16 = 48;
+const HEIGHT: u16 = 32;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Bright stars, in reference coordinates
+const BRIGHT: [(f64, f64); 3] = [(8.0, 6.0), (36.0, 10.0), (14.0, 24.0)];
+
+/// Faint star, in reference coordinates
+const FAINT: (f64, f64) = (30.0, 22.0);
+
+/// Builds a noisy sky, shifted by (dx, dy) with respect to the reference
+fn sky(seed: u32, dx: f64, dy: f64) -> [Gray16; XY] {
+    // sum of uniforms: roughly Gaussian, 7 LSB standard deviation
+    let mut state = seed;
+    let noise = core::array::from_fn::<_, XY, _>(|_| {
+        (0..4)
+            .map(|_| (lcg(&mut state) >> 16) as f64 / 65536.0 - 0.5)
+            .sum::<f64>()
+            * 12.0
+    });
+
+    render(WIDTH, HEIGHT, |i, x, y| {
+        let (x, y) = (x - dx, y - dy);
+        let mut value = BACKGROUND + noise[i] + star(x, y, (FAINT.0, FAINT.1, 20.0), 1.0);
+        for (sx, sy) in BRIGHT {
+            value += star(x, y, (sx, sy, 400.0), 1.0);
+        }
+        value
+    })
+}
+
+#[test]
+fn registration() {
+    let offset = Registration::from_translation(Coord { x: 2.0, y: -1.0 });
+    assert_eq!(
+        offset.map(Coord { x: 10.0, y: 10.0 }),
+        Some(Coord { x: 12.0, y: 9.0 })
+    );
+
+    // camera rotated around its y axis: the scene moves along x
+    let center = Coord { x: 24.0, y: 16.0 };
+    let reference = Rotation3::from_euler_angles(0.1, 0.2, 0.3);
+    let identity = Registration::from_attitude(&reference, &reference, 400.0, center);
+    let point = identity.map(Coord { x: 5.0, y: 7.0 }).unwrap();
+    assert!((point.x - 5.0).abs() < 1.0E-9 && (point.y - 7.0).abs() < 1.0E-9);
+
+    let angle = 0.01_f64;
+    let rotated = reference * Rotation3::from_axis_angle(&Vector3::y_axis(), angle);
+    let registration = Registration::from_attitude(&reference, &rotated, 400.0, center);
+    let point = registration.map(center).unwrap();
+    assert!((point.x - (24.0 - 400.0 * angle.tan())).abs() < 1.0E-9);
+    assert!((point.y - 16.0).abs() < 1.0E-9);
+}
+
+#[test]
+fn stacking() {
+    let offsets = [(0.0, 0.0), (1.0, 0.0), (0.0, 2.0), (2.0, 1.0)];
+    let mut skies = [[0; XY]; 4];
+    for (i, (sky, (dx, dy))) in skies.iter_mut().zip(offsets).enumerate() {
+        *sky = self::sky(i as u32 + 1, dx, dy);
+    }
+
+    // cosmic ray
+    skies[2][4 * WIDTH as usize + 24] = 4000;
+
+    let threshold = AdaptiveLocal::<8>::new(16, 5.0);
+    let frames = skies.each_ref().map(|sky| {
+        Frame::new(48, 32, BitMap::from_slice(WIDTH, HEIGHT, sky).unwrap())
+            .unwrap()
+            .with_threshold(&threshold)
+    });
+
+    // the faint star is lost in a single frame
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let candidates = frames[0].star_candidates::<8>(&mut bits).unwrap();
+    assert_eq!(candidates.len(), 3);
+
+    // star based registration
+    let reference = candidates;
+    let mut accumulators = [Accumulator::default(); XY];
+    let mut stack = Stack::new(&mut accumulators);
+    stack.push(&frames[0], Registration::identity()).unwrap();
+    for (frame, (dx, dy)) in frames.iter().zip(offsets).skip(1) {
+        let stars = frame.star_candidates::<8>(&mut bits).unwrap();
+        let registration =
+            Registration::from_stars(reference.as_slice(), stars.as_slice(), 1.0).unwrap();
+        let point = registration.map(Coord { x: 0.0, y: 0.0 }).unwrap();
+        assert!((point.x - dx).abs() < 0.05 && (point.y - dy).abs() < 0.05);
+        stack
+            .push(
+                frame,
+                Registration::from_translation(Coord { x: dx, y: dy }),
+            )
+            .unwrap();
+    }
+    assert_eq!(stack.len(), 4);
+
+    let mut buf: [Gray16; XY] = [0; XY];
+    let stacked = stack
+        .combine(&frames[3], &mut buf)
+        .unwrap()
+        .with_threshold(&threshold);
+
+    // cosmic ray rejected
+    assert!(stacked.get(24, 4).unwrap().abs_diff(100) < 10);
+
+    let candidates = stacked.star_candidates::<8>(&mut bits).unwrap();
+    assert_eq!(candidates.len(), 4);
+    let faint = candidates.as_slice()[3].centroid.coord;
+    assert!((faint.x - FAINT.0).abs() < 0.5 && (faint.y - FAINT.1).abs() < 0.5);
+}
+
+#[test]
+fn stacking_precision() {
+    // two uniform Gray8 frames, one LSB apart
+    let skies: [[Gray8; 16]; 2] = [[100; 16], [101; 16]];
+    let frames = skies.each_ref().map(|sky| {
+        Frame::new(4, 4, BitMap::from_slice(4, 4, sky).unwrap())
+            .unwrap()
+            .with_saturation_level(200)
+    });
+
+    let mut accumulators = [Accumulator::default(); 4];
+    let mut stack = Stack::new(&mut accumulators);
+    for frame in frames.iter() {
+        let roi = frame.roi(1, 2, 2, 2).unwrap();
+        stack.push(&roi, Registration::identity()).unwrap();
+    }
+
+    // the mean is rounded to the Gray8 resolution
+    let mut buf: [Gray8; 16] = [0; 16];
+    let stacked = stack.combine(&frames[1], &mut buf).unwrap();
+    assert_eq!(stacked.get(0, 0), Some(&101));
+    assert_eq!(stacked.saturation_level(), 200);
+
+    // and kept in a deeper format
+    let mut buf: [Gray16; 16] = [0; 16];
+    let stacked = stack.combine(&frames[1], &mut buf).unwrap();
+    assert_eq!(stacked.origin(), (1, 2));
+    assert_eq!((stacked.width(), stacked.height()), (2, 2));
+    assert_eq!(stacked.bitmap().bit_depth(), 16);
+    assert!(stacked.bitmap().iter().all(|p| *p == 25728));
+    assert_eq!(stacked.saturation_level(), 200 * 256);
+}
+
+/// Focal length of the [Sky] camera (in pixels)
+const FOCAL_LENGTH: f64 = 10_000.0;
+
+/// [VideoSource] panning along x, reusing a single buffer
+struct Sky {
+    offsets: [f64; 4],
+    index: usize,
+    buf: [UnderlyingComponent; XY],
+}
+
+/// Camera orientation of a [Sky] [Frame] shifted by dx (in pixels)
+fn orientation(dx: f64) -> Rotation3<f64> {
+    Rotation3::from_axis_angle(&Vector3::y_axis(), -(dx / FOCAL_LENGTH).atan())
+}
+
+impl VideoSource<XY> for Sky {
+    fn next(&mut self) -> Option<Frame<'_, XY>> {
+        let dx = self.offsets[self.index % 4];
+        let sky = sky(self.index as u32 + 1, dx, 0.0);
+        for (pixel, luma) in self.buf.iter_mut().zip(sky) {
+            *pixel = UnderlyingComponent::gray8((luma / 2).min(255) as u8);
+        }
+        self.index += 1;
+        Frame::new(48, 32, BitMap::from_slice(WIDTH, HEIGHT, &self.buf).ok()?).ok()
+    }
+}
+
+#[test]
+fn solver_stacking() {
+    let threshold = AdaptiveLocal::<8>::new(16, 5.0);
+    let offsets = [0.0, 1.0, 2.0, 0.5];
+    let sky = || Sky {
+        offsets,
+        index: 0,
+        buf: [UnderlyingComponent::gray8(0); XY],
+    };
+
+    // the faint star is lost in a single frame
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut solver = Solver::new(sky())
+        .with_mask_storage(&mut bits)
+        .with_threshold(&threshold);
+    solver.video_processing::<1>(Rotation3::identity());
+    assert_eq!(solver.stars().len(), 3);
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut accumulators = [Accumulator::default(); XY];
+    let mut buf = [UnderlyingComponent::gray8(0); XY];
+    let stacking = Stacking::new(4, FOCAL_LENGTH, Coord { x: 24.0, y: 16.0 });
+    let mut solver = Solver::new(sky())
+        .with_mask_storage(&mut bits)
+        .with_threshold(&threshold)
+        .with_stacking(stacking, &mut accumulators, &mut buf);
+
+    for dx in &offsets[..3] {
+        solver.video_processing::<1>(orientation(*dx));
+        assert!(solver.stars().is_empty());
+    }
+    solver.video_processing::<1>(orientation(offsets[3]));
+
+    let stars = solver.stars();
+    assert_eq!(stars.len(), 4);
+    assert!(stars.iter().any(|star| {
+        let coord = star.centroid.coord;
+        (coord.x - FAINT.0).abs() < 0.5 && (coord.y - FAINT.1).abs() < 0.5
+    }));
+}
@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use celestial_nav::{
-    frame::component::UnderlyingComponent,
+    frame::{blob::Connectivity, component::UnderlyingComponent, mask::Mask},
     prelude::{BitMap, Frame},
 };
 
@@ -35,14 +35,39 @@ fn test_stars_finder() {
     let mut map: [UnderlyingComponent; XY] = [UnderlyingComponent::Gray8(0); XY];
     load_gray_scale_bitmap(
         &mut map,
-        &format!("{}/img/sky1.jpg", env!("CARGO_MANIFEST_DIR")),
+        format!("{}/img/sky1.jpg", env!("CARGO_MANIFEST_DIR")),
     );
 
-    let bitmap = BitMap::from_slice(WIDTH as u16, HEIGHT as u16,&map);
-    let frame = Frame::new(WIDTH, HEIGHT, bitmap);
+    let bitmap = BitMap::from_slice(WIDTH as u16, HEIGHT as u16,&map).unwrap();
+    let frame = Frame::new(WIDTH, HEIGHT, bitmap).unwrap();
 
-    let coords = frame.star_coordinates_finder::<8>();
+    let mut bits = [0; Mask::words(WIDTH as u16, HEIGHT as u16)];
 
-    panic!("{:?}", coords);
-    
+    // the sky is dense: more components than the capacity
+    let blobs = frame
+        .find_blobs::<64>(&mut bits, Connectivity::Eight)
+        .unwrap();
+    assert_eq!(blobs.len(), 64);
+    assert!(blobs.overflow() > 0);
+
+    for blob in blobs.iter() {
+        let (x_min, y_min, x_max, y_max) = blob.bbox();
+        let centroid = blob.centroid();
+        assert!(centroid.x >= x_min as f64 && centroid.x <= x_max as f64);
+        assert!(centroid.y >= y_min as f64 && centroid.y <= y_max as f64);
+        assert!(blob.area() >= 1);
+    }
+
+    let centroids = frame.star_coordinates_finder::<8>(&mut bits).unwrap();
+    let coords = centroids.map(|c| c.expect("missing star").coord);
+
+    // brightest star
+    assert!((coords[0].x - 460.0).abs() < 0.5);
+    assert!((coords[0].y - 70.0).abs() < 0.5);
+
+    for (i, coord) in coords.iter().enumerate() {
+        assert!(coord.x > 0.0 && coord.x < WIDTH as f64);
+        assert!(coord.y > 0.0 && coord.y < HEIGHT as f64);
+        assert!(coords[..i].iter().all(|c| c != coord));
+    }
 }
@@ -0,0 +1,168 @@
+use celestial_nav::{
+    frame::{
+        blob::Connectivity,
+        mask::Mask,
+        streak::{Streak, StreakDetector},
+        threshold::SigmaK,
+    },
+    prelude::{BitMap, Frame, Gray16},
+};
+
+use geo::Coord;
+
+use crate::common::{noise, render, star, BACKGROUND, SIGMA};
+
+const WIDTH: u16 = 64;
+const HEIGHT: u16 = 48;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Satellite trail
+const TRAIL: Streak = Streak {
+    start: Coord { x: 6.0, y: 40.0 },
+    end: Coord { x: 54.0, y: 12.0 },
+    width: 0.0,
+    flux: 0.0,
+};
+
+/// Builds a sky with three stars and a satellite trail
+fn sky() -> [Gray16; XY] {
+    broken_sky(&[(0.0, 1.0)])
+}
+
+/// Builds a sky with three stars and a satellite trail, only visible
+/// over given (start, end) fractions of its length
+fn broken_sky(visible: &[(f64, f64)]) -> [Gray16; XY] {
+    render(WIDTH, HEIGHT, |i, x, y| {
+        let mut value = BACKGROUND + noise(i);
+        for (x0, y0) in [(12.0, 10.0), (50.0, 38.0), (32.0, 14.0)] {
+            value += star(x, y, (x0, y0, 2000.0), SIGMA);
+        }
+        // fraction of the trail length, at the foot of the perpendicular
+        let (dx, dy) = (TRAIL.end.x - TRAIL.start.x, TRAIL.end.y - TRAIL.start.y);
+        let t = ((x - TRAIL.start.x) * dx + (y - TRAIL.start.y) * dy) / (dx * dx + dy * dy);
+        let visible = visible.iter().any(|(start, end)| t >= *start && t <= *end);
+        let d = TRAIL.distance(Coord { x, y });
+        value + (visible as u8 as f64) * 800.0 * (-d * d / (2.0 * 0.7 * 0.7)).exp()
+    })
+}
+
+#[test]
+fn streak_detection() {
+    let sky = sky();
+    let threshold = SigmaK::new(1.0);
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold);
+    let detector = StreakDetector::default();
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let streaks = frame.find_streaks::<4>(&mut bits, &detector).unwrap();
+    assert_eq!(streaks.len(), 1);
+
+    let streak = streaks.as_slice()[0];
+    assert!(
+        (streak.length() - TRAIL.length()).abs() < 5.0,
+        "{:?}",
+        streak
+    );
+    assert!((streak.angle().tan() - TRAIL.angle().tan()).abs() < 0.05);
+    assert!(streak.width < 4.0);
+    assert!(TRAIL.distance(streak.start) < 1.0 && TRAIL.distance(streak.end) < 1.0);
+
+    // the star candidates along the trail are rejected
+    let candidates = frame.star_candidates::<8>(&mut bits).unwrap();
+    assert_eq!(candidates.len(), 4);
+
+    let candidates = frame
+        .with_streak_rejection(detector)
+        .star_candidates::<8>(&mut bits)
+        .unwrap();
+    assert_eq!(candidates.len(), 3);
+    assert!(candidates
+        .iter()
+        .all(|c| TRAIL.distance(c.centroid.coord) > 5.0));
+
+    // the trail does not take up a candidate slot
+    let candidates = frame
+        .with_streak_rejection(detector)
+        .star_candidates::<3>(&mut bits)
+        .unwrap();
+    assert_eq!(candidates.len(), 3);
+    assert!(candidates
+        .iter()
+        .all(|c| TRAIL.distance(c.centroid.coord) > 5.0));
+}
+
+#[test]
+fn broken_streak() {
+    let sky = broken_sky(&[(0.0, 0.3), (0.4, 0.65), (0.75, 1.0)]);
+    let threshold = SigmaK::new(1.0);
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold);
+    let detector = StreakDetector::default();
+
+    // each piece is too short on its own
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let blobs = frame
+        .find_blobs::<8>(&mut bits, Connectivity::default())
+        .unwrap();
+    assert_eq!(blobs.len(), 6);
+    assert!(blobs.iter().all(|blob| detector.classify(blob).is_none()));
+
+    // linked pieces
+    let streaks = frame.find_streaks::<4>(&mut bits, &detector).unwrap();
+    assert_eq!(streaks.len(), 1);
+
+    let streak = streaks.as_slice()[0];
+    assert!(
+        (streak.length() - TRAIL.length()).abs() < 5.0,
+        "{:?}",
+        streak
+    );
+    assert!(TRAIL.distance(streak.start) < 1.0 && TRAIL.distance(streak.end) < 1.0);
+
+    let candidates = frame
+        .with_streak_rejection(detector)
+        .star_candidates::<8>(&mut bits)
+        .unwrap();
+    assert_eq!(candidates.len(), 3);
+
+    // without angular tolerance, the pieces are not linked
+    let streaks = frame
+        .find_streaks::<4>(&mut bits, &detector.with_alignment(0.0))
+        .unwrap();
+    assert!(streaks.is_empty());
+}
+
+#[test]
+fn streak_masking() {
+    let sky = sky();
+    let threshold = SigmaK::new(1.0);
+    let frame = Frame::new(64, 48, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap())
+        .unwrap()
+        .with_threshold(&threshold);
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let streaks = frame
+        .find_streaks::<4>(&mut bits, &StreakDetector::default())
+        .unwrap();
+
+    let mut exclusion_bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut exclusion = Mask::new(WIDTH, HEIGHT, &mut exclusion_bits).unwrap();
+    streaks.mask(&frame, &mut exclusion, 1.0);
+    assert!(exclusion.get(30, 26));
+    assert!(!exclusion.get(12, 10));
+
+    let frame = frame.with_exclusion_mask(&exclusion);
+    let candidates = frame.star_candidates::<8>(&mut bits).unwrap();
+    assert_eq!(candidates.len(), 3);
+
+    // region of interest: sensor coordinates
+    let roi = frame.roi(20, 4, 24, 24).unwrap();
+    let mut bits = [0; Mask::words(24, 24)];
+    let candidates = roi.star_candidates::<8>(&mut bits).unwrap();
+    assert_eq!(candidates.len(), 1);
+    let coord = candidates.as_slice()[0].centroid.coord;
+    assert!((coord.x - 12.0).abs() < 0.2 && (coord.y - 10.0).abs() < 0.2);
+}
@@ -0,0 +1,149 @@
+use celestial_nav::{
+    frame::{
+        component::UnderlyingComponent,
+        mask::Mask,
+        threshold::{AdaptiveLocal, Otsu, Percentile, SigmaK, Threshold},
+    },
+    prelude::{BitMap, Frame, Gray8, Rotation3, Solver},
+    Error, VideoSource,
+};
+
+use crate::common::render;
+
+const WIDTH: u16 = 32;
+const HEIGHT: u16 = 32;
+const XY: usize = WIDTH as usize * HEIGHT as usize;
+
+/// Builds a sky with a horizontal gradient and two stars
+fn sky() -> [Gray8; XY] {
+    let mut sky = render(WIDTH, HEIGHT, |i, x, _| 10.0 + 2.0 * x + (i % 3) as f64);
+    sky[4 * WIDTH as usize + 4] = 100;
+    sky[20 * WIDTH as usize + 28] = 250;
+    sky
+}
+
+/// Custom strategy: flags pixels above a fixed luminance
+struct Fixed(u16);
+
+impl<const XY: usize> Threshold<XY, Gray8> for Fixed {
+    fn detect(&self, frame: &Frame<'_, XY, Gray8>, mask: &mut Mask<'_>) -> Result<usize, Error> {
+        let mut count = 0;
+        for y in 0..frame.height() {
+            for x in 0..frame.width() {
+                let detected = *frame.get(x, y).unwrap() as u16 > self.0;
+                mask.set(x, y, detected);
+                count += detected as usize;
+            }
+        }
+        Ok(count)
+    }
+}
+
+#[test]
+fn threshold_strategies() {
+    let sky = sky();
+    let frame = Frame::new(32, 32, BitMap::from_slice(WIDTH, HEIGHT, &sky).unwrap()).unwrap();
+
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut mask = Mask::new(WIDTH, HEIGHT, &mut bits).unwrap();
+
+    // default global 5 sigma only detects the brightest star
+    assert_eq!(frame.detect(&mut mask).unwrap(), 1);
+    assert!(mask.get(28, 20));
+
+    // sigma-k
+    let sigma_k = SigmaK::new(2.0);
+    assert_eq!(SigmaK::default().k, 5.0);
+    let count = frame.with_threshold(&sigma_k).detect(&mut mask).unwrap();
+    assert!(count > 1);
+
+    // percentile
+    let percentile = Percentile::new(0.99);
+    let count = frame.with_threshold(&percentile).detect(&mut mask).unwrap();
+    assert!(count > 0 && count <= 11, "{}", count);
+    assert!(mask.get(28, 20));
+
+    // otsu splits the gradient
+    let count = frame.with_threshold(&Otsu).detect(&mut mask).unwrap();
+    assert!(count > 100);
+
+    // local background detects both stars, and only them
+    let adaptive = AdaptiveLocal::<16>::new(8, 5.0);
+    assert_eq!(
+        frame.with_threshold(&adaptive).detect(&mut mask).unwrap(),
+        2
+    );
+    assert!(mask.get(4, 4));
+    assert!(mask.get(28, 20));
+
+    // also on regions of interest
+    let roi = frame.roi(16, 16, 16, 16).unwrap();
+    let mut bits = [0; Mask::words(16, 16)];
+    let mut roi_mask = Mask::new(16, 16, &mut bits).unwrap();
+    assert_eq!(
+        roi.with_threshold(&adaptive).detect(&mut roi_mask).unwrap(),
+        1
+    );
+    assert!(roi_mask.get(12, 4));
+
+    // not enough tiles
+    let adaptive = AdaptiveLocal::<4>::new(8, 5.0);
+    assert!(frame.with_threshold(&adaptive).detect(&mut mask).is_err());
+
+    // custom strategy
+    let fixed = Fixed(99);
+    assert_eq!(frame.with_threshold(&fixed).detect(&mut mask).unwrap(), 2);
+
+    // mask dimensions mismatch
+    let mut bits = [0; Mask::words(8, 8)];
+    let mut mask = Mask::new(8, 8, &mut bits).unwrap();
+    assert!(frame.detect(&mut mask).is_err());
+}
+
+/// [VideoSource] capturing the gradient sky
+struct Gradient([UnderlyingComponent; XY]);
+
+impl VideoSource<XY> for Gradient {
+    fn next(&mut self) -> Option<Frame<'_, XY>> {
+        Frame::new(32, 32, BitMap::from_slice(WIDTH, HEIGHT, &self.0).ok()?).ok()
+    }
+}
+
+#[test]
+fn solver_threshold() {
+    let sky = sky().map(UnderlyingComponent::gray8);
+
+    // default global 5 sigma only detects the brightest star
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut solver = Solver::new_fixed_body_camera(Gradient(sky), Rotation3::identity())
+        .with_mask_storage(&mut bits);
+    solver.video_processing::<1>(Rotation3::identity());
+    assert_eq!(solver.stars().len(), 1);
+
+    // the local strategy follows the gradient
+    let adaptive = AdaptiveLocal::<16>::new(8, 5.0);
+    let mut bits = [0; Mask::words(WIDTH, HEIGHT)];
+    let mut solver = Solver::new_fixed_body_camera(Gradient(sky), Rotation3::identity())
+        .with_mask_storage(&mut bits)
+        .with_threshold(&adaptive);
+    solver.video_processing::<1>(Rotation3::identity());
+
+    let stars = solver.stars();
+    assert_eq!(stars.len(), 2);
+    let faint = stars.as_slice()[1].centroid.coord;
+    assert!((faint.x - 4.0).abs() < 0.5 && (faint.y - 4.0).abs() < 0.5);
+    assert_eq!(solver.error(), None);
+
+    // undersized detection mask storage is reported
+    let mut bits = [0; 4];
+    let mut solver = Solver::new(Gradient(sky)).with_mask_storage(&mut bits);
+    solver.video_processing::<1>(Rotation3::identity());
+    assert_eq!(solver.error(), Some(Error::VideoDimensionError));
+    assert!(solver.stars().is_empty());
+
+    // so is a missing one
+    let mut solver = Solver::new(Gradient(sky));
+    solver.video_processing::<1>(Rotation3::identity());
+    assert_eq!(solver.error(), Some(Error::VideoDimensionError));
+    assert!(solver.stars().is_empty());
+}